@@ -0,0 +1,180 @@
+//! 設定の検証結果（診断）。
+//!
+//! GUI での該当エントリの強調表示や、CLI での JSON 出力に使えるよう、
+//! 種類・フィールドのパス・重大度・修正案を持つ。
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// 重大度。Error があると保存できない。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 診断の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// 読み込めない config.toml（TOML の構文エラー、型や値の誤り）
+    ParseError,
+    EmptyTimestampFormat,
+    /// 無効な指定子、またはファイル名に使えない文字を出力するフォーマット
+    InvalidTimestampFormat,
+    InvalidDelimiter,
+    /// trigger.tap_timeout / hold_timeout が範囲外
+    InvalidTriggerTimeout,
+    /// layout の配列にないトリガーキー（警告）
+    TriggerKeyNotOnLayout,
+    UnknownActiveProfile,
+    MissingQueryPlaceholder,
+    InvalidUrl,
+    /// 存在しないフォルダ（警告）
+    MissingFolder,
+    EmptyCommand,
+    EmptySnippet,
+    DuplicateKey,
+    /// 記号を割り当てられないキー（[symbols]）
+    InvalidSymbolKey,
+    /// 編集操作を割り当てられないキー（[editing]）
+    InvalidEditingKey,
+    /// kbd で入力できない記号（空白・制御文字など）
+    InvalidSymbol,
+    EmptyOverrideProcess,
+    UnknownOverrideTarget,
+    /// trigger.double_tap に指定したエントリがない
+    UnknownDoubleTapTarget,
+    /// config.toml の不明なキー（警告）
+    UnknownKey,
+}
+
+/// 検証で見つかった問題1件。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    /// TOML のフィールドパス（例: `search."Google".url`）
+    pub path: String,
+    pub message: String,
+    /// 修正案
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// 問題のあるファイル（読み込んだファイルを検査した場合）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// 行番号（1 始まり）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn error(kind: DiagnosticKind, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity: Severity::Error,
+            path: path.into(),
+            message: message.into(),
+            suggestion: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    pub fn warning(
+        kind: DiagnosticKind,
+        path: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(kind, path, message)
+        }
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "（{}）", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// 診断に Error が含まれるか。
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(Diagnostic::is_error)
+}
+
+/// TOML のキーを引用符付きで表記する（例: `"Google"`）。
+/// GUI 側で解析しやすいよう、常に基本文字列（"..."）で表記する。
+pub fn quote_key(name: &str) -> String {
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('"');
+    for c in name.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// エントリのフィールドのパス（例: `search."Google".url`）。
+pub fn entry_path(section: &str, name: &str, field: &str) -> String {
+    format!("{}.{}.{}", section, quote_key(name), field)
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_path_quotes_name() {
+        assert_eq!(entry_path("search", "Google", "url"), r#"search."Google".url"#);
+        assert_eq!(
+            entry_path("profiles.\"仕事\".folders", "議\"事録", "key"),
+            r#"profiles."仕事".folders."議\"事録".key"#
+        );
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let diagnostic = Diagnostic::error(
+            DiagnosticKind::MissingQueryPlaceholder,
+            entry_path("search", "Google", "url"),
+            "URL に {query} がありません",
+        )
+        .with_suggestion("検索語の位置に {query} を入れてください");
+        let json = serde_json::to_value(&diagnostic).unwrap();
+        assert_eq!(json["kind"], "missing_query_placeholder");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["path"], r#"search."Google".url"#);
+        assert!(json["suggestion"].is_string());
+    }
+
+    #[test]
+    fn test_display_includes_suggestion() {
+        let diagnostic = Diagnostic::warning(DiagnosticKind::EmptyCommand, "commands", "空です")
+            .with_suggestion("削除してください");
+        assert_eq!(diagnostic.to_string(), "空です（削除してください）");
+        assert!(!has_errors(&[diagnostic]));
+    }
+}
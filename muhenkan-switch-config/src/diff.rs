@@ -0,0 +1,439 @@
+//! 2つの設定の差分と、インポート時の取り込み（マージ）。
+//!
+//! 差分はエントリ単位（検索エンジン・フォルダなど名前ごと）で、
+//! 別々のエントリが同じ割当キーを使っている箇所を衝突として報告する。
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostic::quote_key;
+use crate::{keyed, Config, KeyedEntry, Modifier};
+
+/// 差分の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// 取り込む側にだけある
+    Added,
+    /// 今の設定にだけある
+    Removed,
+    /// 両方にあって内容が異なる
+    Changed,
+}
+
+/// エントリまたは設定値の差分。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntryChange {
+    /// TOML のセクション（"search", "profiles.\"仕事\".folders", "overrides" など。トップレベルは ""）
+    pub section: String,
+    /// エントリ名（overrides はプロセス名、設定値はフィールド名）
+    pub name: String,
+    pub change: Change,
+}
+
+/// 今の設定と取り込む側で、別々のエントリが同じ割当キーを使っている。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyConflict {
+    /// 割当キーの表記（"g", "Shift+g"）
+    pub key: String,
+    /// 今の設定のエントリ（"search/Google" の形式）
+    pub mine: String,
+    /// 取り込む側のエントリ
+    pub theirs: String,
+}
+
+/// 2つの設定の差分。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigDiff {
+    pub changes: Vec<EntryChange>,
+    /// トップレベル（標準）の割当キーの衝突
+    pub conflicts: Vec<KeyConflict>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// インポートの方法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// 取り込む側で丸ごと置き換える
+    Replace,
+    /// 足りないエントリだけ取り込み、同名のエントリ・衝突する割当キーは今の設定を残す
+    KeepMine,
+    /// エントリを取り込み、同名のエントリ・衝突する割当キーは取り込む側を優先する
+    TheirsWin,
+}
+
+/// 今の設定（mine）から取り込む側（theirs）への差分を返す。
+pub fn diff(mine: &Config, theirs: &Config) -> ConfigDiff {
+    let mut changes = Vec::new();
+    diff_entries("search", &mine.search, &theirs.search, &mut changes);
+    diff_entries("folders", &mine.folders, &theirs.folders, &mut changes);
+    diff_entries("apps", &mine.apps, &theirs.apps, &mut changes);
+    diff_entries("commands", &mine.commands, &theirs.commands, &mut changes);
+    diff_entries("snippets", &mine.snippets, &theirs.snippets, &mut changes);
+    diff_entries("symbols", &mine.symbols, &theirs.symbols, &mut changes);
+    diff_entries("editing.keys", &mine.editing.keys, &theirs.editing.keys, &mut changes);
+    diff_entries(
+        "overrides",
+        &overrides_by_process(mine),
+        &overrides_by_process(theirs),
+        &mut changes,
+    );
+
+    for (name, profile) in &theirs.profiles {
+        let Some(current) = mine.profiles.get(name) else {
+            changes.push(change("profiles", name, Change::Added));
+            continue;
+        };
+        let section = format!("profiles.{}", quote_key(name));
+        diff_optional(&section, "search", &current.search, &profile.search, &mut changes);
+        diff_optional(&section, "folders", &current.folders, &profile.folders, &mut changes);
+        diff_optional(&section, "apps", &current.apps, &profile.apps, &mut changes);
+    }
+    for name in mine.profiles.keys() {
+        if !theirs.profiles.contains_key(name) {
+            changes.push(change("profiles", name, Change::Removed));
+        }
+    }
+
+    let settings = [
+        ("", "active_profile", mine.active_profile != theirs.active_profile),
+        ("", "punctuation_style", mine.punctuation_style != theirs.punctuation_style),
+        ("", "layout", mine.layout != theirs.layout),
+        ("editing", "preset", mine.editing.preset != theirs.editing.preset),
+        ("extra_layers", "mouse", mine.extra_layers.mouse != theirs.extra_layers.mouse),
+        ("extra_layers", "numpad", mine.extra_layers.numpad != theirs.extra_layers.numpad),
+        ("timestamp", "format", mine.timestamp.format != theirs.timestamp.format),
+        ("timestamp", "position", mine.timestamp.position != theirs.timestamp.position),
+        ("timestamp", "delimiter", mine.timestamp.delimiter != theirs.timestamp.delimiter),
+        ("trigger", "key", mine.trigger.key != theirs.trigger.key),
+        ("trigger", "tap_timeout", mine.trigger.tap_timeout != theirs.trigger.tap_timeout),
+        ("trigger", "hold_timeout", mine.trigger.hold_timeout != theirs.trigger.hold_timeout),
+        ("trigger", "behavior", mine.trigger.behavior != theirs.trigger.behavior),
+        ("trigger", "double_tap", mine.trigger.double_tap != theirs.trigger.double_tap),
+    ];
+    for (section, name, differs) in settings {
+        if differs {
+            changes.push(change(section, name, Change::Changed));
+        }
+    }
+
+    let conflicts = conflicting_bindings(mine, theirs)
+        .into_iter()
+        .map(|c| KeyConflict {
+            key: c.modifier.describe(&c.key),
+            mine: format!("{}/{}", c.mine.0, c.mine.1),
+            theirs: format!("{}/{}", c.theirs.0, c.theirs.1),
+        })
+        .collect();
+
+    ConfigDiff { changes, conflicts }
+}
+
+/// 取り込む側を今の設定に取り込んだ設定を返す。
+/// マージでは選択中のプロファイルは今の設定のものを残す。
+/// 割当キーが衝突したエントリは、優先しない側の割当キーを外して取り込む。
+pub fn merge(mine: &Config, theirs: &Config, strategy: MergeStrategy) -> Config {
+    if strategy == MergeStrategy::Replace {
+        return theirs.clone();
+    }
+    let theirs_win = strategy == MergeStrategy::TheirsWin;
+    let mut merged = mine.clone();
+    merge_entries(&mut merged.search, &theirs.search, theirs_win);
+    merge_entries(&mut merged.folders, &theirs.folders, theirs_win);
+    merge_entries(&mut merged.apps, &theirs.apps, theirs_win);
+    merge_entries(&mut merged.commands, &theirs.commands, theirs_win);
+    merge_entries(&mut merged.snippets, &theirs.snippets, theirs_win);
+    merge_entries(&mut merged.profiles, &theirs.profiles, theirs_win);
+    merge_entries(&mut merged.symbols, &theirs.symbols, theirs_win);
+    merge_entries(&mut merged.editing.keys, &theirs.editing.keys, theirs_win);
+    for entry in &theirs.overrides {
+        match merged.overrides.iter_mut().find(|o| o.process == entry.process) {
+            None => merged.overrides.push(entry.clone()),
+            Some(current) if theirs_win => *current = entry.clone(),
+            Some(_) => {}
+        }
+    }
+    if theirs_win {
+        merged.punctuation_style = theirs.punctuation_style;
+        merged.layout = theirs.layout;
+        merged.editing.preset = theirs.editing.preset;
+        merged.extra_layers = theirs.extra_layers.clone();
+        merged.timestamp = theirs.timestamp.clone();
+        merged.trigger = theirs.trigger.clone();
+    }
+
+    for conflict in conflicting_bindings(mine, theirs) {
+        let (section, name) = if theirs_win { &conflict.mine } else { &conflict.theirs };
+        clear_key(&mut merged, section, name, conflict.modifier, &conflict.key);
+    }
+    merged
+}
+
+fn change(section: &str, name: &str, change: Change) -> EntryChange {
+    EntryChange {
+        section: section.to_string(),
+        name: name.to_string(),
+        change,
+    }
+}
+
+fn diff_entries<E: PartialEq>(
+    section: &str,
+    mine: &IndexMap<String, E>,
+    theirs: &IndexMap<String, E>,
+    out: &mut Vec<EntryChange>,
+) {
+    for (name, entry) in theirs {
+        match mine.get(name) {
+            None => out.push(change(section, name, Change::Added)),
+            Some(current) if current != entry => out.push(change(section, name, Change::Changed)),
+            Some(_) => {}
+        }
+    }
+    for name in mine.keys() {
+        if !theirs.contains_key(name) {
+            out.push(change(section, name, Change::Removed));
+        }
+    }
+}
+
+/// プロファイルのセクション。片方にしか無ければセクションごとの差分にする。
+fn diff_optional<E: PartialEq>(
+    profile: &str,
+    section: &str,
+    mine: &Option<IndexMap<String, E>>,
+    theirs: &Option<IndexMap<String, E>>,
+    out: &mut Vec<EntryChange>,
+) {
+    match (mine, theirs) {
+        (Some(mine), Some(theirs)) => {
+            diff_entries(&format!("{}.{}", profile, section), mine, theirs, out)
+        }
+        (None, Some(_)) => out.push(change(profile, section, Change::Added)),
+        (Some(_), None) => out.push(change(profile, section, Change::Removed)),
+        (None, None) => {}
+    }
+}
+
+fn overrides_by_process(config: &Config) -> IndexMap<String, &crate::OverrideEntry> {
+    config
+        .overrides
+        .iter()
+        .map(|o| (o.process.clone(), o))
+        .collect()
+}
+
+fn merge_entries<E: Clone>(
+    mine: &mut IndexMap<String, E>,
+    theirs: &IndexMap<String, E>,
+    theirs_win: bool,
+) {
+    for (name, entry) in theirs {
+        match mine.get_mut(name) {
+            None => {
+                mine.insert(name.clone(), entry.clone());
+            }
+            Some(current) if theirs_win => *current = entry.clone(),
+            Some(_) => {}
+        }
+    }
+}
+
+/// 割当キーの衝突（エントリは (セクション, 名前)）。
+struct Conflict {
+    modifier: Modifier,
+    key: String,
+    mine: (&'static str, String),
+    theirs: (&'static str, String),
+}
+
+/// トップレベルの割当キーを (修飾キー, キー, セクション, 名前) で列挙する。
+fn bindings(config: &Config) -> Vec<(Modifier, &str, &'static str, &str)> {
+    let mut out = Vec::new();
+    for modifier in Modifier::ALL {
+        let sections = [
+            ("search", keyed(&config.search, modifier)),
+            ("folders", keyed(&config.folders, modifier)),
+            ("apps", keyed(&config.apps, modifier)),
+            ("commands", keyed(&config.commands, modifier)),
+            ("snippets", keyed(&config.snippets, modifier)),
+        ];
+        for (section, entries) in sections {
+            for (name, k) in entries {
+                out.push((modifier, k, section, name));
+            }
+        }
+    }
+    out
+}
+
+fn conflicting_bindings(mine: &Config, theirs: &Config) -> Vec<Conflict> {
+    let mine_bindings = bindings(mine);
+    let mut out = Vec::new();
+    for (modifier, k, section, name) in bindings(theirs) {
+        let found = mine_bindings
+            .iter()
+            .find(|(m, mk, _, _)| *m == modifier && *mk == k);
+        if let Some((_, _, mine_section, mine_name)) = found {
+            if (*mine_section, *mine_name) != (section, name) {
+                out.push(Conflict {
+                    modifier,
+                    key: k.to_string(),
+                    mine: (mine_section, mine_name.to_string()),
+                    theirs: (section, name.to_string()),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// エントリの割当キーが key のままなら外す。
+fn clear_key(config: &mut Config, section: &str, name: &str, modifier: Modifier, key: &str) {
+    let slot = match section {
+        "search" => config.search.get_mut(name).map(|e| e.key_slot_mut(modifier)),
+        "folders" => config.folders.get_mut(name).map(|e| e.key_slot_mut(modifier)),
+        "apps" => config.apps.get_mut(name).map(|e| e.key_slot_mut(modifier)),
+        "commands" => config.commands.get_mut(name).map(|e| e.key_slot_mut(modifier)),
+        "snippets" => config.snippets.get_mut(name).map(|e| e.key_slot_mut(modifier)),
+        _ => None,
+    };
+    if let Some(slot) = slot.filter(|s| s.as_ref().is_some_and(|k| k == key)) {
+        *slot = None;
+    }
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_config, FolderEntry, PunctuationStyle, SearchEntry, TimestampPosition};
+
+    fn search(key: &str, url: &str) -> SearchEntry {
+        SearchEntry {
+            key: Some(key.parse().unwrap()),
+            shift_key: None,
+            url: url.to_string(),
+        }
+    }
+
+    fn empty_config() -> Config {
+        let mut config = default_config();
+        config.search.clear();
+        config.folders.clear();
+        config.apps.clear();
+        config.commands.clear();
+        config.snippets.clear();
+        config.overrides.clear();
+        config.profiles.clear();
+        config
+    }
+
+    /// 今の設定と、検索エンジンを共有したい人の設定。
+    fn configs() -> (Config, Config) {
+        let mut mine = empty_config();
+        mine.search.insert("Google".into(), search("g", "https://www.google.com/search?q={query}"));
+        mine.search.insert("辞書".into(), search("1", "https://dictionary.goo.ne.jp/srch/all/{query}/m0u/"));
+        mine.folders.insert(
+            "作業".into(),
+            FolderEntry {
+                key: Some("2".parse().unwrap()),
+                shift_key: None,
+                path: "~/work".into(),
+            },
+        );
+
+        let mut theirs = empty_config();
+        theirs.search.insert("Google".into(), search("g", "https://www.google.co.jp/search?q={query}"));
+        theirs.search.insert("MDN".into(), search("2", "https://developer.mozilla.org/search?q={query}"));
+        (mine, theirs)
+    }
+
+    #[test]
+    fn test_diff_entries() {
+        let (mine, theirs) = configs();
+        let d = diff(&mine, &theirs);
+        assert!(d.changes.contains(&change("search", "Google", Change::Changed)));
+        assert!(d.changes.contains(&change("search", "MDN", Change::Added)));
+        assert!(d.changes.contains(&change("search", "辞書", Change::Removed)));
+        assert!(d.changes.contains(&change("folders", "作業", Change::Removed)));
+        assert_eq!(
+            d.conflicts,
+            [KeyConflict {
+                key: "2".into(),
+                mine: "folders/作業".into(),
+                theirs: "search/MDN".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_identical_is_empty() {
+        let config = default_config();
+        assert!(diff(&config, &config).is_empty());
+    }
+
+    #[test]
+    fn test_diff_profiles_and_settings() {
+        let mine = default_config();
+        let mut theirs = default_config();
+        theirs.timestamp.position = TimestampPosition::After;
+        theirs.profiles.insert("仕事".into(), Default::default());
+        let d = diff(&mine, &theirs);
+        assert_eq!(
+            d.changes,
+            [
+                change("profiles", "仕事", Change::Added),
+                change("timestamp", "position", Change::Changed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_keep_mine() {
+        let (mine, theirs) = configs();
+        let merged = merge(&mine, &theirs, MergeStrategy::KeepMine);
+        assert_eq!(merged.search["Google"], mine.search["Google"]);
+        assert!(merged.search.contains_key("辞書"));
+        assert_eq!(merged.folders["作業"].dispatch_key(), Some("2"));
+        // 衝突した割当キーは取り込む側から外す
+        assert_eq!(merged.search["MDN"].key, None);
+        assert_eq!(merged.search["MDN"].url, theirs.search["MDN"].url);
+        assert!(!crate::has_errors(&crate::validate(&merged)));
+    }
+
+    #[test]
+    fn test_merge_theirs_win() {
+        let (mine, mut theirs) = configs();
+        theirs.punctuation_style = PunctuationStyle::CommaPeriod;
+        let merged = merge(&mine, &theirs, MergeStrategy::TheirsWin);
+        assert_eq!(merged.search["Google"], theirs.search["Google"]);
+        assert!(merged.search.contains_key("辞書"));
+        assert_eq!(merged.search["MDN"].dispatch_key(), Some("2"));
+        assert_eq!(merged.folders["作業"].key, None);
+        assert_eq!(merged.folders["作業"].path, "~/work");
+        assert_eq!(merged.punctuation_style, PunctuationStyle::CommaPeriod);
+        assert!(!crate::has_errors(&crate::validate(&merged)));
+    }
+
+    #[test]
+    fn test_merge_replace() {
+        let (mine, theirs) = configs();
+        let merged = merge(&mine, &theirs, MergeStrategy::Replace);
+        assert!(diff(&merged, &theirs).is_empty());
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        let strategy: MergeStrategy = serde_json::from_str("\"theirs_win\"").unwrap();
+        assert_eq!(strategy, MergeStrategy::TheirsWin);
+        assert_eq!(serde_json::to_string(&MergeStrategy::KeepMine).unwrap(), "\"keep_mine\"");
+    }
+}
@@ -0,0 +1,120 @@
+//! 無変換レイヤーから切り替える追加レイヤー（マウス・テンキー）。
+//!
+//! kbd の deflayer と SVG の図はどちらもこの表から作る。
+//! 無変換+切替キーで固定され、Esc または同じ切替キーで元のレイヤーに戻る。
+
+use crate::Config;
+
+/// 追加レイヤーの定義。
+#[derive(Debug)]
+pub struct ExtraLayer {
+    /// kanata のレイヤー名
+    pub name: &'static str,
+    /// 表示名（SVG の図のタイトル）
+    pub title: &'static str,
+    /// 無変換レイヤーでこのレイヤーに切り替えるキー（kanata のキー名）
+    pub entry: &'static str,
+    /// 切替キーの表示名
+    pub entry_label: &'static str,
+    /// キー名（keys::KEYS。kanata のキー名と同じ英字と , . / ; だけを使う）
+    /// → (kanata のアクション, 表示用ラベル)
+    pub keys: &'static [(&'static str, &'static str, &'static str)],
+}
+
+impl ExtraLayer {
+    /// キーに割り当てたアクションと表示用ラベル。
+    pub fn action(&self, key: &str) -> Option<(&'static str, &'static str)> {
+        self.keys
+            .iter()
+            .find(|(k, _, _)| *k == key)
+            .map(|(_, action, label)| (*action, *label))
+    }
+}
+
+/// 元のレイヤーに戻るキー（切替キーでも戻れる）。
+pub const EXIT_KEY: &str = "esc";
+
+/// マウス操作レイヤー（無変換+Space）。右手でポインタ移動・クリック・スクロール。
+pub const MOUSE: ExtraLayer = ExtraLayer {
+    name: "mouse-layer",
+    title: "マウス",
+    entry: "spc",
+    entry_label: "Space",
+    keys: &[
+        ("y", "(mwheel-up 50 120)", "ホイール↑"),
+        ("u", "mlft", "左クリック"),
+        ("i", "mmid", "中クリック"),
+        ("o", "mrgt", "右クリック"),
+        ("h", "(movemouse-left 4 4)", "←"),
+        ("j", "(movemouse-down 4 4)", "↓"),
+        ("k", "(movemouse-up 4 4)", "↑"),
+        ("l", "(movemouse-right 4 4)", "→"),
+        ("n", "(mwheel-down 50 120)", "ホイール↓"),
+    ],
+};
+
+/// テンキーレイヤー（無変換+Tab）。右手の U/I/O・J/K/L・M/,/. を 7〜9・4〜6・1〜3 に。
+pub const NUMPAD: ExtraLayer = ExtraLayer {
+    name: "numpad-layer",
+    title: "テンキー",
+    entry: "tab",
+    entry_label: "Tab",
+    keys: &[
+        ("u", "7", "7"),
+        ("i", "8", "8"),
+        ("o", "9", "9"),
+        ("h", "bspc", "BS"),
+        ("j", "4", "4"),
+        ("k", "5", "5"),
+        ("l", "6", "6"),
+        (";", "ret", "Enter"),
+        ("n", "0", "0"),
+        ("m", "1", "1"),
+        (",", "2", "2"),
+        (".", "3", "3"),
+        ("/", ".", "."),
+    ],
+};
+
+/// config で有効にした追加レイヤー。
+pub fn enabled(config: &Config) -> Vec<&'static ExtraLayer> {
+    let mut layers = Vec::new();
+    if config.extra_layers.mouse {
+        layers.push(&MOUSE);
+    }
+    if config.extra_layers.numpad {
+        layers.push(&NUMPAD);
+    }
+    layers
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_config, keys, Layout};
+
+    #[test]
+    fn test_keys_exist_on_both_layouts() {
+        for layer in [&MOUSE, &NUMPAD] {
+            for (key, _, _) in layer.keys {
+                for layout in Layout::ALL {
+                    let k = keys::layout_keys(layout).find(|k| k.name == *key);
+                    assert_eq!(k.map(|k| k.kanata_name()), Some(*key), "{} {}", layer.name, key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_enabled() {
+        let mut config = default_config();
+        assert!(enabled(&config).is_empty());
+        config.extra_layers.numpad = true;
+        let names: Vec<&str> = enabled(&config).iter().map(|l| l.name).collect();
+        assert_eq!(names, vec!["numpad-layer"]);
+        assert_eq!(NUMPAD.action("k"), Some(("5", "5")));
+        assert_eq!(NUMPAD.action("g"), None);
+    }
+}
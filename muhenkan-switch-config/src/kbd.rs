@@ -0,0 +1,840 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::extra_layers::{self, ExtraLayer};
+use crate::keys::{self, PhysicalKey};
+use crate::{Config, DoubleTap, Layout, Modifier, DISPATCH_KEYS};
+
+/// kbd 上の1キー分の定義。
+struct LayerKey {
+    /// defsrc / default レイヤーでのキー名
+    src: String,
+    /// mh-layer でのアクション
+    action: String,
+}
+
+/// コメント付きのキーグループ。defsrc と両 deflayer で同じ並びを使う。
+struct KeyGroup {
+    comment: &'static str,
+    keys: Vec<LayerKey>,
+}
+
+impl KeyGroup {
+    fn new(comment: &'static str, keys: &[(&str, &str)]) -> Self {
+        Self {
+            comment,
+            keys: keys
+                .iter()
+                .map(|(src, action)| LayerKey {
+                    src: src.to_string(),
+                    action: action.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// ディスパッチ用エイリアス名。
+fn dispatch_alias(key: &str, modifier: Modifier) -> String {
+    match modifier {
+        Modifier::None => format!("dsp-{}", key),
+        Modifier::Shift => format!("sdsp-{}", key),
+    }
+}
+
+/// cmd アクションで起動する muhenkan-switch-core のコマンド。
+/// config を指定して生成した kbd では、同じ config を使うよう --config を付ける。
+fn core_command(config_path: Option<&Path>) -> String {
+    match config_path {
+        Some(path) => format!("muhenkan-switch-core --config \"{}\"", path.display()),
+        None => "muhenkan-switch-core".to_string(),
+    }
+}
+
+/// ディスパッチ用エイリアスの定義行（cmd アクション）。
+fn dispatch_alias_def(core: &str, key: &str, modifier: Modifier) -> String {
+    let flag = match modifier {
+        Modifier::None => "",
+        Modifier::Shift => " --shift",
+    };
+    format!(
+        "  {} (cmd {} dispatch {}{})\n",
+        dispatch_alias(key, modifier),
+        core,
+        key,
+        flag
+    )
+}
+
+/// mh-layer でのディスパッチアクション。
+/// Shift レイヤーの割当があるキーは fork で Shift 押下時に切り替える。
+/// コマンドが送るコピー・貼り付け（Ctrl+C / Ctrl+V）に Shift が混ざらないよう、先に Shift を離す。
+fn dispatch_action(key: &str, has_shift: bool) -> String {
+    if has_shift {
+        format!(
+            "(fork @{} (multi (release-key lsft) (release-key rsft) @{}) (lsft rsft))",
+            dispatch_alias(key, Modifier::None),
+            dispatch_alias(key, Modifier::Shift)
+        )
+    } else {
+        format!("@{}", dispatch_alias(key, Modifier::None))
+    }
+}
+
+/// ディスパッチに回すキーの一覧（物理配列順）。
+/// DISPATCH_KEYS は常に含め、それ以外の割当可能キーは config で割り当てた場合のみ含める。
+/// [symbols] で記号、[editing] で編集操作を割り当てたキーはそちらを優先する。
+fn dispatched_keys(config: &Config) -> Vec<&'static PhysicalKey> {
+    let assigned: Vec<&str> = Modifier::ALL
+        .iter()
+        .flat_map(|m| config.assigned_keys(*m))
+        .collect();
+    let symbols = config.symbol_outputs();
+    let editing = config.editing_keys();
+    keys::layout_keys(config.layout)
+        .filter(|k| keys::is_assignable(k.name))
+        .filter(|k| DISPATCH_KEYS.contains(&k.name) || assigned.contains(&k.name))
+        .filter(|k| !symbols.iter().any(|(s, _)| s.name == k.name))
+        .filter(|k| !editing.iter().any(|(e, _)| e.name == k.name))
+        .collect()
+}
+
+/// 無変換レイヤーのキーグループを組み立てる。
+fn key_groups(config: &Config) -> Vec<KeyGroup> {
+    let dispatched = dispatched_keys(config);
+    let shifted: Vec<&str> = config.assigned_keys(Modifier::Shift).collect();
+    let dispatch = |row: usize| -> Vec<LayerKey> {
+        dispatched
+            .iter()
+            .filter(|k| k.row == row)
+            .map(|k| LayerKey {
+                src: k.kanata_name().to_string(),
+                action: dispatch_action(k.name, shifted.contains(&k.name)),
+            })
+            .collect()
+    };
+    let symbols = config
+        .symbol_outputs()
+        .into_iter()
+        .map(|(k, output)| LayerKey {
+            src: k.kanata_name().to_string(),
+            action: format!("(unicode {})", output),
+        })
+        .collect();
+    let editing_keys = config.editing_keys();
+    let editing = |row: usize| -> Vec<LayerKey> {
+        editing_keys
+            .iter()
+            .filter(|(k, _)| k.row == row)
+            .map(|(k, action)| LayerKey {
+                src: k.kanata_name().to_string(),
+                action: action.as_str().to_string(),
+            })
+            .collect()
+    };
+
+    let mut groups = vec![
+        KeyGroup {
+            comment: "数字キー → ディスパッチ",
+            keys: dispatch(0),
+        },
+        KeyGroup {
+            comment: "上段 → ディスパッチ",
+            keys: dispatch(1),
+        },
+        KeyGroup {
+            comment: "中段 → ディスパッチ",
+            keys: dispatch(2),
+        },
+        KeyGroup {
+            comment: "下段 → ディスパッチ",
+            keys: dispatch(3),
+        },
+        KeyGroup::new("左手下段 → タイムスタンプ位置切替", &[("z", "@ts-toggle")]),
+        KeyGroup::new(
+            "左手下段 → タイムスタンプ操作",
+            &[("v", "@ts-v"), ("c", "@ts-c"), ("x", "@ts-x")],
+        ),
+        KeyGroup {
+            comment: "上段 → テキスト編集（config.toml の [editing]）",
+            keys: editing(1),
+        },
+        KeyGroup {
+            comment: "中段 → テキスト編集",
+            keys: editing(2),
+        },
+        KeyGroup {
+            comment: "下段 → テキスト編集",
+            keys: editing(3),
+        },
+        KeyGroup {
+            comment: "句読点・記号入力",
+            keys: symbols,
+        },
+        KeyGroup::new("F1 → GUI 設定ウィンドウを前面に出す", &[("f1", "@open-gui")]),
+    ];
+    // プロファイルが無ければ F2 はそのまま（切り替えてもエラーになるだけ）
+    if !config.profiles.is_empty() {
+        groups.push(KeyGroup::new("F2 → プロファイル切替", &[("f2", "@switch-profile")]));
+    }
+    groups.extend(extra_layer_groups(config, &groups));
+    groups
+}
+
+/// 追加レイヤーの切替キーと、追加レイヤーだけで使うキーのグループ。
+fn extra_layer_groups(config: &Config, groups: &[KeyGroup]) -> Vec<KeyGroup> {
+    let layers = extra_layers::enabled(config);
+    if layers.is_empty() {
+        return Vec::new();
+    }
+    let trigger = config.trigger_key().kanata_name();
+    let mut switches: Vec<LayerKey> = layers
+        .iter()
+        .filter(|layer| layer.entry != trigger)
+        .map(|layer| LayerKey {
+            src: layer.entry.to_string(),
+            action: format!("(layer-switch {})", layer.name),
+        })
+        .collect();
+    switches.push(LayerKey {
+        src: extra_layers::EXIT_KEY.to_string(),
+        action: "_".to_string(),
+    });
+    let mut used: Vec<&str> = groups.iter().flat_map(|g| &g.keys).map(|k| k.src.as_str()).collect();
+    used.extend(switches.iter().map(|k| k.src.as_str()));
+    let mut extra: Vec<LayerKey> = Vec::new();
+    for (key, _, _) in layers.iter().flat_map(|layer| layer.keys) {
+        if !used.contains(key) && !extra.iter().any(|k| k.src == *key) {
+            extra.push(LayerKey {
+                src: key.to_string(),
+                action: "_".to_string(),
+            });
+        }
+    }
+    vec![
+        KeyGroup {
+            comment: "追加レイヤーへの切り替え（config.toml の [extra_layers]）",
+            keys: switches,
+        },
+        KeyGroup {
+            comment: "追加レイヤーで使うキー",
+            keys: extra,
+        },
+    ]
+}
+
+/// 追加レイヤーでのアクション。切替キーと Esc で default レイヤーに戻る。
+fn extra_layer_action(layer: &ExtraLayer, src: &str) -> String {
+    if src == layer.entry || src == extra_layers::EXIT_KEY {
+        return "(layer-switch default)".to_string();
+    }
+    match layer.action(src) {
+        Some((action, _)) => action.to_string(),
+        None => "_".to_string(),
+    }
+}
+
+/// 固定アクションのエイリアス定義。
+const FIXED_ALIASES: &str = r#"
+  ;; ── 固定アクション ──
+  ;; タイムスタンプ操作
+  ts-v (cmd muhenkan-switch-core timestamp --action paste)
+  ts-c (cmd muhenkan-switch-core timestamp --action copy)
+  ts-x (cmd muhenkan-switch-core timestamp --action cut)
+  ;; タイムスタンプ設定
+  ts-toggle (cmd muhenkan-switch-core toggle-timestamp-position)
+
+  ;; GUI 設定ウィンドウを前面に出す
+  open-gui (cmd muhenkan-switch-core open-gui)
+
+  ;; プロファイル切替（標準 → 各プロファイル → 標準）
+  switch-profile (cmd muhenkan-switch-core switch-profile)
+"#;
+
+/// ダブルタップで実行する kanata のアクション。
+fn double_tap_action(double_tap: &DoubleTap, layout: Layout) -> &'static str {
+    match double_tap {
+        // JIS は半角/全角キー、US 配列は Alt+` で IME を切り替える
+        DoubleTap::Ime => match layout {
+            Layout::Jis => "grv",
+            Layout::Ansi => "A-grv",
+        },
+        DoubleTap::Gui => "@open-gui",
+        DoubleTap::OneShot => "(one-shot 2000 (layer-while-held mh-layer))",
+        DoubleTap::Entry(_) => "@double-tap",
+    }
+}
+
+/// config.toml の内容から kanata 用の kbd ファイルを生成する。
+pub fn generate(config: &Config) -> String {
+    generate_with(config, None)
+}
+
+/// config_path を指定すると、cmd アクションがその config を読むように生成する。
+pub fn generate_with(config: &Config, config_path: Option<&Path>) -> String {
+    let core = core_command(config_path);
+    let trigger = &config.trigger;
+    let key = config.trigger_key();
+    let groups = key_groups(config);
+    let mut out = String::new();
+
+    // ── ヘッダー ──
+    out.push_str(
+        ";; muhenkan.kbd — muhenkan-switch が config.toml から自動生成したファイルです。\n\
+         ;; 直接編集しても、設定の保存時に上書きされます。\n\
+         ;; kanata_cmd_allowed ビルドで使用すること\n\
+         ;;\n",
+    );
+    out.push_str(&format!(
+        ";; {label}キーを押しながら他のキーを押すことでショートカットが発動します。\n\
+         ;; {label}キー単体は{label}キーとして動作します。\n",
+        label = key.label()
+    ));
+    out.push_str(
+        ";;\n\
+         ;; 検索・フォルダ・アプリのキー割り当ては config.toml で管理されています。\n\
+         ;; kbd ファイルは汎用ディスパッチのみを定義します。\n\n",
+    );
+
+    // ── defcfg ──
+    out.push_str("(defcfg\n  process-unmapped-keys yes\n  danger-enable-cmd yes\n)\n\n");
+
+    // ── defsrc ──
+    out.push_str(";; ── ソースキー定義 ──\n(defsrc\n");
+    out.push_str(&format!("  {}\n", key.kanata_name()));
+    push_rows(&mut out, &groups, |k| k.src.clone());
+    out.push_str(")\n\n");
+
+    // ── defalias ──
+    out.push_str(";; ── エイリアス ──\n(defalias\n");
+    out.push_str(&format!(
+        "  ;; {label}キー: tap = {label} / hold = レイヤー切り替え（config.toml の [trigger]）\n",
+        label = key.label()
+    ));
+    let tap_hold = format!(
+        "({} {} {} {} (layer-while-held mh-layer))",
+        trigger.behavior.as_str(),
+        trigger.tap_timeout,
+        trigger.hold_timeout,
+        key.kanata_name()
+    );
+    match &trigger.double_tap {
+        Some(double_tap) => {
+            out.push_str(&format!(
+                "  ;; ダブルタップ = {}（config.toml の trigger.double_tap）\n",
+                double_tap.as_str()
+            ));
+            // eager: 1回目の押下は待たずに従来どおりの tap-hold として動作させる
+            out.push_str(&format!(
+                "  mh (tap-dance-eager {} (@mh-tap {}))\n",
+                trigger.tap_timeout,
+                double_tap_action(double_tap, config.layout)
+            ));
+            out.push_str(&format!("  mh-tap {}\n", tap_hold));
+            if matches!(double_tap, DoubleTap::Entry(_)) {
+                out.push_str(&format!("  double-tap (cmd {} double-tap)\n", core));
+            }
+            out.push('\n');
+        }
+        None => out.push_str(&format!("  mh {}\n\n", tap_hold)),
+    }
+    out.push_str("  ;; ── 汎用ディスパッチ ──\n");
+    out.push_str("  ;; config.toml の key フィールドに対応するアクションが実行される\n");
+    for key in dispatched_keys(config) {
+        out.push_str(&dispatch_alias_def(&core, key.name, Modifier::None));
+    }
+    let shifted: Vec<&str> = config.assigned_keys(Modifier::Shift).collect();
+    if !shifted.is_empty() {
+        out.push_str("  ;; 無変換+Shift+キー（config.toml の shift_key フィールドに対応）\n");
+        for key in dispatched_keys(config) {
+            if shifted.contains(&key.name) {
+                out.push_str(&dispatch_alias_def(&core, key.name, Modifier::Shift));
+            }
+        }
+    }
+    out.push_str(&FIXED_ALIASES.replace("(cmd muhenkan-switch-core ", &format!("(cmd {} ", core)));
+    out.push_str(")\n\n");
+
+    // ── デフォルトレイヤー ──
+    out.push_str(";; ── デフォルトレイヤー ──\n");
+    out.push_str(&format!(";; {}キーだけ tap-hold に変更、他はそのまま\n", key.label()));
+    out.push_str("(deflayer default\n  @mh\n");
+    push_rows(&mut out, &groups, |k| k.src.clone());
+    out.push_str(")\n\n");
+
+    // ── 無変換キー押下中レイヤー ──
+    out.push_str(";; ── 無変換キー押下中レイヤー ──\n");
+    out.push_str("(deflayer mh-layer\n  _\n");
+    push_rows(&mut out, &groups, |k| k.action.clone());
+    out.push_str(")\n");
+
+    // ── 追加レイヤー ──
+    for layer in extra_layers::enabled(config) {
+        out.push_str(&format!(
+            "\n;; ── {}レイヤー（{}+{} で切り替え、Esc で戻る） ──\n",
+            layer.title,
+            key.label(),
+            layer.entry_label
+        ));
+        out.push_str(";; 並びは defsrc と同じ（各行のコメントは無変換レイヤーでの役割）\n");
+        out.push_str(&format!("(deflayer {}\n  _\n", layer.name));
+        push_rows(&mut out, &groups, |k| extra_layer_action(layer, &k.src));
+        out.push_str(")\n");
+    }
+
+    out
+}
+
+/// キーグループをコメント付きで1グループ1行として書き出す。
+fn push_rows(out: &mut String, groups: &[KeyGroup], field: impl Fn(&LayerKey) -> String) {
+    for group in groups.iter().filter(|g| !g.keys.is_empty()) {
+        out.push_str(&format!("  ;; {}\n", group.comment));
+        let row: Vec<String> = group.keys.iter().map(&field).collect();
+        out.push_str(&format!("  {}\n", row.join("  ")));
+    }
+}
+
+/// config から kbd ファイルを生成して書き込む。
+pub fn write(kbd_path: &Path, config: &Config) -> Result<()> {
+    std::fs::write(kbd_path, generate(config))
+        .with_context(|| format!("kbd ファイルの書き込みに失敗しました: {}", kbd_path.display()))
+}
+
+/// 内容が変わる場合だけ kbd ファイルを書き込む。書き込んだら true（kanata の再起動が必要）。
+pub fn sync(kbd_path: &Path, config: &Config) -> Result<bool> {
+    let kbd = generate(config);
+    if std::fs::read_to_string(kbd_path).is_ok_and(|current| current == kbd) {
+        return Ok(false);
+    }
+    std::fs::write(kbd_path, kbd)
+        .with_context(|| format!("kbd ファイルの書き込みに失敗しました: {}", kbd_path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_config, PunctuationStyle};
+
+    /// `(deflayer name ...)` / `(defsrc ...)` ブロック内のキー・アクションを列挙する。
+    fn block_items(kbd: &str, header: &str) -> Vec<String> {
+        let start = kbd.find(header).expect("block not found");
+        let body = &kbd[start + header.len()..];
+        let end = body.find("\n)").expect("block not closed");
+        body[..end]
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with(";;"))
+            .flat_map(split_items)
+            .collect()
+    }
+
+    /// 括弧を考慮して空白区切りで分割する。
+    fn split_items(line: &str) -> Vec<String> {
+        let mut items = Vec::new();
+        let mut depth = 0;
+        let mut cur = String::new();
+        for c in line.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if c.is_whitespace() && depth == 0 {
+                if !cur.is_empty() {
+                    items.push(std::mem::take(&mut cur));
+                }
+            } else {
+                cur.push(c);
+            }
+        }
+        if !cur.is_empty() {
+            items.push(cur);
+        }
+        items
+    }
+
+    #[test]
+    fn test_layers_match_defsrc() {
+        let kbd = generate(&default_config());
+        let src = block_items(&kbd, "(defsrc");
+        let default = block_items(&kbd, "(deflayer default");
+        let mh = block_items(&kbd, "(deflayer mh-layer");
+        assert_eq!(src.len(), default.len());
+        assert_eq!(src.len(), mh.len());
+    }
+
+    #[test]
+    fn test_all_dispatch_keys_have_alias() {
+        let kbd = generate(&default_config());
+        for key in DISPATCH_KEYS {
+            assert!(
+                kbd.contains(&format!("dsp-{} (cmd muhenkan-switch-core dispatch {})", key, key)),
+                "missing alias for {}",
+                key
+            );
+            assert!(kbd.contains(&format!("@dsp-{}", key)), "missing layer entry for {}", key);
+        }
+    }
+
+    #[test]
+    fn test_spare_key_dispatched_only_when_assigned() {
+        let mut config = default_config();
+        assert!(!generate(&config).contains("dsp-p"));
+
+        config.search.insert(
+            "extra".to_string(),
+            crate::SearchEntry {
+                key: Some("p".parse().unwrap()),
+                shift_key: None,
+                url: "https://example.com/?q={query}".to_string(),
+            },
+        );
+        let kbd = generate(&config);
+        assert!(kbd.contains("dsp-p (cmd muhenkan-switch-core dispatch p)"));
+        assert!(kbd.contains("@dsp-p"));
+        let src = block_items(&kbd, "(defsrc");
+        let mh = block_items(&kbd, "(deflayer mh-layer");
+        assert_eq!(src.len(), mh.len());
+    }
+
+    #[test]
+    fn test_override_key_is_dispatched() {
+        let mut config = default_config();
+        let name = config.search.keys().next().unwrap().clone();
+        config.overrides.push(crate::OverrideEntry {
+            process: "firefox".to_string(),
+            keys: [("p".parse().unwrap(), name)].into_iter().collect(),
+            shift_keys: Default::default(),
+        });
+        let kbd = generate(&config);
+        assert!(kbd.contains("dsp-p (cmd muhenkan-switch-core dispatch p)"));
+    }
+
+    #[test]
+    fn test_profile_key_is_dispatched() {
+        let mut config = default_config();
+        let folders = [(
+            "manuscript".to_string(),
+            crate::FolderEntry {
+                key: Some("p".parse().unwrap()),
+                shift_key: None,
+                path: "~/原稿".to_string(),
+            },
+        )]
+        .into_iter()
+        .collect();
+        config.profiles.insert(
+            "執筆".to_string(),
+            crate::Profile {
+                folders: Some(folders),
+                ..Default::default()
+            },
+        );
+        assert!(!generate(&default_config()).contains("@switch-profile"));
+        let kbd = generate(&config);
+        assert!(kbd.contains("dsp-p (cmd muhenkan-switch-core dispatch p)"));
+        assert!(kbd.contains("@switch-profile"));
+    }
+
+    #[test]
+    fn test_shift_binding_uses_fork() {
+        let mut config = default_config();
+        assert!(!generate(&config).contains("sdsp-"));
+
+        config.folders.insert(
+            "extra".to_string(),
+            crate::FolderEntry {
+                key: None,
+                shift_key: Some("g".parse().unwrap()),
+                path: "~".to_string(),
+            },
+        );
+        let kbd = generate(&config);
+        assert!(kbd.contains("sdsp-g (cmd muhenkan-switch-core dispatch g --shift)"));
+        assert!(kbd.contains(
+            "(fork @dsp-g (multi (release-key lsft) (release-key rsft) @sdsp-g) (lsft rsft))"
+        ));
+        let src = block_items(&kbd, "(defsrc");
+        let mh = block_items(&kbd, "(deflayer mh-layer");
+        assert_eq!(src.len(), mh.len());
+    }
+
+    #[test]
+    fn test_punctuation_style() {
+        let mut config = default_config();
+        config.punctuation_style = PunctuationStyle::CommaPeriod;
+        let kbd = generate(&config);
+        assert!(kbd.contains("(unicode ，)  (unicode ．)"));
+        assert!(!kbd.contains("(unicode 、)"));
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut config = default_config();
+        config.symbols.insert(",".to_string(), '；');
+        config.symbols.insert("@".to_string(), '＠');
+        config.symbols.insert("]".to_string(), '」');
+        let kbd = generate(&config);
+        let src = block_items(&kbd, "(defsrc");
+        let mh = block_items(&kbd, "(deflayer mh-layer");
+        assert_eq!(src.len(), mh.len());
+        // [symbols] が punctuation_style より優先される
+        assert!(kbd.contains("(unicode ；)  (unicode 。)"));
+        // JIS の @ ] は kanata では [ \ のキー
+        let at = src.iter().position(|k| k == "[").unwrap();
+        assert_eq!(mh[at], "(unicode ＠)");
+        let bracket = src.iter().position(|k| k == "\\").unwrap();
+        assert_eq!(mh[bracket], "(unicode 」)");
+    }
+
+    #[test]
+    fn test_symbol_takes_over_dispatch_key() {
+        let mut config = default_config();
+        config.symbols.insert("/".to_string(), '・');
+        config.search.get_mut("Google").unwrap().key = Some("/".parse().unwrap());
+        let kbd = generate(&config);
+        assert!(kbd.contains("(unicode ・)"));
+        assert!(!kbd.contains("dsp-/"));
+        let src = block_items(&kbd, "(defsrc");
+        assert_eq!(src.iter().filter(|k| *k == "/").count(), 1);
+    }
+
+    #[test]
+    fn test_editing_preset() {
+        let mut config = default_config();
+        config.editing.preset = crate::EditingPreset::Wasd;
+        config.editing.keys.insert("h".to_string(), crate::EditAction::PageUp);
+        let kbd = generate(&config);
+        let src = block_items(&kbd, "(defsrc");
+        let mh = block_items(&kbd, "(deflayer mh-layer");
+        assert_eq!(src.len(), mh.len());
+        let action = |key: &str| &mh[src.iter().position(|k| k == key).unwrap()];
+        // 編集キーは割当キーより優先し、ディスパッチに回さない
+        assert_eq!(action("w"), "up");
+        assert_eq!(action("a"), "left");
+        assert!(!kbd.contains("dsp-w "));
+        // editing.keys はプリセットに追加される
+        assert_eq!(action("h"), "pgup");
+        assert!(!src.contains(&"j".to_string()));
+    }
+
+    #[test]
+    fn test_extra_layers() {
+        let mut config = default_config();
+        assert!(!generate(&config).contains("layer-switch"));
+        config.extra_layers.mouse = true;
+        config.extra_layers.numpad = true;
+        config.editing.preset = crate::EditingPreset::None;
+        let kbd = generate(&config);
+        let src = block_items(&kbd, "(defsrc");
+        let mh = block_items(&kbd, "(deflayer mh-layer");
+        let mouse = block_items(&kbd, "(deflayer mouse-layer");
+        let numpad = block_items(&kbd, "(deflayer numpad-layer");
+        assert_eq!(src.len(), mh.len());
+        assert_eq!(src.len(), mouse.len());
+        assert_eq!(src.len(), numpad.len());
+        let at = |key: &str| src.iter().position(|k| k == key).unwrap();
+        assert_eq!(mh[at("spc")], "(layer-switch mouse-layer)");
+        assert_eq!(mh[at("tab")], "(layer-switch numpad-layer)");
+        // 編集キーがなくても追加レイヤーのキーは defsrc に入る
+        assert_eq!(mh[at("h")], "_");
+        assert_eq!(mouse[at("h")], "(movemouse-left 4 4)");
+        assert_eq!(numpad[at("k")], "5");
+        assert_eq!(mouse[at("esc")], "(layer-switch default)");
+        assert_eq!(numpad[at("tab")], "(layer-switch default)");
+        assert_eq!(mouse[at("tab")], "_");
+    }
+
+    #[test]
+    fn test_extra_layer_entry_skips_trigger() {
+        let mut config = default_config();
+        config.extra_layers.mouse = true;
+        config.trigger.key = Some(crate::TriggerKey::Space);
+        let kbd = generate(&config);
+        let src = block_items(&kbd, "(defsrc");
+        assert_eq!(src.iter().filter(|k| *k == "spc").count(), 1);
+        assert!(!kbd.contains("(layer-switch mouse-layer)"));
+    }
+
+    #[test]
+    fn test_trigger() {
+        let mut config = default_config();
+        config.trigger.key = Some(crate::TriggerKey::CapsLock);
+        config.trigger.tap_timeout = 150;
+        config.trigger.hold_timeout = 250;
+        config.trigger.behavior = crate::TapHoldBehavior::TapHoldPress;
+        let kbd = generate(&config);
+        assert!(kbd.contains("mh (tap-hold-press 150 250 caps (layer-while-held mh-layer))"));
+        assert_eq!(block_items(&kbd, "(defsrc")[0], "caps");
+        assert!(kbd.contains(";; CapsLockキーを押しながら"));
+        assert!(!kbd.contains("muhenkan "));
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let mut config = default_config();
+        assert!(!generate(&config).contains("tap-dance"));
+
+        config.trigger.double_tap = Some(DoubleTap::Ime);
+        let kbd = generate(&config);
+        assert!(kbd.contains("mh (tap-dance-eager 200 (@mh-tap grv))"));
+        assert!(kbd.contains("mh-tap (tap-hold 200 200 muhenkan (layer-while-held mh-layer))"));
+        assert!(!kbd.contains("double-tap (cmd"));
+
+        config.layout = Layout::Ansi;
+        assert!(generate(&config).contains("(@mh-tap A-grv)"));
+
+        config.trigger.double_tap = Some(DoubleTap::OneShot);
+        assert!(generate(&config).contains("(@mh-tap (one-shot 2000 (layer-while-held mh-layer)))"));
+
+        config.trigger.double_tap = Some(DoubleTap::Entry("google".to_string()));
+        let kbd = generate(&config);
+        assert!(kbd.contains("(@mh-tap @double-tap)"));
+        assert!(kbd.contains("double-tap (cmd muhenkan-switch-core double-tap)"));
+    }
+
+    #[test]
+    fn test_config_path_is_passed_to_commands() {
+        let mut config = default_config();
+        config.trigger.double_tap = Some(DoubleTap::Entry("google".to_string()));
+        config.search["Google"].shift_key = Some("g".parse().unwrap());
+        let kbd = generate_with(&config, Some(Path::new("/tmp/alt config.toml")));
+        let cmds: Vec<&str> = kbd.lines().filter(|l| l.contains("(cmd ")).collect();
+        assert!(cmds.len() > 10);
+        for line in &cmds {
+            assert!(
+                line.contains(r#"(cmd muhenkan-switch-core --config "/tmp/alt config.toml" "#),
+                "{}",
+                line
+            );
+        }
+        for alias in ["dsp-g", "sdsp-g", "ts-v", "ts-toggle", "open-gui", "switch-profile", "double-tap"] {
+            assert!(cmds.iter().any(|l| l.trim_start().starts_with(alias)), "{}", alias);
+        }
+        // 指定しなければ従来どおり
+        assert!(!generate(&config).contains("--config"));
+    }
+
+    #[test]
+    fn test_ansi_layout() {
+        let mut config = default_config();
+        config.layout = crate::Layout::Ansi;
+        let kbd = generate(&config);
+        let src = block_items(&kbd, "(defsrc");
+        // トリガーキーは CapsLock、記号キーは US 配列のキー名そのまま
+        assert_eq!(src[0], "caps");
+        assert!(src.contains(&"[".to_string()) && src.contains(&"]".to_string()));
+        assert!(!src.contains(&"\\".to_string()));
+
+        config.layout = crate::Layout::Jis;
+        let src = block_items(&generate(&config), "(defsrc");
+        assert!(src.contains(&"\\".to_string()));
+    }
+
+    #[test]
+    fn test_write_creates_file() {
+        let dir = std::env::temp_dir().join("muhenkan_test_kbd_write");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("muhenkan.kbd");
+        let config = default_config();
+        write(&path, &config).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), generate(&config));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_sync_writes_only_changes() {
+        let dir = std::env::temp_dir().join("muhenkan_test_kbd_sync");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("muhenkan.kbd");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        assert!(sync(&path, &config).unwrap());
+        assert!(!sync(&path, &config).unwrap());
+        config.punctuation_style = PunctuationStyle::CommaPeriod;
+        assert!(sync(&path, &config).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), generate(&config));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// kanata シミュレータの出力（入力は kanata の sim 形式 "d:KEY t:MS u:KEY ..."）。
+    fn simulate(kbd: &str, name: &str, sim: &str) -> String {
+        let dir = std::env::temp_dir().join("muhenkan_test_kanata_sim");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = dir.join(format!("{}.kbd", name));
+        let sim_file = dir.join(format!("{}.sim.txt", name));
+        std::fs::write(&cfg, kbd).unwrap();
+        std::fs::write(&sim_file, sim).unwrap();
+        let kanata = std::env::var("KANATA").unwrap_or_else(|_| "kanata".to_string());
+        let check = std::process::Command::new(&kanata)
+            .arg("--cfg")
+            .arg(&cfg)
+            .arg("--check")
+            .output()
+            .expect("kanata を実行できません（KANATA で cmd_allowed 版のパスを指定）");
+        assert!(check.status.success(), "kanata --check: {}", String::from_utf8_lossy(&check.stderr));
+        let sim_bin =
+            std::env::var("KANATA_SIM").unwrap_or_else(|_| "kanata_simulated_input".to_string());
+        let output = std::process::Command::new(&sim_bin)
+            .arg("--cfg")
+            .arg(&cfg)
+            .arg("--sim")
+            .arg(&sim_file)
+            .output()
+            .expect("kanata_simulated_input を実行できません（KANATA_SIM でパスを指定）");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    // cargo test -p muhenkan-switch-config -- --ignored で実行する
+    #[test]
+    #[ignore = "kanata（cmd_allowed 版）と kanata_simulated_input が必要"]
+    fn test_double_tap_keeps_tap_and_hold_in_kanata() {
+        let plain = generate(&default_config());
+        let mut config = default_config();
+        config.trigger.double_tap = Some(DoubleTap::Ime);
+        let double_tap = generate(&config);
+
+        // 1回押し・長押し+H・素早いロールは、ダブルタップ無しと同じ出力・タイミング
+        for (name, sim) in [
+            ("tap", "d:muhenkan t:50 u:muhenkan t:500"),
+            ("hold", "d:muhenkan t:300 d:h t:50 u:h t:50 u:muhenkan t:500"),
+            ("roll", "d:muhenkan t:30 d:h t:30 u:h t:10 u:muhenkan t:500"),
+        ] {
+            assert_eq!(
+                simulate(&double_tap, &format!("{}-double", name), sim),
+                simulate(&plain, &format!("{}-plain", name), sim),
+                "{}",
+                name
+            );
+        }
+        // 2回押しでだけ挙動が変わる
+        let sim = "d:muhenkan t:30 u:muhenkan t:30 d:muhenkan t:30 u:muhenkan t:500";
+        assert_ne!(simulate(&double_tap, "double-double", sim), simulate(&plain, "double-plain", sim));
+    }
+
+    /// 同梱の kanata/muhenkan.kbd がジェネレーターの出力と一致していること。
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_shipped_kbd_is_up_to_date() {
+        let shipped = include_str!("../../kanata/muhenkan.kbd");
+        assert_eq!(
+            shipped.replace("\r\n", "\n"),
+            generate(&default_config()),
+            "kanata/muhenkan.kbd を `muhenkan-switch-core generate-kbd -o kanata/muhenkan.kbd` で再生成してください"
+        );
+    }
+
+    #[test]
+    fn test_shipped_macos_kbd_is_up_to_date() {
+        let shipped = include_str!("../../kanata/muhenkan-macos.kbd");
+        let config: Config = toml::from_str(crate::DEFAULT_MACOS_CONFIG).unwrap();
+        assert_eq!(
+            shipped.replace("\r\n", "\n"),
+            generate(&config),
+            "kanata/muhenkan-macos.kbd を `muhenkan-switch-core --config config/default-macos.toml generate-kbd --no-config-arg -o kanata/muhenkan-macos.kbd` で再生成してください"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! 無変換レイヤーで扱う物理キーの一覧。
+//!
+//! kbd 生成・SVG 描画・割当キーのバリデーションはすべてこの表を参照する。
+
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::str::FromStr;
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+
+use crate::Layout;
+
+/// 無変換レイヤーでのキーの役割。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRole {
+    /// config の割当キーとして使えるキー
+    Dispatch,
+    /// タイムスタンプ操作（Z, X, C, V）
+    Timestamp,
+    /// 右手テキスト編集（カーソル移動・削除。操作は config の [editing] で変えられる）
+    TextEdit,
+    /// 記号入力（句読点・括弧など。出力する文字は config の [symbols] で変えられる）
+    Symbol,
+}
+
+/// 物理キーの定義。
+#[derive(Debug)]
+pub struct PhysicalKey {
+    /// config.toml / kanata で使うキー名（小文字）
+    pub name: &'static str,
+    /// 表示用ラベル
+    pub label: &'static str,
+    /// QWERTY 物理配列の行 (0=数字行, 1=Q行, 2=A行, 3=Z行)
+    pub row: usize,
+    /// QWERTY 物理配列の列 (0=左端, 5 以降が右手)
+    pub col: usize,
+    /// 無変換レイヤーでの役割
+    pub role: KeyRole,
+    /// kanata でのキー名
+    kanata: &'static str,
+    /// このキーがある配列（None は両方）
+    layout: Option<Layout>,
+}
+
+impl PhysicalKey {
+    /// kanata でのキー名。JIS 配列の記号キーは US 配列の同じ位置のキー名になる。
+    pub fn kanata_name(&self) -> &'static str {
+        self.kanata
+    }
+
+    /// 指定の配列にあるキーかどうか。
+    pub fn is_on(&self, layout: Layout) -> bool {
+        self.layout.is_none_or(|l| l == layout)
+    }
+
+    const fn jis(self, kanata: &'static str) -> Self {
+        Self { kanata, layout: Some(Layout::Jis), ..self }
+    }
+
+    const fn ansi(self) -> Self {
+        Self { layout: Some(Layout::Ansi), ..self }
+    }
+}
+
+const fn key(name: &'static str, label: &'static str, row: usize, col: usize, role: KeyRole) -> PhysicalKey {
+    PhysicalKey { name, label, row, col, role, kanata: name, layout: None }
+}
+
+use KeyRole::{Dispatch, Symbol, TextEdit, Timestamp};
+
+/// JIS / US (ANSI) キーボードの主要キー（QWERTY 物理配列順）。
+/// 記号キーは配列ごとに位置と名前が異なるため、配列ごとに定義する。
+pub const KEYS: &[PhysicalKey] = &[
+    // ── 数字行 (row 0) ──
+    key("1", "1", 0, 0, Dispatch),
+    key("2", "2", 0, 1, Dispatch),
+    key("3", "3", 0, 2, Dispatch),
+    key("4", "4", 0, 3, Dispatch),
+    key("5", "5", 0, 4, Dispatch),
+    key("6", "6", 0, 5, Dispatch),
+    key("7", "7", 0, 6, Dispatch),
+    key("8", "8", 0, 7, Dispatch),
+    key("9", "9", 0, 8, Dispatch),
+    key("0", "0", 0, 9, Dispatch),
+    // ── Q行 (row 1) ──
+    key("q", "Q", 1, 0, Dispatch),
+    key("w", "W", 1, 1, Dispatch),
+    key("e", "E", 1, 2, Dispatch),
+    key("r", "R", 1, 3, Dispatch),
+    key("t", "T", 1, 4, Dispatch),
+    key("y", "Y", 1, 5, TextEdit),
+    key("u", "U", 1, 6, TextEdit),
+    key("i", "I", 1, 7, TextEdit),
+    key("o", "O", 1, 8, TextEdit),
+    key("p", "P", 1, 9, Dispatch),
+    key("@", "@", 1, 10, Symbol).jis("["),
+    key("[", "[", 1, 11, Symbol).jis("]"),
+    key("[", "[", 1, 10, Symbol).ansi(),
+    key("]", "]", 1, 11, Symbol).ansi(),
+    key("\\", "\\", 1, 12, Symbol).ansi(),
+    // ── A行 / ホーム行 (row 2) ──
+    key("a", "A", 2, 0, Dispatch),
+    key("s", "S", 2, 1, Dispatch),
+    key("d", "D", 2, 2, Dispatch),
+    key("f", "F", 2, 3, Dispatch),
+    key("g", "G", 2, 4, Dispatch),
+    key("h", "H", 2, 5, TextEdit),
+    key("j", "J", 2, 6, TextEdit),
+    key("k", "K", 2, 7, TextEdit),
+    key("l", "L", 2, 8, TextEdit),
+    key(";", ";", 2, 9, TextEdit),
+    key(":", ":", 2, 10, Symbol).jis("'"),
+    key("]", "]", 2, 11, Symbol).jis("\\"),
+    key("'", "'", 2, 10, Symbol).ansi(),
+    // ── Z行 / 下行 (row 3) ──
+    key("z", "Z", 3, 0, Timestamp),
+    key("x", "X", 3, 1, Timestamp),
+    key("c", "C", 3, 2, Timestamp),
+    key("v", "V", 3, 3, Timestamp),
+    key("b", "B", 3, 4, Dispatch),
+    key("n", "N", 3, 5, TextEdit),
+    key("m", "M", 3, 6, TextEdit),
+    key(",", ",", 3, 7, Symbol),
+    key(".", ".", 3, 8, Symbol),
+    key("/", "/", 3, 9, Dispatch),
+];
+
+/// キー名から物理キーを検索する（配列を問わない。割当キーはどの配列でも同じ）。
+pub fn find(name: &str) -> Option<&'static PhysicalKey> {
+    KEYS.iter().find(|k| k.name == name)
+}
+
+/// 指定の配列にあるキーの一覧（物理配列順）。
+pub fn layout_keys(layout: Layout) -> impl Iterator<Item = &'static PhysicalKey> {
+    KEYS.iter().filter(move |k| k.is_on(layout))
+}
+
+/// config の割当キーとして使えるキーかどうか。
+/// テキスト編集キーも使える（編集操作と重なる場合は validate で報告する）。
+pub fn is_assignable(name: &str) -> bool {
+    find(name).is_some_and(|k| matches!(k.role, KeyRole::Dispatch | KeyRole::TextEdit))
+}
+
+/// 大文字・全角・前後の空白を含むキー名を config.toml の表記に直す。
+/// 元の表記と同じ場合は None。
+pub fn normalize(name: &str) -> Option<String> {
+    let normalized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            // 全角の英数字・記号 → 半角
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            c => c,
+        })
+        .collect::<String>()
+        .to_lowercase();
+    (normalized != name).then_some(normalized)
+}
+
+/// [symbols] で記号を割り当てられるキーかどうか（その配列の記号キーと割当可能なキー）。
+pub fn is_symbol_key(layout: Layout, name: &str) -> bool {
+    layout_keys(layout)
+        .any(|k| k.name == name && matches!(k.role, KeyRole::Symbol | KeyRole::Dispatch))
+}
+
+/// [editing] で編集操作を割り当てられるキーかどうか（テキスト編集キーと割当可能なキー）。
+pub fn is_editing_key(layout: Layout, name: &str) -> bool {
+    layout_keys(layout)
+        .any(|k| k.name == name && matches!(k.role, KeyRole::TextEdit | KeyRole::Dispatch))
+}
+
+/// 割当キーとして使えるキー名の一覧（物理配列順）。
+pub fn assignable_keys() -> Vec<&'static str> {
+    KEYS.iter()
+        .filter(|k| is_assignable(k.name))
+        .map(|k| k.name)
+        .collect()
+}
+
+/// 割当キー（config の key / shift_key、overrides のキー）。
+/// 割当可能なキーであることを読み込み時に検証する。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DispatchKey(String);
+
+impl DispatchKey {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// キー名を検証する。使えないキーは理由と候補を含むメッセージを返す。
+impl FromStr for DispatchKey {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match find(name) {
+            Some(k) if is_assignable(k.name) => Ok(Self(k.name.to_string())),
+            Some(k) if k.role == KeyRole::Symbol => Err(format!(
+                "'{}' は記号入力用のキーのため割当キーにできません（[symbols] で入力する文字を変えられます）",
+                name
+            )),
+            Some(_) => Err(format!(
+                "'{}' はタイムスタンプ操作用のキーのため割当キーにできません（別のキーを指定してください）",
+                name
+            )),
+            None => Err(match normalize(name) {
+                Some(normalized) if is_assignable(&normalized) => {
+                    format!("'{}' は割当キーに使用できません（'{}' の誤りではありませんか）", name, normalized)
+                }
+                _ => format!(
+                    "'{}' は割当キーに使用できません（{} のいずれかを指定してください）",
+                    name,
+                    assignable_keys().join(", ")
+                ),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for DispatchKey {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        name.parse()
+    }
+}
+
+impl From<DispatchKey> for String {
+    fn from(key: DispatchKey) -> String {
+        key.0
+    }
+}
+
+impl fmt::Display for DispatchKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Borrow<str> for DispatchKey {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for DispatchKey {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for DispatchKey {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl JsonSchema for DispatchKey {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "DispatchKey".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": assignable_keys(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DISPATCH_KEYS;
+
+    #[test]
+    fn test_dispatch_keys_are_assignable() {
+        for key in DISPATCH_KEYS {
+            assert!(is_assignable(key), "{} should be assignable", key);
+        }
+    }
+
+    #[test]
+    fn test_fixed_keys_are_not_assignable() {
+        for key in ["z", "x", "c", "v", ",", "."] {
+            assert!(!is_assignable(key), "{} should not be assignable", key);
+        }
+        assert!(!is_assignable("f1"));
+        assert!(!is_assignable("ctrl"));
+    }
+
+    #[test]
+    fn test_spare_keys_are_assignable() {
+        for key in ["6", "7", "8", "9", "0", "p", "/", "h", "j", "k", "l", ";", "y", "n"] {
+            assert!(is_assignable(key), "{} should be assignable", key);
+        }
+    }
+
+    #[test]
+    fn test_symbol_keys() {
+        for key in [",", ".", "/", "[", "]", "@", ":"] {
+            assert!(is_symbol_key(Layout::Jis, key), "{} should be a symbol key", key);
+        }
+        for key in ["h", ";", "z", "\\", "'"] {
+            assert!(!is_symbol_key(Layout::Jis, key), "{} should not be a symbol key", key);
+        }
+        assert!(!is_assignable("["));
+        // JIS の [ ] は US 配列の ] \ の位置
+        let kanata = |layout, name| layout_keys(layout).find(|k| k.name == name).unwrap().kanata_name();
+        assert_eq!(kanata(Layout::Jis, "["), "]");
+        assert_eq!(kanata(Layout::Jis, "]"), "\\");
+        assert_eq!(kanata(Layout::Jis, "/"), "/");
+    }
+
+    #[test]
+    fn test_ansi_keys() {
+        for key in ["[", "]", "\\", "'"] {
+            assert!(is_symbol_key(Layout::Ansi, key), "{} should be a symbol key", key);
+        }
+        assert!(!is_symbol_key(Layout::Ansi, "@"));
+        assert!(!is_symbol_key(Layout::Ansi, ":"));
+        // 割当キーはどちらの配列にもある
+        for key in assignable_keys() {
+            assert!(layout_keys(Layout::Ansi).any(|k| k.name == key), "{}", key);
+        }
+        let names = |layout| layout_keys(layout).map(|k| k.kanata_name()).collect::<Vec<_>>();
+        assert_eq!(names(Layout::Jis).len(), 44);
+        assert_eq!(names(Layout::Ansi).len(), 44);
+        assert!(names(Layout::Ansi).contains(&"["));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("G").as_deref(), Some("g"));
+        assert_eq!(normalize(" ｑ ").as_deref(), Some("q"));
+        assert_eq!(normalize("／").as_deref(), Some("/"));
+        assert_eq!(normalize("g"), None);
+    }
+
+    #[test]
+    fn test_dispatch_key_parse() {
+        assert_eq!("g".parse::<DispatchKey>().unwrap(), "g");
+        assert_eq!("h".parse::<DispatchKey>().unwrap(), "h");
+        assert!("z".parse::<DispatchKey>().unwrap_err().contains("タイムスタンプ操作"));
+        assert!("G".parse::<DispatchKey>().unwrap_err().contains("'g' の誤りではありませんか"));
+        assert!("f1".parse::<DispatchKey>().unwrap_err().contains("いずれかを指定してください"));
+    }
+
+    #[test]
+    fn test_dispatch_key_serde() {
+        let key: DispatchKey = serde_json::from_str("\"/\"").unwrap();
+        assert_eq!(serde_json::to_string(&key).unwrap(), "\"/\"");
+        let err = serde_json::from_str::<DispatchKey>("\"ｇ\"").unwrap_err();
+        assert!(err.to_string().contains("'g' の誤りではありませんか"), "{}", err);
+    }
+}
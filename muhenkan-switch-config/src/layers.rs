@@ -0,0 +1,595 @@
+//! config.toml の探索とレイヤーの重ね合わせ。
+//!
+//! 複数の場所に config.toml があれば、優先度の低いものから順に深くマージする。
+//! テーブル（[search] など）はキーごとに、エントリやその他の値は丸ごと上書きする。
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::migrate::{migrate_document_as, DocumentRole};
+use crate::Config;
+
+/// 設定ファイルのパスを指定する環境変数。
+pub const CONFIG_ENV: &str = "MUHENKAN_SWITCH_CONFIG";
+
+/// 設定レイヤーの種類（優先度の高い順）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerKind {
+    /// コマンドラインの --config で指定したファイル（他のレイヤーは読まない）
+    Explicit,
+    /// $MUHENKAN_SWITCH_CONFIG
+    Env,
+    /// $XDG_CONFIG_HOME/muhenkan-switch/config.toml（ユーザーごと）
+    User,
+    /// 実行ファイルと同じディレクトリ
+    ExeDir,
+    /// ワークスペースルートの bin/（開発環境）
+    DevBin,
+    /// バイナリに埋め込まれたデフォルト設定（ファイルが無い場合のみ）
+    Embedded,
+}
+
+impl LayerKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            LayerKind::Explicit => "--config で指定したファイル",
+            LayerKind::Env => "環境変数 MUHENKAN_SWITCH_CONFIG",
+            LayerKind::User => "ユーザー設定",
+            LayerKind::ExeDir => "実行ファイルのディレクトリ",
+            LayerKind::DevBin => "開発環境 (bin/)",
+            LayerKind::Embedded => "組み込みデフォルト",
+        }
+    }
+}
+
+/// 読み込んだ設定レイヤー。
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub kind: LayerKind,
+    /// 組み込みデフォルトは None
+    pub path: Option<PathBuf>,
+}
+
+impl std::fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{} ({})", self.kind.label(), path.display()),
+            None => f.write_str(self.kind.label()),
+        }
+    }
+}
+
+/// レイヤーを重ねた設定と、各値の由来。
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: Config,
+    /// 優先度の高い順
+    pub layers: Vec<ConfigLayer>,
+    /// 値のキー（"search.Google", "timestamp.format" など）→ layers のインデックス
+    pub origins: IndexMap<String, usize>,
+}
+
+impl LayeredConfig {
+    /// 値がどのレイヤーから来たかを返す。
+    pub fn origin(&self, key: &str) -> Option<&ConfigLayer> {
+        self.origins.get(key).map(|&i| &self.layers[i])
+    }
+}
+
+/// ユーザーごとの設定ファイルのパス。
+/// $XDG_CONFIG_HOME → (Windows) %APPDATA% → $HOME/.config の順に決める。
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = non_empty_env("XDG_CONFIG_HOME")
+        .or_else(|| {
+            if cfg!(target_os = "windows") {
+                non_empty_env("APPDATA")
+            } else {
+                None
+            }
+        })
+        .or_else(|| non_empty_env("HOME").map(|home| home.join(".config")))?;
+    Some(base.join("muhenkan-switch").join("config.toml"))
+}
+
+fn non_empty_env(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 設定ファイルの候補（優先度の高い順、存在しないものも含む）。
+pub fn candidate_paths() -> Vec<(LayerKind, PathBuf)> {
+    let mut candidates = Vec::new();
+    if let Some(path) = non_empty_env(CONFIG_ENV) {
+        candidates.push((LayerKind::Env, path));
+    }
+    if let Some(path) = user_config_path() {
+        candidates.push((LayerKind::User, path));
+    }
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        candidates.push((LayerKind::ExeDir, dir.join("config.toml")));
+    }
+    if let Some(dir) = Path::new(env!("CARGO_MANIFEST_DIR")).parent() {
+        candidates.push((LayerKind::DevBin, dir.join("bin").join("config.toml")));
+    }
+    candidates
+}
+
+/// 存在する設定ファイルのレイヤー（優先度の高い順）。
+pub fn find_layers() -> Vec<ConfigLayer> {
+    let mut layers: Vec<ConfigLayer> = Vec::new();
+    for (kind, path) in candidate_paths() {
+        // 同じファイルを複数のレイヤーとして数えない
+        if path.is_file() && !layers.iter().any(|l| l.path.as_deref() == Some(path.as_path())) {
+            layers.push(ConfigLayer {
+                kind,
+                path: Some(path),
+            });
+        }
+    }
+    layers
+}
+
+/// 設定ファイルを探索し、見つかったレイヤーを重ねて読み込む。
+/// ファイルが1つも無ければ組み込みデフォルトを使う。
+pub fn load_layered() -> Result<LayeredConfig> {
+    let layers = find_layers();
+    if layers.is_empty() {
+        eprintln!("警告: config.toml が見つかりません。デフォルト値を使用します。");
+        return Ok(embedded());
+    }
+    merge_layers(layers)
+}
+
+/// 書き込み先のレイヤー。$MUHENKAN_SWITCH_CONFIG があればそのファイル、なければユーザー設定。
+/// 実行ファイルのディレクトリは読み取り専用のことがあるため書き込み先にしない。
+pub fn writable_layer() -> Option<ConfigLayer> {
+    let (kind, path) = match non_empty_env(CONFIG_ENV) {
+        Some(path) => (LayerKind::Env, path),
+        None => (LayerKind::User, user_config_path()?),
+    };
+    Some(ConfigLayer {
+        kind,
+        path: Some(path),
+    })
+}
+
+/// 重ねた設定を変更して、書き込み先のレイヤーに保存する（[`update_layer`]）。
+pub fn update(f: impl FnOnce(&mut Config) -> Result<()>) -> Result<Config> {
+    let target =
+        writable_layer().context("設定ファイルの保存先が見つかりません（HOME が未設定です）")?;
+    let lower = find_layers()
+        .into_iter()
+        .filter(|layer| layer.path != target.path)
+        .collect();
+    update_layer(target, lower, f)
+}
+
+/// 設定を書き込み先のレイヤーに保存する（下のレイヤーとの差分だけを書く）。
+pub fn save(config: &Config) -> Result<()> {
+    update(|current| {
+        *current = config.clone();
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// target に lower（優先度の高い順）を重ねた設定をロックしたまま変更し、target に保存する。
+/// target には lower を重ねた値と違うものだけを書く（ファイルが無ければ作る）。
+/// lower が無ければ target は単独の設定ファイルなので全体を書く。
+pub fn update_layer(
+    target: ConfigLayer,
+    lower: Vec<ConfigLayer>,
+    f: impl FnOnce(&mut Config) -> Result<()>,
+) -> Result<Config> {
+    let path = target.path.clone().context("書き込み先のファイルが指定されていません")?;
+    let _lock = crate::storage::lock(&path)?;
+    let base = if lower.is_empty() {
+        None
+    } else {
+        Some(merge_layers(lower.clone())?.config)
+    };
+    let mut config = if path.is_file() {
+        merge_layers(std::iter::once(target).chain(lower).collect())?.config
+    } else {
+        base.clone().unwrap_or_else(crate::default_config)
+    };
+    f(&mut config)?;
+    crate::save_locked(&path, &config, base.as_ref())?;
+    Ok(config)
+}
+
+/// レイヤー（優先度の高い順）を重ねて読み込む。
+pub fn merge_layers(layers: Vec<ConfigLayer>) -> Result<LayeredConfig> {
+    let mut merged = Table::new();
+    let mut origins = IndexMap::new();
+    for (index, layer) in layers.iter().enumerate().rev() {
+        let Some(path) = &layer.path else { continue };
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("設定ファイルの読み込みに失敗しました: {}", path.display()))?;
+        let mut doc = content
+            .parse::<DocumentMut>()
+            .with_context(|| format!("設定ファイルの解析に失敗しました: {}", path.display()))?;
+        // 形式バージョンはレイヤーごとに違いうるので、重ねる前にそれぞれ移行する
+        let role = if merged.is_empty() {
+            DocumentRole::Standalone
+        } else {
+            DocumentRole::Overlay
+        };
+        migrate_document_as(&mut doc, role)
+            .with_context(|| format!("設定ファイルの移行に失敗しました: {}", path.display()))?;
+        overlay(&mut merged, doc.as_table(), index, "", &mut origins);
+    }
+
+    let doc = DocumentMut::from(merged);
+    let config: Config = toml::from_str(&doc.to_string()).with_context(|| {
+        let paths: Vec<String> = layers.iter().map(ToString::to_string).collect();
+        format!("設定ファイルの解析に失敗しました: {}", paths.join(", "))
+    })?;
+
+    Ok(LayeredConfig {
+        config,
+        layers,
+        origins,
+    })
+}
+
+/// 組み込みデフォルトだけのレイヤー。
+fn embedded() -> LayeredConfig {
+    let mut origins = IndexMap::new();
+    if let Ok(doc) = crate::embedded_default_toml().parse::<DocumentMut>() {
+        record(doc.as_table(), 0, "", &mut origins);
+    }
+    LayeredConfig {
+        config: crate::default_config(),
+        layers: vec![ConfigLayer {
+            kind: LayerKind::Embedded,
+            path: None,
+        }],
+        origins,
+    }
+}
+
+/// 上のレイヤーにあれば下のレイヤーの表をまるごと置き換える表（名前付きエントリの一覧）。
+/// 下のレイヤーのエントリの削除・名前変更を上のレイヤーで表せるようにする。
+const OWNED_TABLES: &[&str] = &[
+    "search",
+    "folders",
+    "apps",
+    "commands",
+    "snippets",
+    "symbols",
+    "editing.keys",
+    "profiles",
+];
+
+/// base に上位レイヤーの値を重ねる。テーブル同士は再帰的にマージし（OWNED_TABLES は除く）、
+/// それ以外は置き換える。
+fn overlay(
+    base: &mut Table,
+    upper: &Table,
+    layer: usize,
+    prefix: &str,
+    origins: &mut IndexMap<String, usize>,
+) {
+    for (key, item) in upper.iter() {
+        let path = join_key(prefix, key);
+        match (base.get_mut(key).and_then(Item::as_table_mut), item.as_table()) {
+            (Some(base_table), Some(upper_table)) if !OWNED_TABLES.contains(&path.as_str()) => {
+                overlay(base_table, upper_table, layer, &path, origins);
+            }
+            _ => {
+                let nested = format!("{}.", path);
+                origins.retain(|k, _| *k != path && !k.starts_with(&nested));
+                base.insert(key, item.clone());
+                match item.as_table() {
+                    Some(table) => record(table, layer, &path, origins),
+                    None => {
+                        origins.insert(path, layer);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// テーブル内の値（テーブル以外）の由来を記録する。
+fn record(table: &Table, layer: usize, prefix: &str, origins: &mut IndexMap<String, usize>) {
+    for (key, item) in table.iter() {
+        let path = join_key(prefix, key);
+        match item.as_table() {
+            Some(child) => record(child, layer, &path, origins),
+            None => {
+                origins.insert(path, layer);
+            }
+        }
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyedEntry;
+
+    fn write_layer(dir: &Path, name: &str, kind: LayerKind, content: &str) -> ConfigLayer {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        ConfigLayer {
+            kind,
+            path: Some(path),
+        }
+    }
+
+    #[test]
+    fn test_merge_layers_deep_merges_sections() {
+        let dir = std::env::temp_dir().join("muhenkan_test_merge_layers");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let user = write_layer(
+            &dir,
+            "user.toml",
+            LayerKind::User,
+            r#"
+            config_version = 1
+            [search]
+            Google = {key = "s", url = "https://www.google.co.jp/search?q={query}"}
+            [timestamp]
+            delimiter = "-"
+            "#,
+        );
+        let exe = write_layer(
+            &dir,
+            "exe.toml",
+            LayerKind::ExeDir,
+            r#"
+            config_version = 1
+            [search]
+            Google = {key = "g", url = "https://www.google.com/search?q={query}"}
+            Wiki = {key = "w", url = "https://ja.wikipedia.org/w/index.php?search={query}"}
+            [folders]
+            docs = {key = "1", path = "~/Documents"}
+            [timestamp]
+            format = "%Y-%m-%d"
+            "#,
+        );
+        let layered = merge_layers(vec![user, exe]).unwrap();
+
+        let config = &layered.config;
+        // エントリの表は上のレイヤーのものに置き換わる
+        assert_eq!(config.search["Google"].dispatch_key(), Some("s"));
+        assert!(!config.search.contains_key("Wiki"));
+        assert_eq!(config.folders["docs"].path(), "~/Documents");
+        assert_eq!(config.timestamp.format, "%Y-%m-%d");
+        assert_eq!(config.timestamp.delimiter, "-");
+
+        assert_eq!(layered.origin("search.Google").unwrap().kind, LayerKind::User);
+        assert!(layered.origin("search.Wiki").is_none());
+        assert_eq!(layered.origin("folders.docs").unwrap().kind, LayerKind::ExeDir);
+        assert_eq!(layered.origin("timestamp.delimiter").unwrap().kind, LayerKind::User);
+        assert_eq!(layered.origin("timestamp.format").unwrap().kind, LayerKind::ExeDir);
+        assert!(layered.origin("timestamp.position").is_none());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_merge_layers_migrates_each_layer() {
+        let dir = std::env::temp_dir().join("muhenkan_test_merge_mixed_versions");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let user = write_layer(
+            &dir,
+            "user.toml",
+            LayerKind::User,
+            "config_version = 1\n[timestamp]\ndelimiter = \"-\"\n",
+        );
+        // 旧形式（値だけのエントリ）の下のレイヤー
+        let exe = write_layer(
+            &dir,
+            "exe.toml",
+            LayerKind::ExeDir,
+            "[search]\ngoogle = \"https://www.google.com/search?q={query}\"\n[folders]\ndocs = \"~/Documents\"\n",
+        );
+        let config = merge_layers(vec![user, exe]).unwrap().config;
+        assert_eq!(config.search["google"].url, "https://www.google.com/search?q={query}");
+        assert_eq!(config.folders["docs"].path(), "~/Documents");
+        assert_eq!(config.timestamp.delimiter, "-");
+
+        // 旧形式の上のレイヤーには、無いセクションのデフォルトを補わない
+        let env = write_layer(
+            &dir,
+            "env.toml",
+            LayerKind::Env,
+            "[search]\nbing = \"https://www.bing.com/search?q={query}\"\n",
+        );
+        let exe = write_layer(
+            &dir,
+            "exe.toml",
+            LayerKind::ExeDir,
+            "config_version = 1\n[folders]\ndocs = {path = \"~/Documents\"}\n",
+        );
+        let config = merge_layers(vec![env, exe]).unwrap().config;
+        assert!(config.search.contains_key("bing"));
+        assert_eq!(config.folders.keys().collect::<Vec<_>>(), vec!["docs"]);
+        assert!(config.apps.is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_update_layer_writes_only_changes() {
+        let dir = std::env::temp_dir().join("muhenkan_test_update_layer");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let env = write_layer(
+            &dir,
+            "env.toml",
+            LayerKind::Env,
+            "[search]\nBing = {key = \"b\", url = \"https://www.bing.com/search?q={query}\"}\n",
+        );
+        let user_content = r#"
+            config_version = 1
+            [folders]
+            docs = {key = "1", path = "~/Documents"}
+            [profiles."執筆".search]
+            Wiki = {key = "w", url = "https://ja.wikipedia.org/w/index.php?search={query}"}
+            [timestamp]
+            format = "%Y-%m-%d"
+            position = "after"
+            "#;
+        let user = write_layer(&dir, "user.toml", LayerKind::User, user_content);
+        let env_path = env.path.clone().unwrap();
+
+        // 重ねた値（after）をトグルし、変わった値だけを書き込み先に書く
+        let config = update_layer(env.clone(), vec![user.clone()], |config| {
+            config.timestamp.position = config.timestamp.position.toggled();
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(config.timestamp.position, crate::TimestampPosition::Before);
+        let content = std::fs::read_to_string(&env_path).unwrap();
+        assert!(content.contains("position = \"before\""), "{}", content);
+        for absent in ["format", "delimiter", "punctuation_style", "[folders]", "[apps]", "profiles"] {
+            assert!(!content.contains(absent), "{} in {}", absent, content);
+        }
+        assert_eq!(std::fs::read_to_string(user.path.as_ref().unwrap()).unwrap(), user_content);
+
+        // 下のレイヤーのプロファイルに切り替えられる
+        update_layer(env.clone(), vec![user.clone()], |config| {
+            assert!(config.profiles.contains_key("執筆"));
+            config.active_profile = Some("執筆".to_string());
+            Ok(())
+        })
+        .unwrap();
+        let content = std::fs::read_to_string(&env_path).unwrap();
+        assert!(content.contains("active_profile = \"執筆\""), "{}", content);
+        assert!(!content.contains("Wiki"), "{}", content);
+
+        // 下のレイヤーの変更はそのまま反映される
+        let edited = user_content.replace("%Y-%m-%d", "%Y%m%d");
+        std::fs::write(user.path.as_ref().unwrap(), edited).unwrap();
+        let config = merge_layers(vec![env, user]).unwrap().config;
+        assert_eq!(config.timestamp.format, "%Y%m%d");
+        assert_eq!(config.timestamp.position, crate::TimestampPosition::Before);
+        assert_eq!(config.active_profile.as_deref(), Some("執筆"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_update_layer_removes_and_renames_inherited_entries() {
+        let dir = std::env::temp_dir().join("muhenkan_test_update_layer_remove");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let env = ConfigLayer {
+            kind: LayerKind::Env,
+            path: Some(dir.join("env.toml")),
+        };
+        let user_content = r#"
+            config_version = 1
+            [search]
+            Google = {key = "g", url = "https://www.google.com/search?q={query}"}
+            Wiki = {key = "w", url = "https://ja.wikipedia.org/w/index.php?search={query}"}
+            [folders]
+            docs = {key = "1", path = "~/Documents"}
+            [commands]
+            build = {key = "b", command = "make"}
+            "#;
+        let user = write_layer(&dir, "user.toml", LayerKind::User, user_content);
+
+        // 下のレイヤーのエントリを削除・名前変更する
+        update_layer(env.clone(), vec![user.clone()], |config| {
+            config.search.shift_remove("Wiki");
+            let docs = config.folders.shift_remove("docs").unwrap();
+            config.folders.insert("文書".to_string(), docs);
+            config.commands.clear();
+            Ok(())
+        })
+        .unwrap();
+        let content = std::fs::read_to_string(env.path.as_ref().unwrap()).unwrap();
+        assert!(content.contains("[commands]"), "{}", content);
+        assert!(!content.contains("[apps]"), "{}", content);
+        assert_eq!(std::fs::read_to_string(user.path.as_ref().unwrap()).unwrap(), user_content);
+
+        let config = merge_layers(vec![env, user]).unwrap().config;
+        assert_eq!(config.search.keys().collect::<Vec<_>>(), vec!["Google"]);
+        assert_eq!(config.folders.keys().collect::<Vec<_>>(), vec!["文書"]);
+        assert!(config.commands.is_empty());
+        assert_eq!(crate::validate(&config), vec![]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_update_layer_creates_standalone_file() {
+        let dir = std::env::temp_dir().join("muhenkan_test_update_layer_new");
+        std::fs::remove_dir_all(&dir).ok();
+        let path = dir.join("muhenkan-switch").join("config.toml");
+        let target = ConfigLayer {
+            kind: LayerKind::User,
+            path: Some(path.clone()),
+        };
+        // 下のレイヤーが無ければ、デフォルト設定を含めて全体を書く
+        let config = update_layer(target, Vec::new(), |config| {
+            config.timestamp.delimiter = "-".to_string();
+            Ok(())
+        })
+        .unwrap();
+        let loaded = crate::load_from(&path).unwrap();
+        assert_eq!(loaded.timestamp.delimiter, "-");
+        assert_eq!(loaded.folders, config.folders);
+        assert!(!loaded.folders.is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_overlay_replaces_non_table_values_wholesale() {
+        let mut base: DocumentMut = "[search]\nGoogle = {key = \"g\", url = \"a\"}\n".parse().unwrap();
+        let upper: DocumentMut = "search = {Bing = {url = \"b\"}}\n".parse().unwrap();
+        let mut origins = IndexMap::new();
+        record(base.as_table(), 1, "", &mut origins);
+        overlay(base.as_table_mut(), upper.as_table(), 0, "", &mut origins);
+        // インラインテーブルの search はセクションごと置き換わる
+        assert!(base["search"].get("Google").is_none());
+        assert_eq!(origins.get("search"), Some(&0));
+        assert!(!origins.contains_key("search.Google"));
+    }
+
+    #[test]
+    fn test_embedded_layer_records_origins() {
+        let layered = embedded();
+        assert_eq!(layered.layers[0].kind, LayerKind::Embedded);
+        assert_eq!(
+            layered.origin("punctuation_style").unwrap().kind,
+            LayerKind::Embedded
+        );
+    }
+
+    #[test]
+    fn test_layer_display() {
+        let layer = ConfigLayer {
+            kind: LayerKind::User,
+            path: Some(PathBuf::from("/home/u/.config/muhenkan-switch/config.toml")),
+        };
+        assert_eq!(
+            layer.to_string(),
+            "ユーザー設定 (/home/u/.config/muhenkan-switch/config.toml)"
+        );
+    }
+}
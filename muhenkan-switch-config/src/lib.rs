@@ -1,13 +1,36 @@
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
 use std::path::PathBuf;
-
+use std::str::FromStr;
+use toml_edit::{InlineTable, Item, Table, TableLike, Value};
+
+pub mod diagnostic;
+pub mod diff;
+pub mod extra_layers;
+pub mod kbd;
+pub mod keys;
+pub mod layers;
+pub mod migrate;
+pub mod schema;
+pub mod storage;
 pub mod svg;
+pub mod timestamp;
+pub mod unknown_keys;
+
+pub use diagnostic::{has_errors, Diagnostic, DiagnosticKind, Severity};
+pub use keys::DispatchKey;
+pub use migrate::{MigrationReport, CONFIG_VERSION};
+
+use diagnostic::entry_path;
 
 // ── Dispatch keys ──
 
-/// kbd ファイルでディスパッチに割り当てられている物理キーの一覧。
+/// 割当の有無にかかわらず kbd でディスパッチに回す物理キーの一覧（左手側）。
+/// これ以外の割当可能キー（keys::assignable_keys）は config で割り当てた場合のみ回す。
 pub const DISPATCH_KEYS: &[&str] = &[
     "1", "2", "3", "4", "5",
     "q", "w", "e", "r", "t",
@@ -15,12 +38,560 @@ pub const DISPATCH_KEYS: &[&str] = &[
     "b",
 ];
 
+// ── Setting values ──
+
+/// タイムスタンプをファイル名の前後どちらに付けるか（timestamp.position）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TimestampPosition {
+    #[default]
+    Before,
+    After,
+}
+
+impl TimestampPosition {
+    pub const ALL: [Self; 2] = [Self::Before, Self::After];
+
+    /// config.toml での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+        }
+    }
+
+    /// 前後を入れ替えた値。
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Before => Self::After,
+            Self::After => Self::Before,
+        }
+    }
+}
+
+/// 無変換+「,」「.」で入力する句読点の組（punctuation_style）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PunctuationStyle {
+    /// 、。
+    #[default]
+    TenMaru,
+    /// ，．
+    CommaPeriod,
+    /// ，。
+    CommaMaru,
+    /// 、．
+    TenPeriod,
+}
+
+impl PunctuationStyle {
+    pub const ALL: [Self; 4] = [Self::TenMaru, Self::CommaPeriod, Self::CommaMaru, Self::TenPeriod];
+
+    /// config.toml での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TenMaru => "、。",
+            Self::CommaPeriod => "，．",
+            Self::CommaMaru => "，。",
+            Self::TenPeriod => "、．",
+        }
+    }
+
+    /// 「,」「.」に割り当てる文字（読点, 句点）。
+    pub fn chars(self) -> (char, char) {
+        match self {
+            Self::TenMaru => ('、', '。'),
+            Self::CommaPeriod => ('，', '．'),
+            Self::CommaMaru => ('，', '。'),
+            Self::TenPeriod => ('、', '．'),
+        }
+    }
+}
+
+/// キーボードの物理配列（layout）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Layout {
+    /// JIS 配列（日本語キーボード）
+    #[default]
+    Jis,
+    /// US 配列（ANSI）
+    Ansi,
+}
+
+impl Layout {
+    pub const ALL: [Self; 2] = [Self::Jis, Self::Ansi];
+
+    /// config.toml での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Jis => "jis",
+            Self::Ansi => "ansi",
+        }
+    }
+}
+
+/// レイヤーを切り替えるトリガーキー（trigger.key）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TriggerKey {
+    Muhenkan,
+    Henkan,
+    /// macOS (JIS) の英数キー
+    Eisu,
+    CapsLock,
+    Space,
+    RightAlt,
+}
+
+impl TriggerKey {
+    pub const ALL: [Self; 6] = [
+        Self::Muhenkan,
+        Self::Henkan,
+        Self::Eisu,
+        Self::CapsLock,
+        Self::Space,
+        Self::RightAlt,
+    ];
+
+    /// config.toml での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Muhenkan => "muhenkan",
+            Self::Henkan => "henkan",
+            Self::Eisu => "eisu",
+            Self::CapsLock => "caps_lock",
+            Self::Space => "space",
+            Self::RightAlt => "right_alt",
+        }
+    }
+
+    /// kanata でのキー名。
+    pub fn kanata_name(self) -> &'static str {
+        match self {
+            Self::Muhenkan => "muhenkan",
+            Self::Henkan => "henkan",
+            Self::Eisu => "eisu",
+            Self::CapsLock => "caps",
+            Self::Space => "spc",
+            Self::RightAlt => "ralt",
+        }
+    }
+
+    /// 表示用のキー名（SVG・ヘルプ）。
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Muhenkan => "無変換",
+            Self::Henkan => "変換",
+            Self::Eisu => "英数",
+            Self::CapsLock => "CapsLock",
+            Self::Space => "Space",
+            Self::RightAlt => "右Alt",
+        }
+    }
+
+    /// 未指定時のトリガーキー。US 配列では CapsLock、macOS (JIS) では「英数」キーを使う。
+    pub fn default_for(layout: Layout) -> Self {
+        match layout {
+            Layout::Ansi => Self::CapsLock,
+            Layout::Jis if cfg!(target_os = "macos") => Self::Eisu,
+            Layout::Jis => Self::Muhenkan,
+        }
+    }
+
+    /// JIS 配列にしかないキーかどうか。
+    pub fn is_jis_only(self) -> bool {
+        matches!(self, Self::Muhenkan | Self::Henkan | Self::Eisu)
+    }
+}
+
+/// トリガーキーの tap / hold の判定方法（trigger.behavior）。kanata のアクション名と同じ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TapHoldBehavior {
+    /// hold_timeout だけ押し続けるとレイヤーを切り替える
+    #[default]
+    TapHold,
+    /// 押している間に他のキーを押すとすぐにレイヤーを切り替える
+    TapHoldPress,
+    /// 押している間に他のキーを押して離すとレイヤーを切り替える
+    TapHoldRelease,
+}
+
+impl TapHoldBehavior {
+    pub const ALL: [Self; 3] = [Self::TapHold, Self::TapHoldPress, Self::TapHoldRelease];
+
+    /// config.toml・kanata での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TapHold => "tap-hold",
+            Self::TapHoldPress => "tap-hold-press",
+            Self::TapHoldRelease => "tap-hold-release",
+        }
+    }
+}
+
+/// トリガーキーのダブルタップで実行する操作（trigger.double_tap）。
+/// 組み込みの操作名以外はエントリ名として扱い、割当キーと同じ DispatchAction で実行する。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum DoubleTap {
+    /// IME のオン・オフ（半角/全角キー）
+    Ime,
+    /// GUI 設定ウィンドウを前面に出す
+    Gui,
+    /// 次の1キーだけレイヤーを有効にする（片手で使う）
+    OneShot,
+    /// [search] / [folders] / [apps] / [commands] / [snippets] のエントリ名
+    Entry(String),
+}
+
+impl DoubleTap {
+    /// 組み込みの操作（config.toml での表記）。
+    pub const BUILTIN: [Self; 3] = [Self::Ime, Self::Gui, Self::OneShot];
+
+    /// config.toml での表記。
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Ime => "ime",
+            Self::Gui => "gui",
+            Self::OneShot => "one-shot",
+            Self::Entry(name) => name,
+        }
+    }
+}
+
+impl From<String> for DoubleTap {
+    fn from(s: String) -> Self {
+        Self::BUILTIN
+            .into_iter()
+            .find(|builtin| builtin.as_str() == s)
+            .unwrap_or(Self::Entry(s))
+    }
+}
+
+impl From<DoubleTap> for String {
+    fn from(value: DoubleTap) -> String {
+        value.as_str().to_string()
+    }
+}
+
+impl JsonSchema for DoubleTap {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "DoubleTap".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let builtin: Vec<&str> = Self::BUILTIN.iter().map(|v| v.as_str()).collect();
+        schemars::json_schema!({
+            "anyOf": [
+                { "type": "string", "enum": builtin },
+                { "type": "string", "description": "エントリ名" },
+            ],
+        })
+    }
+}
+
+/// 編集レイヤーのキーに割り当てる操作（editing.keys の値）。kanata のアクションと同じ表記。
+/// kbd のアクションと SVG のラベルはどちらもこの表から作る。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum EditAction {
+    Left,
+    Down,
+    Up,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    DocumentStart,
+    DocumentEnd,
+    Backspace,
+    Delete,
+    WordBackspace,
+    WordDelete,
+    Escape,
+    Enter,
+    Tab,
+}
+
+impl EditAction {
+    pub const ALL: [Self; 19] = [
+        Self::Left,
+        Self::Down,
+        Self::Up,
+        Self::Right,
+        Self::WordLeft,
+        Self::WordRight,
+        Self::Home,
+        Self::End,
+        Self::PageUp,
+        Self::PageDown,
+        Self::DocumentStart,
+        Self::DocumentEnd,
+        Self::Backspace,
+        Self::Delete,
+        Self::WordBackspace,
+        Self::WordDelete,
+        Self::Escape,
+        Self::Enter,
+        Self::Tab,
+    ];
+
+    /// config.toml・kanata での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Down => "down",
+            Self::Up => "up",
+            Self::Right => "right",
+            Self::WordLeft => "C-left",
+            Self::WordRight => "C-right",
+            Self::Home => "home",
+            Self::End => "end",
+            Self::PageUp => "pgup",
+            Self::PageDown => "pgdn",
+            Self::DocumentStart => "C-home",
+            Self::DocumentEnd => "C-end",
+            Self::Backspace => "bspc",
+            Self::Delete => "del",
+            Self::WordBackspace => "C-bspc",
+            Self::WordDelete => "C-del",
+            Self::Escape => "esc",
+            Self::Enter => "ret",
+            Self::Tab => "tab",
+        }
+    }
+
+    /// 表示用のラベル（SVG）。
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Left => "←",
+            Self::Down => "↓",
+            Self::Up => "↑",
+            Self::Right => "→",
+            Self::WordLeft => "単語←",
+            Self::WordRight => "単語→",
+            Self::Home => "Home",
+            Self::End => "End",
+            Self::PageUp => "PgUp",
+            Self::PageDown => "PgDn",
+            Self::DocumentStart => "文頭",
+            Self::DocumentEnd => "文末",
+            Self::Backspace => "BS",
+            Self::Delete => "Del",
+            Self::WordBackspace => "単語BS",
+            Self::WordDelete => "単語Del",
+            Self::Escape => "Esc",
+            Self::Enter => "Enter",
+            Self::Tab => "Tab",
+        }
+    }
+}
+
+/// 編集レイヤーのプリセット（editing.preset）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum EditingPreset {
+    /// 右手 HJKL でカーソル移動
+    #[default]
+    Vim,
+    /// Ctrl+B/F/P/N 風（左手のキーも使う）
+    Emacs,
+    /// 左手 WASD でカーソル移動
+    Wasd,
+    /// プリセットを使わず editing.keys だけを使う
+    None,
+}
+
+impl EditingPreset {
+    pub const ALL: [Self; 4] = [Self::Vim, Self::Emacs, Self::Wasd, Self::None];
+
+    /// config.toml での表記。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Vim => "vim",
+            Self::Emacs => "emacs",
+            Self::Wasd => "wasd",
+            Self::None => "none",
+        }
+    }
+
+    /// プリセットのキー → 操作の一覧。
+    pub fn keys(self) -> &'static [(&'static str, EditAction)] {
+        use EditAction::*;
+        match self {
+            Self::Vim => &[
+                ("h", Left),
+                ("j", Down),
+                ("k", Up),
+                ("l", Right),
+                ("u", WordLeft),
+                ("i", WordRight),
+                ("y", Home),
+                ("o", End),
+                ("n", Backspace),
+                ("m", Delete),
+                (";", Escape),
+            ],
+            Self::Emacs => &[
+                ("b", Left),
+                ("n", Down),
+                ("p", Up),
+                ("f", Right),
+                ("a", Home),
+                ("e", End),
+                ("h", Backspace),
+                ("d", Delete),
+                ("g", Escape),
+            ],
+            Self::Wasd => &[
+                ("a", Left),
+                ("s", Down),
+                ("w", Up),
+                ("d", Right),
+                ("q", WordLeft),
+                ("e", WordRight),
+                ("r", Home),
+                ("f", End),
+                ("t", Backspace),
+                ("g", Delete),
+            ],
+            Self::None => &[],
+        }
+    }
+}
+
+/// 列挙型の設定値に共通の文字列変換・スキーマを実装する。
+macro_rules! setting_value {
+    ($ty:ident, $field:literal) => {
+        impl FromStr for $ty {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, String> {
+                Self::ALL.into_iter().find(|v| v.as_str() == s).ok_or_else(|| {
+                    let values: Vec<String> =
+                        Self::ALL.iter().map(|v| format!("\"{}\"", v.as_str())).collect();
+                    format!(
+                        "{} に \"{}\" は指定できません（{} のいずれかを指定してください）",
+                        $field,
+                        s,
+                        values.join(", ")
+                    )
+                })
+            }
+        }
+
+        impl TryFrom<String> for $ty {
+            type Error = String;
+
+            fn try_from(s: String) -> Result<Self, String> {
+                s.parse()
+            }
+        }
+
+        impl From<$ty> for String {
+            fn from(value: $ty) -> String {
+                value.as_str().to_string()
+            }
+        }
+
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl JsonSchema for $ty {
+            fn inline_schema() -> bool {
+                true
+            }
+
+            fn schema_name() -> Cow<'static, str> {
+                stringify!($ty).into()
+            }
+
+            fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+                let values: Vec<&str> = Self::ALL.iter().map(|v| v.as_str()).collect();
+                schemars::json_schema!({
+                    "type": "string",
+                    "enum": values,
+                })
+            }
+        }
+    };
+}
+
+setting_value!(TimestampPosition, "timestamp.position");
+setting_value!(PunctuationStyle, "punctuation_style");
+setting_value!(Layout, "layout");
+setting_value!(TriggerKey, "trigger.key");
+setting_value!(TapHoldBehavior, "trigger.behavior");
+setting_value!(EditAction, "editing.keys");
+setting_value!(EditingPreset, "editing.preset");
+
 // ── Types ──
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// 割当キー（key / shift_key）を持つエントリ。
+pub trait KeyedEntry {
+    /// 指定レイヤーの割当キーのフィールド（key / shift_key）。
+    fn key_slot(&self, modifier: Modifier) -> &Option<DispatchKey>;
+
+    fn key_slot_mut(&mut self, modifier: Modifier) -> &mut Option<DispatchKey>;
+
+    fn dispatch_key(&self) -> Option<&str> {
+        self.key_for(Modifier::None)
+    }
+
+    /// 指定レイヤーの割当キーを返す。
+    fn key_for(&self, modifier: Modifier) -> Option<&str> {
+        self.key_slot(modifier).as_ref().map(DispatchKey::as_str)
+    }
+}
+
+/// key / shift_key フィールドを持つエントリに KeyedEntry を実装する。
+macro_rules! keyed_entry {
+    ($($ty:ident),*) => {
+        $(
+            impl KeyedEntry for $ty {
+                fn key_slot(&self, modifier: Modifier) -> &Option<DispatchKey> {
+                    match modifier {
+                        Modifier::None => &self.key,
+                        Modifier::Shift => &self.shift_key,
+                    }
+                }
+
+                fn key_slot_mut(&mut self, modifier: Modifier) -> &mut Option<DispatchKey> {
+                    match modifier {
+                        Modifier::None => &mut self.key,
+                        Modifier::Shift => &mut self.shift_key,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+keyed_entry!(SearchEntry, FolderEntry, AppEntry, CommandEntry, SnippetEntry);
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct SearchEntry {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub key: Option<String>,
+    /// 無変換+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub key: Option<DispatchKey>,
+    /// 無変換+Shift+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub shift_key: Option<DispatchKey>,
     pub url: String,
 }
 
@@ -28,16 +599,19 @@ impl SearchEntry {
     pub fn url(&self) -> &str {
         &self.url
     }
-
-    pub fn dispatch_key(&self) -> Option<&str> {
-        self.key.as_deref()
-    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct FolderEntry {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub key: Option<String>,
+    /// 無変換+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub key: Option<DispatchKey>,
+    /// 無変換+Shift+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub shift_key: Option<DispatchKey>,
     pub path: String,
 }
 
@@ -45,16 +619,19 @@ impl FolderEntry {
     pub fn path(&self) -> &str {
         &self.path
     }
-
-    pub fn dispatch_key(&self) -> Option<&str> {
-        self.key.as_deref()
-    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct AppEntry {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub key: Option<String>,
+    /// 無変換+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub key: Option<DispatchKey>,
+    /// 無変換+Shift+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub shift_key: Option<DispatchKey>,
     pub process: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub command: Option<String>,
@@ -69,71 +646,413 @@ impl AppEntry {
     pub fn command(&self) -> Option<&str> {
         Some(self.command.as_deref().unwrap_or(&self.process))
     }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct CommandEntry {
+    /// 無変換+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub key: Option<DispatchKey>,
+    /// 無変換+Shift+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub shift_key: Option<DispatchKey>,
+    /// 実行するシェルコマンド（{selection} などのプレースホルダを展開）
+    pub command: String,
+    /// 標準出力をトースト通知で表示するか
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub show_output: bool,
+}
+
+impl CommandEntry {
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SnippetEntry {
+    /// 無変換+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub key: Option<DispatchKey>,
+    /// 無変換+Shift+キー で発動する割当キー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "DispatchKey")]
+    pub shift_key: Option<DispatchKey>,
+    /// 入力する定型文（{date:%Y-%m-%d} などの変数を展開）
+    pub text: String,
+}
+
+impl SnippetEntry {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// 前面アプリごとの割当キーの上書き（[[overrides]]）。
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct OverrideEntry {
+    /// 前面アプリのプロセス名（Linux では WM_CLASS も可）。大文字小文字と .exe は無視
+    pub process: String,
+    /// 割当キー → エントリ名（search / folders / apps / commands / snippets のいずれか）
+    #[serde(default)]
+    pub keys: IndexMap<DispatchKey, String>,
+    /// 無変換+Shift+キー の上書き
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub shift_keys: IndexMap<DispatchKey, String>,
+}
+
+impl OverrideEntry {
+    /// 指定レイヤーの上書き表を返す。
+    pub fn keys_for(&self, modifier: Modifier) -> &IndexMap<DispatchKey, String> {
+        match modifier {
+            Modifier::None => &self.keys,
+            Modifier::Shift => &self.shift_keys,
+        }
+    }
+
+    /// 前面アプリの識別名のいずれかが process と一致するか。
+    pub fn matches(&self, foreground: &[String]) -> bool {
+        let process = normalize_app_name(&self.process);
+        foreground.iter().any(|name| normalize_app_name(name) == process)
+    }
+}
+
+/// アプリ名の比較用に小文字化し、末尾の .exe を取り除く。
+fn normalize_app_name(name: &str) -> String {
+    let lower = name.trim().to_lowercase();
+    match lower.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => lower,
+    }
+}
+
+/// 割当キーと同時に押されている修飾キー（無変換レイヤー内のサブレイヤー）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Modifier {
+    /// 無変換+キー
+    #[default]
+    None,
+    /// 無変換+Shift+キー
+    Shift,
+}
+
+impl Modifier {
+    /// 両レイヤーの一覧。
+    pub const ALL: [Modifier; 2] = [Modifier::None, Modifier::Shift];
+
+    /// エントリで割当キーを指定するフィールド名。
+    pub fn field(self) -> &'static str {
+        match self {
+            Modifier::None => "key",
+            Modifier::Shift => "shift_key",
+        }
+    }
 
-    pub fn dispatch_key(&self) -> Option<&str> {
-        self.key.as_deref()
+    /// 表示用の割当キー表記（例: "g", "Shift+g"）。
+    pub fn describe(self, key: &str) -> String {
+        match self {
+            Modifier::None => key.to_string(),
+            Modifier::Shift => format!("Shift+{}", key),
+        }
     }
 }
 
+/// 名前付きプロファイル（[profiles.NAME]）。
+/// 定義したセクションだけがトップレベルの同名セクションを置き換える。
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<IndexMap<String, SearchEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folders: Option<IndexMap<String, FolderEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apps: Option<IndexMap<String, AppEntry>>,
+}
+
+/// プロファイル未選択時の表示名。
+pub const DEFAULT_PROFILE_LABEL: &str = "標準";
+
 #[derive(Debug, Clone)]
 pub enum DispatchAction {
     Search { engine: String },
     OpenFolder { target: String },
     SwitchApp { target: String },
+    RunCommand { target: String },
+    Snippet { target: String },
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Config {
+    /// 設定ファイルの形式バージョン（旧形式は読み込み時に migrate で移行される）
+    #[serde(default = "migrate::default_version")]
+    pub config_version: u32,
+    /// Web 検索（url の {query} が選択テキストに置き換わる）
     #[serde(default)]
     pub search: IndexMap<String, SearchEntry>,
+    /// フォルダを開く
     #[serde(default)]
     pub folders: IndexMap<String, FolderEntry>,
+    /// アプリの切り替え（起動していなければ起動）
     #[serde(default)]
     pub apps: IndexMap<String, AppEntry>,
+    /// 任意のコマンドの実行
+    #[serde(default)]
+    pub commands: IndexMap<String, CommandEntry>,
+    /// 定型文の入力
+    #[serde(default)]
+    pub snippets: IndexMap<String, SnippetEntry>,
+    /// 前面アプリごとの割当キーの上書き
+    #[serde(default)]
+    pub overrides: Vec<OverrideEntry>,
+    /// 選択中のプロファイル名（未設定ならトップレベルのセクションを使う）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// 名前付きプロファイル（search / folders / apps を切り替える）
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub profiles: IndexMap<String, Profile>,
+    /// ファイル名に付けるタイムスタンプ
     #[serde(default)]
     pub timestamp: TimestampConfig,
-    #[serde(default = "default_punctuation_style")]
-    pub punctuation_style: String,
-}
-
-fn default_punctuation_style() -> String {
-    "、。".to_string()
+    /// レイヤーを切り替えるトリガーキー
+    #[serde(default)]
+    pub trigger: TriggerConfig,
+    /// キーボードの物理配列
+    #[serde(default)]
+    pub layout: Layout,
+    /// 編集レイヤー（カーソル移動・削除）のキー
+    #[serde(default)]
+    pub editing: EditingConfig,
+    /// 無変換レイヤーから切り替える追加レイヤー
+    #[serde(default)]
+    pub extra_layers: ExtraLayersConfig,
+    /// 無変換+「,」「.」で入力される句読点
+    #[serde(default)]
+    pub punctuation_style: PunctuationStyle,
+    /// 無変換+キー で入力する記号（キー → 文字）。「,」「.」は punctuation_style より優先
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub symbols: IndexMap<String, char>,
 }
 
 impl Config {
-    /// 割当キーに対応するアクションを検索する。
-    pub fn dispatch_lookup(&self, key: &str) -> Option<DispatchAction> {
-        for (name, entry) in &self.search {
-            if entry.dispatch_key() == Some(key) {
+    /// 選択中のプロファイル（未選択または未定義なら None）。
+    pub fn profile(&self) -> Option<&Profile> {
+        self.active_profile
+            .as_deref()
+            .and_then(|name| self.profiles.get(name))
+    }
+
+    /// 選択中のプロファイルを反映した [search]。
+    pub fn active_search(&self) -> &IndexMap<String, SearchEntry> {
+        self.profile()
+            .and_then(|p| p.search.as_ref())
+            .unwrap_or(&self.search)
+    }
+
+    /// 選択中のプロファイルを反映した [folders]。
+    pub fn active_folders(&self) -> &IndexMap<String, FolderEntry> {
+        self.profile()
+            .and_then(|p| p.folders.as_ref())
+            .unwrap_or(&self.folders)
+    }
+
+    /// 選択中のプロファイルを反映した [apps]。
+    pub fn active_apps(&self) -> &IndexMap<String, AppEntry> {
+        self.profile()
+            .and_then(|p| p.apps.as_ref())
+            .unwrap_or(&self.apps)
+    }
+
+    /// 指定したプロファイルを選択した状態の設定を返す（None で標準）。
+    pub fn with_profile(&self, name: Option<&str>) -> Config {
+        Config {
+            active_profile: name.map(str::to_string),
+            ..self.clone()
+        }
+    }
+
+    /// 表示用のプロファイル名。
+    pub fn active_profile_label(&self) -> &str {
+        self.active_profile.as_deref().unwrap_or(DEFAULT_PROFILE_LABEL)
+    }
+
+    /// 標準 → 各プロファイル（定義順）→ 標準 の順で次のプロファイル名を返す。
+    pub fn next_profile(&self) -> Option<String> {
+        let names: Vec<&String> = self.profiles.keys().collect();
+        let next = match self.active_profile.as_ref() {
+            None => names.first(),
+            Some(current) => match names.iter().position(|n| *n == current) {
+                Some(i) => names.get(i + 1),
+                None => names.first(),
+            },
+        };
+        next.map(|n| n.to_string())
+    }
+
+    /// 割当キーと修飾キーに対応するアクションを検索する。
+    /// search / folders / apps は選択中のプロファイルのものを使う。
+    pub fn dispatch_lookup(&self, key: &str, modifier: Modifier) -> Option<DispatchAction> {
+        for (name, entry) in self.active_search() {
+            if entry.key_for(modifier) == Some(key) {
                 return Some(DispatchAction::Search {
                     engine: name.clone(),
                 });
             }
         }
-        for (name, entry) in &self.folders {
-            if entry.dispatch_key() == Some(key) {
+        for (name, entry) in self.active_folders() {
+            if entry.key_for(modifier) == Some(key) {
                 return Some(DispatchAction::OpenFolder {
                     target: name.clone(),
                 });
             }
         }
-        for (name, entry) in &self.apps {
-            if entry.dispatch_key() == Some(key) {
+        for (name, entry) in self.active_apps() {
+            if entry.key_for(modifier) == Some(key) {
                 return Some(DispatchAction::SwitchApp {
                     target: name.clone(),
                 });
             }
         }
+        for (name, entry) in &self.commands {
+            if entry.key_for(modifier) == Some(key) {
+                return Some(DispatchAction::RunCommand {
+                    target: name.clone(),
+                });
+            }
+        }
+        for (name, entry) in &self.snippets {
+            if entry.key_for(modifier) == Some(key) {
+                return Some(DispatchAction::Snippet {
+                    target: name.clone(),
+                });
+            }
+        }
         None
     }
+
+    /// 前面アプリ別の上書き（[[overrides]]）を考慮して割当キーのアクションを検索する。
+    /// foreground には前面アプリの識別名（プロセス名・WM_CLASS）を渡す。
+    pub fn dispatch_lookup_for(
+        &self,
+        key: &str,
+        modifier: Modifier,
+        foreground: &[String],
+    ) -> Option<DispatchAction> {
+        self.overrides
+            .iter()
+            .filter(|o| o.matches(foreground))
+            .find_map(|o| o.keys_for(modifier).get(key))
+            .and_then(|name| self.entry_action(name))
+            .or_else(|| self.dispatch_lookup(key, modifier))
+    }
+
+    /// エントリ名に対応するアクションを返す（同名があれば dispatch と同じ順で先勝ち）。
+    pub fn entry_action(&self, name: &str) -> Option<DispatchAction> {
+        let target = name.to_string();
+        if self.active_search().contains_key(name) {
+            Some(DispatchAction::Search { engine: target })
+        } else if self.active_folders().contains_key(name) {
+            Some(DispatchAction::OpenFolder { target })
+        } else if self.active_apps().contains_key(name) {
+            Some(DispatchAction::SwitchApp { target })
+        } else if self.commands.contains_key(name) {
+            Some(DispatchAction::RunCommand { target })
+        } else if self.snippets.contains_key(name) {
+            Some(DispatchAction::Snippet { target })
+        } else {
+            None
+        }
+    }
+
+    /// 使用するトリガーキー（未指定なら配列・OS ごとのデフォルト）。
+    pub fn trigger_key(&self) -> TriggerKey {
+        self.trigger.key.unwrap_or_else(|| TriggerKey::default_for(self.layout))
+    }
+
+    /// 編集レイヤーのキーと操作（物理配列順）。
+    /// 編集に使えないキーと、記号を割り当てたキーは除く。
+    pub fn editing_keys(&self) -> Vec<(&'static keys::PhysicalKey, EditAction)> {
+        let symbols = self.symbol_outputs();
+        keys::layout_keys(self.layout)
+            .filter(|k| keys::is_editing_key(self.layout, k.name))
+            .filter(|k| !symbols.iter().any(|(s, _)| s.name == k.name))
+            .filter_map(|k| Some((k, self.editing.action(k.name)?)))
+            .collect()
+    }
+
+    /// ダブルタップで実行するエントリのアクション（組み込みの操作・未設定なら None）。
+    pub fn double_tap_action(&self) -> Option<DispatchAction> {
+        match &self.trigger.double_tap {
+            Some(DoubleTap::Entry(name)) => self.entry_action(name),
+            _ => None,
+        }
+    }
+
+    /// 無変換+キー で入力する記号（物理配列順）。
+    /// punctuation_style の「,」「.」に [symbols] を重ね、記号を割り当てられないキーは除く。
+    pub fn symbol_outputs(&self) -> Vec<(&'static keys::PhysicalKey, char)> {
+        let (comma, period) = self.punctuation_style.chars();
+        keys::layout_keys(self.layout)
+            .filter_map(|k| {
+                let default = match k.name {
+                    "," => Some(comma),
+                    "." => Some(period),
+                    _ => None,
+                };
+                let output = self.symbols.get(k.name).copied().or(default)?;
+                keys::is_symbol_key(self.layout, k.name).then_some((k, output))
+            })
+            .collect()
+    }
+
+    /// 指定レイヤーでいずれかのエントリ（アプリ別の上書き・全プロファイルを含む）に
+    /// 割り当てられているキーを列挙する。
+    pub fn assigned_keys(&self, modifier: Modifier) -> impl Iterator<Item = &str> {
+        let search = self.search.values().filter_map(move |e| e.key_for(modifier));
+        let folders = self.folders.values().filter_map(move |e| e.key_for(modifier));
+        let apps = self.apps.values().filter_map(move |e| e.key_for(modifier));
+        let commands = self.commands.values().filter_map(move |e| e.key_for(modifier));
+        let snippets = self.snippets.values().filter_map(move |e| e.key_for(modifier));
+        let overrides = self
+            .overrides
+            .iter()
+            .flat_map(move |o| o.keys_for(modifier).keys().map(DispatchKey::as_str));
+        let profiles = self.profiles.values().flat_map(move |p| {
+            let search = p.search.iter().flat_map(|m| m.values());
+            let folders = p.folders.iter().flat_map(|m| m.values());
+            let apps = p.apps.iter().flat_map(|m| m.values());
+            search
+                .filter_map(move |e| e.key_for(modifier))
+                .chain(folders.filter_map(move |e| e.key_for(modifier)))
+                .chain(apps.filter_map(move |e| e.key_for(modifier)))
+        });
+        search
+            .chain(folders)
+            .chain(apps)
+            .chain(commands)
+            .chain(snippets)
+            .chain(overrides)
+            .chain(profiles)
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct TimestampConfig {
+    /// 日時のフォーマット（chrono の strftime 形式）
     #[serde(default = "default_format")]
     pub format: String,
-    #[serde(default = "default_position")]
-    pub position: String,
+    /// ファイル名の前 (before) / 後 (after) のどちらに付けるか
+    #[serde(default)]
+    pub position: TimestampPosition,
+    /// タイムスタンプとファイル名の区切り文字
     #[serde(default = "default_delimiter")]
     pub delimiter: String,
 }
@@ -142,74 +1061,160 @@ impl Default for TimestampConfig {
     fn default() -> Self {
         Self {
             format: default_format(),
-            position: default_position(),
+            position: TimestampPosition::default(),
             delimiter: default_delimiter(),
         }
     }
 }
 
-fn default_format() -> String {
+pub(crate) fn default_format() -> String {
     "%Y%m%d".to_string()
 }
 
-fn default_position() -> String {
-    "before".to_string()
-}
-
 fn default_delimiter() -> String {
     "_".to_string()
 }
 
-// ── Config path resolution ──
+/// レイヤーを切り替えるトリガーキーと tap / hold の判定（[trigger]）。
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct TriggerConfig {
+    /// トリガーキー（省略時は無変換、macOS は英数、US 配列は CapsLock）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "TriggerKey")]
+    pub key: Option<TriggerKey>,
+    /// タップと判定する時間（ミリ秒）
+    #[serde(default = "default_trigger_timeout")]
+    pub tap_timeout: u16,
+    /// 長押しと判定する時間（ミリ秒）
+    #[serde(default = "default_trigger_timeout")]
+    pub hold_timeout: u16,
+    /// tap / hold の判定方法
+    #[serde(default)]
+    pub behavior: TapHoldBehavior,
+    /// ダブルタップ（tap_timeout 以内に2回押す）で実行する操作
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub double_tap: Option<DoubleTap>,
+}
 
-/// config.toml のパスを決定する。
-/// 優先順位:
-/// 1. 実行ファイルと同じディレクトリの config.toml（インストール環境 / dev: ./bin/ 実行時）
-/// 2. CARGO_MANIFEST_DIR/../bin/config.toml（開発環境: cargo run 互換）
-/// 3. 見つからなければ None（default_config() の embedded config で補完）
-pub fn config_path() -> Option<PathBuf> {
-    // 1. 実行ファイルと同じディレクトリ
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(dir) = exe_path.parent() {
-            let path = dir.join("config.toml");
-            if path.exists() {
-                return Some(path);
-            }
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            key: None,
+            tap_timeout: default_trigger_timeout(),
+            hold_timeout: default_trigger_timeout(),
+            behavior: TapHoldBehavior::default(),
+            double_tap: None,
         }
     }
+}
 
-    // 2. ワークスペースルートの bin/（開発環境）
-    let bin_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .map(|p| p.join("bin"));
-    if let Some(ref dir) = bin_dir {
-        let path = dir.join("config.toml");
-        if path.exists() {
-            return Some(path);
-        }
+fn default_trigger_timeout() -> u16 {
+    200
+}
+
+/// 編集レイヤーのキー割り当て（[editing]）。
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct EditingConfig {
+    /// 元にするプリセット
+    #[serde(default)]
+    pub preset: EditingPreset,
+    /// プリセットへの追加・上書き（キー → kanata のアクション）
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub keys: IndexMap<String, EditAction>,
+}
+
+impl EditingConfig {
+    /// キーに割り当てた操作（editing.keys、なければプリセット）。
+    pub fn action(&self, key: &str) -> Option<EditAction> {
+        self.keys.get(key).copied().or_else(|| {
+            self.preset
+                .keys()
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, action)| *action)
+        })
     }
+}
+
+/// 追加レイヤーの有効・無効（[extra_layers]）。
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ExtraLayersConfig {
+    /// マウス操作レイヤー（無変換+Space）
+    #[serde(default)]
+    pub mouse: bool,
+    /// テンキーレイヤー（無変換+Tab）
+    #[serde(default)]
+    pub numpad: bool,
+}
 
-    None
+/// trigger.tap_timeout / hold_timeout に指定できる範囲（ミリ秒）。
+pub const TRIGGER_TIMEOUT_RANGE: std::ops::RangeInclusive<u16> = 1..=2000;
+
+// ── Config path resolution ──
+
+/// 書き込み先の config.toml のパスを決定する。
+/// 次の順で探し、最初に見つかったもの（最も優先度の高いレイヤー）を返す:
+/// 1. 環境変数 MUHENKAN_SWITCH_CONFIG
+/// 2. $XDG_CONFIG_HOME/muhenkan-switch/config.toml（ユーザーごと）
+/// 3. 実行ファイルと同じディレクトリの config.toml（インストール環境 / dev: ./bin/ 実行時）
+/// 4. CARGO_MANIFEST_DIR/../bin/config.toml（開発環境: cargo run 互換）
+/// 5. 見つからなければ None（default_config() の embedded config で補完）
+pub fn config_path() -> Option<PathBuf> {
+    layers::find_layers().into_iter().find_map(|layer| layer.path)
 }
 
 /// 指定パスから config.toml を読み込む。
+/// 旧形式のファイルはメモリ上で現在の形式に移行してから読み込む（ファイルは書き換えない）。
 pub fn load_from(path: &std::path::Path) -> Result<Config> {
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("設定ファイルの読み込みに失敗しました: {}", path.display()))?;
+    let mut doc = content
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("設定ファイルの解析に失敗しました: {}", path.display()))?;
+    let content = match migrate::migrate_document(&mut doc)? {
+        Some(_) => doc.to_string(),
+        None => content,
+    };
     let config: Config = toml::from_str(&content)
         .with_context(|| format!("設定ファイルの解析に失敗しました: {}", path.display()))?;
     Ok(config)
 }
 
-/// config.toml を自動検出して読み込む。見つからなければデフォルト値。
+/// config.toml を自動検出して読み込む。複数見つかった場合は重ねて読み込み、
+/// 見つからなければデフォルト値。
 pub fn load() -> Result<Config> {
-    match config_path() {
-        Some(path) => load_from(&path),
-        None => {
-            eprintln!("警告: config.toml が見つかりません。デフォルト値を使用します。");
-            Ok(default_config())
-        }
+    Ok(layers::load_layered()?.config)
+}
+
+/// load_from と同じく読み込み、あわせて不明なキー（typo など）の警告を返す。
+pub fn load_from_lenient(path: &std::path::Path) -> Result<(Config, Vec<Diagnostic>)> {
+    let config = load_from(path)?;
+    Ok((config, unknown_keys_in(path)))
+}
+
+/// load と同じく読み込み、あわせて各レイヤーのファイルの不明なキーの警告を返す。
+pub fn load_lenient() -> Result<(Config, Vec<Diagnostic>)> {
+    let layered = layers::load_layered()?;
+    let warnings = layered
+        .layers
+        .iter()
+        .filter_map(|layer| layer.path.as_deref())
+        .flat_map(unknown_keys_in)
+        .collect();
+    Ok((layered.config, warnings))
+}
+
+/// ファイルの不明なキーの警告（ファイル名付き）。
+fn unknown_keys_in(path: &std::path::Path) -> Vec<Diagnostic> {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let mut diagnostics = unknown_keys::check(&content);
+    for diagnostic in &mut diagnostics {
+        diagnostic.file = Some(path.to_path_buf());
     }
+    diagnostics
 }
 
 // デフォルト設定ファイルをコンパイル時にバイナリへ埋め込む。
@@ -219,16 +1224,20 @@ const DEFAULT_LINUX_CONFIG: &str = include_str!("../../config/default-linux.toml
 const DEFAULT_MACOS_CONFIG: &str = include_str!("../../config/default-macos.toml");
 const DEFAULT_CONFIG: &str = include_str!("../../config/default.toml");
 
+/// 現在の OS 用の埋め込みデフォルト設定（TOML）。
+pub(crate) fn embedded_default_toml() -> &'static str {
+    match std::env::consts::OS {
+        "windows" => DEFAULT_WINDOWS_CONFIG,
+        "macos" => DEFAULT_MACOS_CONFIG,
+        _ => DEFAULT_LINUX_CONFIG,
+    }
+}
+
 /// デフォルト設定を返す。
 /// バイナリに埋め込まれた OS 別デフォルト設定 → 共通デフォルト → 最小限フォールバックの順で試みる。
 pub fn default_config() -> Config {
     // 1. OS 別埋め込みデフォルト設定（コンパイル時にバイナリに組み込み済み）
-    let os_default = match std::env::consts::OS {
-        "windows" => DEFAULT_WINDOWS_CONFIG,
-        "macos" => DEFAULT_MACOS_CONFIG,
-        _ => DEFAULT_LINUX_CONFIG,
-    };
-    if let Ok(config) = toml::from_str(os_default) {
+    if let Ok(config) = toml::from_str(embedded_default_toml()) {
         return config;
     }
 
@@ -239,11 +1248,22 @@ pub fn default_config() -> Config {
 
     // 3. 最小限のフォールバック（通常はここに到達しない）
     Config {
+        config_version: CONFIG_VERSION,
         search: default_search_engines(),
         folders: IndexMap::new(),
         apps: IndexMap::new(),
+        commands: IndexMap::new(),
+        snippets: IndexMap::new(),
+        overrides: Vec::new(),
+        active_profile: None,
+        profiles: IndexMap::new(),
         timestamp: TimestampConfig::default(),
-        punctuation_style: default_punctuation_style(),
+        trigger: TriggerConfig::default(),
+        layout: Layout::default(),
+        editing: EditingConfig::default(),
+        extra_layers: ExtraLayersConfig::default(),
+        punctuation_style: PunctuationStyle::default(),
+        symbols: IndexMap::new(),
     }
 }
 
@@ -253,6 +1273,7 @@ fn default_search_engines() -> IndexMap<String, SearchEntry> {
         "google".to_string(),
         SearchEntry {
             key: None,
+            shift_key: None,
             url: "https://www.google.com/search?q={query}".to_string(),
         },
     );
@@ -261,238 +1282,1003 @@ fn default_search_engines() -> IndexMap<String, SearchEntry> {
 
 // ── Save (comment-preserving) ──
 
+// 値が同じか（文字列の引用符の種類などの表記の違いは無視する）
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        _ => false,
+    }
+}
+
+// 値を更新する。同じ値なら表記ごとそのまま残し、変わった場合も前後の装飾（行末コメントなど）は保持する
+fn update_value(existing: &mut Value, value: Value) {
+    match (existing, value) {
+        (Value::InlineTable(old), Value::InlineTable(new)) => sync_fields(old, new),
+        (existing, mut value) => {
+            if !same_value(existing, &value) {
+                *value.decor_mut() = existing.decor().clone();
+                *existing = value;
+            }
+        }
+    }
+}
+
+// テーブルのキーに値を設定する（既存の値はその場で更新する）
+fn set_value(table: &mut dyn TableLike, key: &str, value: Value) {
+    match table.get_mut(key) {
+        Some(Item::Value(existing)) => update_value(existing, value),
+        // [search.Google] のような標準テーブル形式のエントリ
+        Some(Item::Table(existing)) if value.is_inline_table() => {
+            if let Value::InlineTable(new) = value {
+                sync_fields(existing, new);
+            }
+        }
+        _ => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+// エントリのフィールドを new に合わせる（無くなったフィールドは削除する）
+fn sync_fields(table: &mut dyn TableLike, new: InlineTable) {
+    let stale: Vec<String> = table
+        .iter()
+        .map(|(k, _)| k.to_string())
+        .filter(|k| !new.contains_key(k))
+        .collect();
+    for k in stale {
+        table.remove(&k);
+    }
+    for (k, v) in new {
+        set_value(table, &k, v);
+    }
+}
+
+// セクションのエントリを entries に合わせる。
+// 既存のエントリはその場で更新してコメントを保持し、無くなったエントリだけを削除、
+// 新しいエントリを追加したうえで entries の順（GUI での並び順）に並べる。
+fn sync_entries(table: &mut Table, entries: Vec<(&str, InlineTable)>) {
+    let order: IndexMap<String, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.to_string(), i))
+        .collect();
+    let stale: Vec<String> = table
+        .iter()
+        .map(|(k, _)| k.to_string())
+        .filter(|k| !order.contains_key(k))
+        .collect();
+    for k in stale {
+        table.remove(&k);
+    }
+    for (name, inline) in entries {
+        set_value(table, name, Value::InlineTable(inline));
+    }
+    table.sort_values_by(|a, _, b, _| order.get(a.get()).cmp(&order.get(b.get())));
+}
+
+// 割当キー・Shift 割当キーをインラインテーブルに書き出す
+fn key_fields(key: &Option<DispatchKey>, shift_key: &Option<DispatchKey>) -> InlineTable {
+    let mut inline = InlineTable::new();
+    if let Some(dk) = key {
+        inline.insert("key", Value::from(dk.as_str()));
+    }
+    if let Some(sk) = shift_key {
+        inline.insert("shift_key", Value::from(sk.as_str()));
+    }
+    inline
+}
+
+// セクションの中身を entries に合わせる（トップレベルとプロファイルで共用）
+pub(crate) fn fill_search(table: &mut Table, entries: &IndexMap<String, SearchEntry>) {
+    let entries = entries
+        .iter()
+        .map(|(name, entry)| {
+            let mut inline = key_fields(&entry.key, &entry.shift_key);
+            inline.insert("url", Value::from(entry.url.as_str()));
+            (name.as_str(), inline)
+        })
+        .collect();
+    sync_entries(table, entries);
+}
+
+pub(crate) fn fill_folders(table: &mut Table, entries: &IndexMap<String, FolderEntry>) {
+    let entries = entries
+        .iter()
+        .map(|(name, entry)| {
+            let mut inline = key_fields(&entry.key, &entry.shift_key);
+            inline.insert("path", Value::from(entry.path.as_str()));
+            (name.as_str(), inline)
+        })
+        .collect();
+    sync_entries(table, entries);
+}
+
+pub(crate) fn fill_apps(table: &mut Table, entries: &IndexMap<String, AppEntry>) {
+    let entries = entries
+        .iter()
+        .map(|(name, entry)| {
+            let mut inline = key_fields(&entry.key, &entry.shift_key);
+            inline.insert("process", Value::from(entry.process.as_str()));
+            if let Some(cmd) = &entry.command {
+                inline.insert("command", Value::from(cmd.as_str()));
+            }
+            (name.as_str(), inline)
+        })
+        .collect();
+    sync_entries(table, entries);
+}
+
+fn fill_commands(table: &mut Table, entries: &IndexMap<String, CommandEntry>) {
+    let entries = entries
+        .iter()
+        .map(|(name, entry)| {
+            let mut inline = key_fields(&entry.key, &entry.shift_key);
+            inline.insert("command", Value::from(entry.command.as_str()));
+            if entry.show_output {
+                inline.insert("show_output", Value::from(true));
+            }
+            (name.as_str(), inline)
+        })
+        .collect();
+    sync_entries(table, entries);
+}
+
+fn fill_snippets(table: &mut Table, entries: &IndexMap<String, SnippetEntry>) {
+    let entries = entries
+        .iter()
+        .map(|(name, entry)| {
+            let mut inline = key_fields(&entry.key, &entry.shift_key);
+            inline.insert("text", Value::from(entry.text.as_str()));
+            (name.as_str(), inline)
+        })
+        .collect();
+    sync_entries(table, entries);
+}
+
+// [[overrides]] の i 番目をその場で更新する
+fn fill_override(table: &mut Table, entry: &OverrideEntry) {
+    let to_inline = |map: &IndexMap<DispatchKey, String>| {
+        let mut inline = InlineTable::new();
+        for (k, name) in map {
+            inline.insert(k.as_str(), Value::from(name.as_str()));
+        }
+        inline
+    };
+    set_value(table, "process", Value::from(entry.process.as_str()));
+    set_value(table, "keys", Value::InlineTable(to_inline(&entry.keys)));
+    if entry.shift_keys.is_empty() {
+        table.remove("shift_keys");
+    } else {
+        set_value(table, "shift_keys", Value::InlineTable(to_inline(&entry.shift_keys)));
+    }
+}
+
+// 暗黙のテーブル（[profiles.NAME.folders] の profiles や NAME）
+fn implicit_table() -> Item {
+    let mut table = Table::new();
+    table.set_implicit(true);
+    Item::Table(table)
+}
+
+// 子のテーブル（無ければ作る）
+fn sub_table<'a>(parent: &'a mut Table, key: &str) -> Result<&'a mut Table> {
+    parent
+        .entry(key)
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .with_context(|| format!("{} がテーブル形式ではありません", key))
+}
+
 /// config.toml にコメントを保持しつつ保存する。
-/// 既存ファイルがあればコメントを保持、なければ新規作成。
-/// エントリは割当キー順でソートされる（キーなしは末尾、名前順）。
+/// 既存ファイルがあれば変わったエントリ・値だけを書き換え、コメントや書式を保持する。なければ新規作成。
+/// エントリは config の順（GUI での並び順）に並べる。
+/// 書き込みは排他ロックを取ってアトミックに行い、元の内容はバックアップする（[`storage`]）。
 pub fn save(path: &std::path::Path, config: &Config) -> Result<()> {
-    use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
+    let _lock = storage::lock(path)?;
+    save_locked(path, config, None)
+}
+
+/// config.toml をロックしたまま読み込み・変更・保存する。
+/// CLI のトグルと GUI の保存が同時に走っても、互いの変更を上書きしない。
+pub fn update(
+    path: &std::path::Path,
+    f: impl FnOnce(&mut Config) -> Result<()>,
+) -> Result<Config> {
+    let _lock = storage::lock(path)?;
+    let mut config = load_from(path)?;
+    f(&mut config)?;
+    save_locked(path, &config, None)?;
+    Ok(config)
+}
+
+/// エントリの表を書くかどうか。下のレイヤーに重ねるファイルの表は下のレイヤーの表を置き換えるため
+/// （[`layers`] の overlay）、書く場合は削除・名前変更も表せるよう全エントリを書く。
+/// 下のレイヤー（base）と同じでファイルにも無ければ書かない。base が None なら空でなければ書く。
+fn writes_entries<E: PartialEq>(
+    entries: &IndexMap<String, E>,
+    base: Option<&IndexMap<String, E>>,
+    existing: Option<&Item>,
+) -> bool {
+    existing.is_some() || base.map_or(!entries.is_empty(), |b| b != entries)
+}
 
-    // ソート用ヘルパー: dispatch key あり → key 昇順、なし → 名前昇順で末尾
-    fn sort_key<'a>(dispatch_key: Option<&'a str>, name: &'a str) -> (u8, &'a str) {
-        match dispatch_key {
-            Some(k) => (0, k),
-            None => (1, name),
+/// セクションの値を書く（None は削除）。inherited（下のレイヤーと同じ値）はファイルに無ければ書かず、
+/// 書く値が無ければセクションも作らない。
+fn set_section(
+    doc: &mut toml_edit::DocumentMut,
+    section: &str,
+    fields: Vec<(&str, Option<Value>, bool)>,
+) -> Result<()> {
+    let in_file = |key: &str| {
+        doc.get(section)
+            .and_then(Item::as_table_like)
+            .is_some_and(|t| t.contains_key(key))
+    };
+    let fields: Vec<_> = fields
+        .into_iter()
+        .filter(|(key, value, inherited)| (value.is_some() && !inherited) || in_file(key))
+        .collect();
+    if fields.is_empty() && !doc.contains_key(section) {
+        return Ok(());
+    }
+    let table = sub_table(doc.as_table_mut(), section)?;
+    for (key, value, _) in fields {
+        match value {
+            Some(value) => set_value(table, key, value),
+            None => {
+                table.remove(key);
+            }
         }
     }
+    Ok(())
+}
 
-    // 既存ファイルがあればパースして構造を保持、なければ空ドキュメント
-    let existing = if path.exists() {
-        std::fs::read_to_string(path)
-            .unwrap_or_default()
-            .parse::<DocumentMut>()
-            .unwrap_or_default()
+/// config をファイルに書く。base は下のレイヤーを重ねた設定で、base と同じ値・エントリは
+/// ファイルに書いてある場合だけ更新する（下のレイヤーの変更がそのまま反映されるように）。
+/// 下のレイヤーにしか無い値やエントリはこのファイルからは消せない。
+pub(crate) fn save_locked(
+    path: &std::path::Path,
+    config: &Config,
+    base: Option<&Config>,
+) -> Result<()> {
+    use toml_edit::{ArrayOfTables, DocumentMut};
+    // 既存ファイルがあればパースして構造を保持、なければ空ドキュメント。
+    // 解析できないファイル（手で編集中など）は上書きせずにエラーにする
+    let mut doc = if path.exists() {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("設定ファイルの読み込みに失敗しました: {}", path.display()))?;
+        content.parse::<DocumentMut>().with_context(|| {
+            format!(
+                "設定ファイルを解析できないため保存しませんでした。修正するかバックアップから復元してください: {}",
+                path.display()
+            )
+        })?
     } else {
         DocumentMut::new()
     };
 
-    let mut doc = existing;
+    // [search] / [folders] / [apps] セクション（単独の設定ファイルなら常に書く）
+    if base.is_none() || writes_entries(&config.search, base.map(|b| &b.search), doc.get("search")) {
+        fill_search(sub_table(doc.as_table_mut(), "search")?, &config.search);
+    }
+    if base.is_none() || writes_entries(&config.folders, base.map(|b| &b.folders), doc.get("folders")) {
+        fill_folders(sub_table(doc.as_table_mut(), "folders")?, &config.folders);
+    }
+    if base.is_none() || writes_entries(&config.apps, base.map(|b| &b.apps), doc.get("apps")) {
+        fill_apps(sub_table(doc.as_table_mut(), "apps")?, &config.apps);
+    }
+
+    // [commands] セクション（未使用なら作らない）
+    if writes_entries(&config.commands, base.map(|b| &b.commands), doc.get("commands")) {
+        fill_commands(sub_table(doc.as_table_mut(), "commands")?, &config.commands);
+    }
 
-    // [search] セクション
-    let search_table = doc
-        .entry("search")
-        .or_insert_with(|| Item::Table(Table::new()))
-        .as_table_mut()
-        .context("search セクションがテーブル形式ではありません")?;
-    search_table.clear();
-    let mut search_entries: Vec<_> = config.search.iter().collect();
-    search_entries.sort_by(|(na, a), (nb, b)| {
-        sort_key(a.dispatch_key(), na).cmp(&sort_key(b.dispatch_key(), nb))
-    });
-    for (name, entry) in search_entries {
-        let mut inline = InlineTable::new();
-        if let Some(dk) = &entry.key {
-            inline.insert("key", Value::from(dk.as_str()));
+    // [snippets] セクション（未使用なら作らない）
+    if writes_entries(&config.snippets, base.map(|b| &b.snippets), doc.get("snippets")) {
+        fill_snippets(sub_table(doc.as_table_mut(), "snippets")?, &config.snippets);
+    }
+
+    // [[overrides]]（未使用なら作らない。既存の要素は順番どおりにその場で更新する）
+    let inherited = base.is_some_and(|b| b.overrides == config.overrides);
+    if config.overrides.is_empty() || (inherited && !doc.contains_key("overrides")) {
+        doc.remove("overrides");
+    } else {
+        let overrides = doc
+            .entry("overrides")
+            .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .context("overrides は [[overrides]] 形式で記述してください")?;
+        while overrides.len() > config.overrides.len() {
+            overrides.remove(overrides.len() - 1);
+        }
+        for (i, entry) in config.overrides.iter().enumerate() {
+            if i == overrides.len() {
+                overrides.push(Table::new());
+            }
+            if let Some(table) = overrides.get_mut(i) {
+                fill_override(table, entry);
+            }
         }
-        inline.insert("url", Value::from(entry.url.as_str()));
-        search_table[name] = toml_edit::value(inline);
     }
 
-    // [folders] セクション
-    let folders_table = doc
-        .entry("folders")
-        .or_insert_with(|| Item::Table(Table::new()))
-        .as_table_mut()
-        .context("folders セクションがテーブル形式ではありません")?;
-    folders_table.clear();
-    let mut folder_entries: Vec<_> = config.folders.iter().collect();
-    folder_entries.sort_by(|(na, a), (nb, b)| {
-        sort_key(a.dispatch_key(), na).cmp(&sort_key(b.dispatch_key(), nb))
-    });
-    for (name, entry) in folder_entries {
-        let mut inline = InlineTable::new();
-        if let Some(dk) = &entry.key {
-            inline.insert("key", Value::from(dk.as_str()));
+    // active_profile（トップレベル、未選択なら書かない）
+    match &config.active_profile {
+        Some(name)
+            if base.is_some_and(|b| b.active_profile.as_ref() == Some(name))
+                && !doc.contains_key("active_profile") => {}
+        Some(name) => set_value(doc.as_table_mut(), "active_profile", Value::from(name.as_str())),
+        None => {
+            doc.remove("active_profile");
         }
-        inline.insert("path", Value::from(entry.path.as_str()));
-        folders_table[name] = toml_edit::value(inline);
     }
 
-    // [apps] セクション
-    let apps_table = doc
-        .entry("apps")
-        .or_insert_with(|| Item::Table(Table::new()))
-        .as_table_mut()
-        .context("apps セクションがテーブル形式ではありません")?;
-    apps_table.clear();
-    let mut app_entries: Vec<_> = config.apps.iter().collect();
-    app_entries.sort_by(|(na, a), (nb, b)| {
-        sort_key(a.dispatch_key(), na).cmp(&sort_key(b.dispatch_key(), nb))
-    });
-    for (name, entry) in app_entries {
-        let mut inline = InlineTable::new();
-        if let Some(dk) = &entry.key {
-            inline.insert("key", Value::from(dk.as_str()));
+    // [profiles.NAME.*]（未使用なら作らない）
+    let profiles = &config.profiles;
+    if profiles.is_empty() && base.is_none_or(|b| b.profiles.is_empty()) {
+        doc.remove("profiles");
+    } else if writes_entries(profiles, base.map(|b| &b.profiles), doc.get("profiles")) {
+        let profiles_table = doc
+            .entry("profiles")
+            .or_insert_with(implicit_table)
+            .as_table_mut()
+            .context("profiles セクションがテーブル形式ではありません")?;
+        // 下のレイヤーのプロファイルをすべて消す場合も、空の表で置き換える
+        if profiles.is_empty() {
+            profiles_table.set_implicit(false);
         }
-        inline.insert("process", Value::from(entry.process.as_str()));
-        if let Some(cmd) = &entry.command {
-            inline.insert("command", Value::from(cmd.as_str()));
+        let stale: Vec<String> = profiles_table
+            .iter()
+            .map(|(k, _)| k.to_string())
+            .filter(|k| !profiles.contains_key(k))
+            .collect();
+        for name in stale {
+            profiles_table.remove(&name);
+        }
+        for (name, profile) in profiles {
+            let profile_table = profiles_table
+                .entry(name)
+                .or_insert_with(implicit_table)
+                .as_table_mut()
+                .with_context(|| format!("profiles.{} がテーブル形式ではありません", name))?;
+            match &profile.search {
+                Some(search) => fill_search(sub_table(profile_table, "search")?, search),
+                None => {
+                    profile_table.remove("search");
+                }
+            }
+            match &profile.folders {
+                Some(folders) => fill_folders(sub_table(profile_table, "folders")?, folders),
+                None => {
+                    profile_table.remove("folders");
+                }
+            }
+            match &profile.apps {
+                Some(apps) => fill_apps(sub_table(profile_table, "apps")?, apps),
+                None => {
+                    profile_table.remove("apps");
+                }
+            }
         }
-        apps_table[name] = toml_edit::value(inline);
     }
 
-    // punctuation_style（トップレベル）
-    doc["punctuation_style"] = toml_edit::value(&config.punctuation_style);
+    // config_version（トップレベル、保存する内容は常に現在の形式）
+    set_value(doc.as_table_mut(), "config_version", Value::from(i64::from(CONFIG_VERSION)));
 
-    // [timestamp] セクション
-    let ts_table = doc
-        .entry("timestamp")
-        .or_insert_with(|| Item::Table(Table::new()))
-        .as_table_mut()
-        .context("timestamp セクションがテーブル形式ではありません")?;
-    ts_table["format"] = toml_edit::value(&config.timestamp.format);
-    ts_table["position"] = toml_edit::value(&config.timestamp.position);
-    ts_table["delimiter"] = toml_edit::value(&config.timestamp.delimiter);
+    // punctuation_style（トップレベル、単独の設定ファイルなら常に書く）
+    if base.is_none_or(|b| b.punctuation_style != config.punctuation_style)
+        || doc.contains_key("punctuation_style")
+    {
+        set_value(doc.as_table_mut(), "punctuation_style", Value::from(config.punctuation_style.as_str()));
+    }
+
+    // layout（トップレベル、下のレイヤー・JIS と同じなら書かない）
+    if base.map_or(Layout::default(), |b| b.layout) != config.layout || doc.contains_key("layout") {
+        set_value(doc.as_table_mut(), "layout", Value::from(config.layout.as_str()));
+    }
 
-    std::fs::write(path, doc.to_string())
-        .with_context(|| format!("設定ファイルの書き込みに失敗しました: {}", path.display()))?;
+    // [symbols] セクション（未使用なら作らない）
+    let symbols = &config.symbols;
+    if writes_entries(symbols, base.map(|b| &b.symbols), doc.get("symbols")) {
+        let symbols_table = sub_table(doc.as_table_mut(), "symbols")?;
+        let stale: Vec<String> = symbols_table
+            .iter()
+            .map(|(k, _)| k.to_string())
+            .filter(|k| !symbols.contains_key(k))
+            .collect();
+        for k in stale {
+            symbols_table.remove(&k);
+        }
+        for (k, output) in symbols {
+            set_value(symbols_table, k, Value::from(output.to_string()));
+        }
+    }
+
+    // [timestamp] セクション（単独の設定ファイルなら常に書く）
+    let timestamp = &config.timestamp;
+    let base_timestamp = base.map(|b| &b.timestamp);
+    set_section(
+        &mut doc,
+        "timestamp",
+        vec![
+            (
+                "format",
+                Some(Value::from(timestamp.format.as_str())),
+                base_timestamp.is_some_and(|b| b.format == timestamp.format),
+            ),
+            (
+                "position",
+                Some(Value::from(timestamp.position.as_str())),
+                base_timestamp.is_some_and(|b| b.position == timestamp.position),
+            ),
+            (
+                "delimiter",
+                Some(Value::from(timestamp.delimiter.as_str())),
+                base_timestamp.is_some_and(|b| b.delimiter == timestamp.delimiter),
+            ),
+        ],
+    )?;
+
+    // [trigger] セクション（下のレイヤー・デフォルトと同じ値は書かない）
+    let trigger = &config.trigger;
+    let base_trigger = base.map_or_else(TriggerConfig::default, |b| b.trigger.clone());
+    set_section(
+        &mut doc,
+        "trigger",
+        vec![
+            ("key", trigger.key.map(|k| Value::from(k.as_str())), trigger.key == base_trigger.key),
+            (
+                "tap_timeout",
+                Some(Value::from(i64::from(trigger.tap_timeout))),
+                trigger.tap_timeout == base_trigger.tap_timeout,
+            ),
+            (
+                "hold_timeout",
+                Some(Value::from(i64::from(trigger.hold_timeout))),
+                trigger.hold_timeout == base_trigger.hold_timeout,
+            ),
+            (
+                "behavior",
+                Some(Value::from(trigger.behavior.as_str())),
+                trigger.behavior == base_trigger.behavior,
+            ),
+            (
+                "double_tap",
+                trigger.double_tap.as_ref().map(|d| Value::from(d.as_str())),
+                trigger.double_tap == base_trigger.double_tap,
+            ),
+        ],
+    )?;
+
+    // [editing] セクション（下のレイヤー・デフォルトと同じ値は書かない）
+    let base_editing = base.map_or_else(EditingConfig::default, |b| b.editing.clone());
+    set_section(
+        &mut doc,
+        "editing",
+        vec![(
+            "preset",
+            Some(Value::from(config.editing.preset.as_str())),
+            config.editing.preset == base_editing.preset,
+        )],
+    )?;
+    let editing_keys = &config.editing.keys;
+    if writes_entries(
+        editing_keys,
+        Some(&base_editing.keys),
+        doc.get("editing").and_then(|e| e.get("keys")),
+    ) {
+        let keys_table = sub_table(sub_table(doc.as_table_mut(), "editing")?, "keys")?;
+        let stale: Vec<String> = keys_table
+            .iter()
+            .map(|(k, _)| k.to_string())
+            .filter(|k| !editing_keys.contains_key(k))
+            .collect();
+        for k in stale {
+            keys_table.remove(&k);
+        }
+        for (k, action) in editing_keys {
+            set_value(keys_table, k, Value::from(action.as_str()));
+        }
+    }
 
+    // [extra_layers] セクション（下のレイヤー・デフォルトと同じ値は書かない）
+    let extra_layers = &config.extra_layers;
+    let base_extra_layers = base.map_or_else(ExtraLayersConfig::default, |b| b.extra_layers.clone());
+    set_section(
+        &mut doc,
+        "extra_layers",
+        vec![
+            ("mouse", Some(Value::from(extra_layers.mouse)), extra_layers.mouse == base_extra_layers.mouse),
+            ("numpad", Some(Value::from(extra_layers.numpad)), extra_layers.numpad == base_extra_layers.numpad),
+        ],
+    )?;
+
+    storage::write_locked(path, &doc.to_string())?;
     Ok(())
 }
 
 // ── Validation ──
 
-/// 設定のバリデーション。エラーメッセージのリストを返す。
-pub fn validate(config: &Config) -> Vec<String> {
-    let mut errors = Vec::new();
+/// 設定のバリデーション。見つかった問題を診断のリストで返す。
+pub fn validate(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
 
-    // timestamp format の検証
-    if config.timestamp.format.is_empty() {
-        errors.push("タイムスタンプのフォーマットを入力してください".to_string());
-    }
+    // timestamp format の検証（指定子・出力にファイル名に使えない文字が無いか）
+    diagnostics.extend(timestamp::check_format(&config.timestamp.format));
 
     // timestamp delimiter の検証 (空=区切りなし は許可)
-    if !config.timestamp.delimiter.is_empty()
-        && config
-            .timestamp
-            .delimiter
-            .contains(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..])
+    if config
+        .timestamp
+        .delimiter
+        .contains(timestamp::FORBIDDEN_FILENAME_CHARS)
     {
-        errors.push(format!(
-            "区切り文字に使用できない文字が含まれています: \"{}\"",
-            config.timestamp.delimiter
-        ));
+        diagnostics.push(
+            Diagnostic::error(
+                DiagnosticKind::InvalidDelimiter,
+                "timestamp.delimiter",
+                format!(
+                    "区切り文字に使用できない文字が含まれています: \"{}\"",
+                    config.timestamp.delimiter
+                ),
+            )
+            .with_suggestion("/ \\ : * ? \" < > | 以外の文字を指定してください"),
+        );
     }
 
-    // timestamp position の検証
-    if config.timestamp.position != "before" && config.timestamp.position != "after" {
-        errors.push(format!(
-            "タイムスタンプの位置は \"before\" か \"after\" を指定してください (現在: \"{}\")",
-            config.timestamp.position
-        ));
+    // [symbols] の検証
+    for (k, output) in &config.symbols {
+        let path = format!("symbols.{}", diagnostic::quote_key(k));
+        if !keys::is_symbol_key(config.layout, k) {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::InvalidSymbolKey,
+                    path,
+                    format!("'{}' には記号を割り当てられません", k),
+                )
+                .with_suggestion(match keys::normalize(k) {
+                    Some(normalized) if keys::is_symbol_key(config.layout, &normalized) => {
+                        format!("'{}' の誤りではありませんか", normalized)
+                    }
+                    _ => format!("{} のいずれかを指定してください", symbol_keys(config.layout).join(", ")),
+                }),
+            );
+            continue;
+        }
+        if output.is_whitespace() || output.is_control() || matches!(output, '(' | ')' | '"') {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::InvalidSymbol,
+                    path.clone(),
+                    format!("記号キー '{}' の文字 {:?} は入力できません", k, output),
+                )
+                .with_suggestion("空白・制御文字・( ) \" 以外の1文字を指定してください"),
+            );
+        }
+        for modifier in Modifier::ALL {
+            if config.assigned_keys(modifier).any(|assigned| assigned == k) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticKind::DuplicateKey,
+                        path.clone(),
+                        format!(
+                            "記号キー '{}' が割当キー '{}' と重複しています",
+                            k,
+                            modifier.describe(k)
+                        ),
+                    )
+                    .with_suggestion("記号か割当キーのどちらかを削除してください"),
+                );
+            }
+        }
     }
 
-    // punctuation_style の検証
-    if !["、。", "，．", "，。", "、．"].contains(&config.punctuation_style.as_str()) {
-        errors.push(format!(
-            "punctuation_style は \"、。\", \"，．\", \"，。\", \"、．\" のいずれかを指定してください (現在: \"{}\")",
-            config.punctuation_style
-        ));
+    // [editing] の検証
+    for k in config.editing.keys.keys() {
+        if !keys::is_editing_key(config.layout, k) {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::InvalidEditingKey,
+                    format!("editing.keys.{}", diagnostic::quote_key(k)),
+                    format!("'{}' には編集操作を割り当てられません", k),
+                )
+                .with_suggestion(match keys::normalize(k) {
+                    Some(normalized) if keys::is_editing_key(config.layout, &normalized) => {
+                        format!("'{}' の誤りではありませんか", normalized)
+                    }
+                    _ => "タイムスタンプ・記号入力用以外のキーを指定してください".to_string(),
+                }),
+            );
+        }
     }
-
-    // search URL テンプレートの検証
-    for (name, entry) in &config.search {
-        if !entry.url().contains("{query}") {
-            errors.push(format!(
-                "検索エンジン '{}' の URL には {{query}} プレースホルダを含めてください",
-                name
-            ));
-        }
-    }
-
-    // 割当キーの重複チェック
-    let mut used_keys: IndexMap<String, String> = IndexMap::new();
-    for (name, entry) in &config.search {
-        if let Some(k) = entry.dispatch_key() {
-            let label = format!("search/{}", name);
-            if let Some(prev) = used_keys.get(k) {
-                errors.push(format!(
-                    "割当キー '{}' が '{}' と '{}' で重複しています",
-                    k, prev, label
-                ));
-            } else {
-                used_keys.insert(k.to_string(), label);
+    // プリセットと重なる割当キーの移し先（編集操作・記号・割当のないキー）
+    let free_key = keys::assignable_keys().into_iter().find(|k| {
+        config.editing.action(k).is_none()
+            && !config.symbols.contains_key(*k)
+            && Modifier::ALL.iter().all(|m| !config.assigned_keys(*m).any(|a| a == *k))
+    });
+    for k in keys::layout_keys(config.layout).filter(|k| keys::is_editing_key(config.layout, k.name)) {
+        if config.editing.action(k.name).is_none() {
+            continue;
+        }
+        // プリセットのキーは editing.preset の問題として報告する
+        let (path, suggestion) = if config.editing.keys.contains_key(k.name) {
+            (
+                format!("editing.keys.{}", diagnostic::quote_key(k.name)),
+                "編集キーか割当キーのどちらかを削除してください".to_string(),
+            )
+        } else {
+            (
+                "editing.preset".to_string(),
+                match free_key {
+                    Some(free) => format!(
+                        "割当キーを空いているキー（'{}' など）に移すか、別のプリセットを指定してください",
+                        free
+                    ),
+                    None => "割当キーを変更するか、別のプリセットを指定してください".to_string(),
+                },
+            )
+        };
+        for modifier in Modifier::ALL {
+            if config.assigned_keys(modifier).any(|assigned| assigned == k.name) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticKind::DuplicateKey,
+                        path.clone(),
+                        format!(
+                            "編集キー '{}' が割当キー '{}' と重複しています",
+                            k.name,
+                            modifier.describe(k.name)
+                        ),
+                    )
+                    .with_suggestion(suggestion.clone()),
+                );
             }
         }
+        // 記号を割り当てられないキーの [symbols] は上で報告済み
+        if config.symbols.contains_key(k.name) && keys::is_symbol_key(config.layout, k.name) {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::DuplicateKey,
+                    path.clone(),
+                    format!("編集キー '{}' が記号キーと重複しています", k.name),
+                )
+                .with_suggestion("編集キーか記号のどちらかを削除してください"),
+            );
+        }
     }
-    for (name, entry) in &config.folders {
-        if let Some(k) = entry.dispatch_key() {
-            let label = format!("folders/{}", name);
-            if let Some(prev) = used_keys.get(k) {
-                errors.push(format!(
-                    "割当キー '{}' が '{}' と '{}' で重複しています",
-                    k, prev, label
-                ));
-            } else {
-                used_keys.insert(k.to_string(), label);
-            }
+
+    // trigger のタイミングの検証
+    for (name, value) in [
+        ("tap_timeout", config.trigger.tap_timeout),
+        ("hold_timeout", config.trigger.hold_timeout),
+    ] {
+        if !TRIGGER_TIMEOUT_RANGE.contains(&value) {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::InvalidTriggerTimeout,
+                    format!("trigger.{}", name),
+                    format!(
+                        "trigger.{} は {}〜{} ミリ秒で指定してください (現在: {})",
+                        name,
+                        TRIGGER_TIMEOUT_RANGE.start(),
+                        TRIGGER_TIMEOUT_RANGE.end(),
+                        value
+                    ),
+                )
+                .with_suggestion(format!("{} = {}", name, default_trigger_timeout())),
+            );
         }
     }
-    for (name, entry) in &config.apps {
-        if let Some(k) = entry.dispatch_key() {
-            let label = format!("apps/{}", name);
-            if let Some(prev) = used_keys.get(k) {
-                errors.push(format!(
-                    "割当キー '{}' が '{}' と '{}' で重複しています",
-                    k, prev, label
-                ));
+
+    // トリガーキーと同じ切替キーの追加レイヤーには入れない
+    for layer in extra_layers::enabled(config) {
+        if config.trigger_key().kanata_name() == layer.entry {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::DuplicateKey,
+                    "trigger.key",
+                    format!(
+                        "トリガーキーが{}レイヤーの切替キー（{}）と重複しています",
+                        layer.title, layer.entry_label
+                    ),
+                )
+                .with_suggestion("別のトリガーキーを指定するか、追加レイヤーを無効にしてください"),
+            );
+        }
+    }
+
+    // US 配列にないトリガーキー（レイヤーに入れなくなる）
+    let trigger_key = config.trigger_key();
+    if config.layout == Layout::Ansi && trigger_key.is_jis_only() {
+        diagnostics.push(
+            Diagnostic::warning(
+                DiagnosticKind::TriggerKeyNotOnLayout,
+                "trigger.key",
+                format!("US 配列のキーボードには{}キーがありません", trigger_key.label()),
+            )
+            .with_suggestion(format!("key = \"{}\"", TriggerKey::default_for(Layout::Ansi))),
+        );
+    }
+
+    // active_profile の検証
+    if let Some(name) = &config.active_profile {
+        if !config.profiles.contains_key(name) {
+            let defined: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            let diagnostic = Diagnostic::error(
+                DiagnosticKind::UnknownActiveProfile,
+                "active_profile",
+                format!("active_profile '{}' が profiles に定義されていません", name),
+            );
+            diagnostics.push(if defined.is_empty() {
+                diagnostic.with_suggestion("active_profile を削除してください")
             } else {
-                used_keys.insert(k.to_string(), label);
+                diagnostic.with_suggestion(format!("定義済み: {}", defined.join(", ")))
+            });
+        }
+    }
+
+    // 標準と各プロファイルそれぞれの状態でエントリを検証する
+    let mut views = vec![(None, config.with_profile(None))];
+    for name in config.profiles.keys() {
+        views.push((Some(name.as_str()), config.with_profile(Some(name))));
+    }
+    for (profile, view) in &views {
+        validate_entries(view, *profile, &mut diagnostics);
+    }
+
+    // ダブルタップのエントリ（いずれかのプロファイルにあればよい）
+    if let Some(DoubleTap::Entry(name)) = &config.trigger.double_tap {
+        if views.iter().all(|(_, view)| view.entry_action(name).is_none()) {
+            let builtin: Vec<String> =
+                DoubleTap::BUILTIN.iter().map(|v| format!("\"{}\"", v.as_str())).collect();
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::UnknownDoubleTapTarget,
+                    "trigger.double_tap",
+                    format!(
+                        "trigger.double_tap の '{}' が search / folders / apps / commands / snippets に定義されていません",
+                        name
+                    ),
+                )
+                .with_suggestion(format!("{} またはエントリ名を指定してください", builtin.join(", "))),
+            );
+        }
+    }
+
+    // アプリ別の上書きの検証（全体の割当との重複は上書きとして許可）
+    for (i, entry) in config.overrides.iter().enumerate() {
+        if entry.process.trim().is_empty() {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::EmptyOverrideProcess,
+                    format!("overrides[{}].process", i),
+                    format!("overrides の {} 番目の process を入力してください", i + 1),
+                )
+                .with_suggestion("前面アプリのプロセス名（例: \"firefox\"）を指定してください"),
+            );
+            continue;
+        }
+        let label = format!("overrides/{}", entry.process);
+        for modifier in Modifier::ALL {
+            let table = match modifier {
+                Modifier::None => "keys",
+                Modifier::Shift => "shift_keys",
+            };
+            for (k, name) in entry.keys_for(modifier) {
+                let k = k.as_str();
+                let path = format!("overrides[{}].{}.{}", i, table, diagnostic::quote_key(k));
+                if views.iter().all(|(_, view)| view.entry_action(name).is_none()) {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            DiagnosticKind::UnknownOverrideTarget,
+                            path,
+                            format!(
+                                "'{}' の割当キー '{}' の割当先 '{}' が search / folders / apps / commands / snippets に定義されていません",
+                                label,
+                                modifier.describe(k),
+                                name
+                            ),
+                        )
+                        .with_suggestion("いずれかのセクションで定義したエントリ名を指定してください"),
+                    );
+                }
             }
         }
     }
 
-    errors
+    // 共通セクションの問題はプロファイルごとに重複するためまとめる
+    diagnostics
+        .into_iter()
+        .collect::<indexmap::IndexSet<_>>()
+        .into_iter()
+        .collect()
 }
 
-// ── kbd punctuation rewrite ──
+/// エントリの検証（URL / コマンド / スニペット / 割当キー）。
+/// profile を指定した場合、そのプロファイルで置き換えたセクションは profiles/NAME/SECTION と表示する。
+fn validate_entries(config: &Config, profile: Option<&str>, diagnostics: &mut Vec<Diagnostic>) {
+    let owned = config.profile();
+    // (表示用ラベル, TOML のパス)
+    let section = |name: &str, replaced: bool| match profile {
+        Some(p) if replaced => (
+            format!("profiles/{}/{}", p, name),
+            format!("profiles.{}.{}", diagnostic::quote_key(p), name),
+        ),
+        _ => (name.to_string(), name.to_string()),
+    };
+    let (search_label, search_path) = section("search", owned.is_some_and(|p| p.search.is_some()));
+    let (folders_label, folders_path) =
+        section("folders", owned.is_some_and(|p| p.folders.is_some()));
+    let (apps_label, apps_path) = section("apps", owned.is_some_and(|p| p.apps.is_some()));
+
+    // search URL テンプレートの検証
+    for (name, entry) in config.active_search() {
+        let display = match search_label.as_str() {
+            "search" => name.to_string(),
+            label => format!("{}/{}", label, name),
+        };
+        // {query} を置き換えた後の URL として解釈できるか
+        if let Err(e) = url::Url::parse(&entry.url().replace("{query}", "query")) {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::InvalidUrl,
+                    entry_path(&search_path, name, "url"),
+                    format!("検索エンジン '{}' の URL が正しくありません ({})", display, e),
+                )
+                .with_suggestion("https:// から始まる URL を指定してください"),
+            );
+        } else if !entry.url().contains("{query}") {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::MissingQueryPlaceholder,
+                    entry_path(&search_path, name, "url"),
+                    format!(
+                        "検索エンジン '{}' の URL には {{query}} プレースホルダを含めてください",
+                        display
+                    ),
+                )
+                .with_suggestion("検索語を入れる位置に {query} を書いてください（例: https://www.google.com/search?q={query}）"),
+            );
+        }
+    }
 
-/// kbd ファイル内の句読点行を指定スタイルに書き換える。
-pub fn rewrite_kbd_punctuation(kbd_path: &std::path::Path, style: &str) -> Result<()> {
-    let content = std::fs::read_to_string(kbd_path)
-        .with_context(|| format!("kbd ファイルの読み込みに失敗しました: {}", kbd_path.display()))?;
+    // コマンドの検証
+    for (name, entry) in &config.commands {
+        if entry.command().trim().is_empty() {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::EmptyCommand,
+                    entry_path("commands", name, "command"),
+                    format!("コマンド '{}' の実行内容を入力してください", name),
+                )
+                .with_suggestion("使わない場合はエントリを削除してください"),
+            );
+        }
+    }
 
-    let patterns = [
-        "(unicode 、)  (unicode 。)",
-        "(unicode ，)  (unicode ．)",
-        "(unicode ，)  (unicode 。)",
-        "(unicode 、)  (unicode ．)",
-    ];
-    let new_fragment = match style {
-        "，．" => "(unicode ，)  (unicode ．)",
-        "，。" => "(unicode ，)  (unicode 。)",
-        "、．" => "(unicode 、)  (unicode ．)",
-        _ => "(unicode 、)  (unicode 。)",
-    };
+    // スニペットの検証
+    for (name, entry) in &config.snippets {
+        if entry.text().is_empty() {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::EmptySnippet,
+                    entry_path("snippets", name, "text"),
+                    format!("スニペット '{}' の本文を入力してください", name),
+                )
+                .with_suggestion("使わない場合はエントリを削除してください"),
+            );
+        }
+    }
 
-    let mut new_content = content.clone();
-    for pat in &patterns {
-        new_content = new_content.replace(pat, new_fragment);
+    // 割当キーの検証（使用可能なキーか / セクション横断の重複。レイヤーごと）
+    // (表示用ラベル, TOML のパス, キー, 修飾キー)
+    let mut bindings: Vec<(String, String, &str, Modifier)> = Vec::new();
+    for modifier in Modifier::ALL {
+        let sections = [
+            (
+                search_label.as_str(),
+                search_path.as_str(),
+                keyed(config.active_search(), modifier),
+            ),
+            (
+                folders_label.as_str(),
+                folders_path.as_str(),
+                keyed(config.active_folders(), modifier),
+            ),
+            (
+                apps_label.as_str(),
+                apps_path.as_str(),
+                keyed(config.active_apps(), modifier),
+            ),
+            (
+                "commands",
+                "commands",
+                keyed(&config.commands, modifier),
+            ),
+            (
+                "snippets",
+                "snippets",
+                keyed(&config.snippets, modifier),
+            ),
+        ];
+        for (label, path, entries) in sections {
+            for (name, k) in entries {
+                bindings.push((
+                    format!("{}/{}", label, name),
+                    entry_path(path, name, modifier.field()),
+                    k,
+                    modifier,
+                ));
+            }
+        }
+    }
+    let mut used_keys: IndexMap<(Modifier, &str), String> = IndexMap::new();
+    for (label, path, k, modifier) in bindings {
+        if let Some(prev) = used_keys.get(&(modifier, k)) {
+            diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticKind::DuplicateKey,
+                    path,
+                    format!(
+                        "割当キー '{}' が '{}' と '{}' で重複しています",
+                        modifier.describe(k),
+                        prev,
+                        label
+                    ),
+                )
+                .with_suggestion("どちらかの割当キーを変更してください"),
+            );
+        } else {
+            used_keys.insert((modifier, k), label);
+        }
     }
+}
 
-    std::fs::write(kbd_path, new_content)
-        .with_context(|| format!("kbd ファイルの書き込みに失敗しました: {}", kbd_path.display()))?;
+/// [symbols] で記号を割り当てられるキー名の一覧（物理配列順）。
+fn symbol_keys(layout: Layout) -> Vec<&'static str> {
+    keys::layout_keys(layout)
+        .filter(|k| keys::is_symbol_key(layout, k.name))
+        .map(|k| k.name)
+        .collect()
+}
 
-    Ok(())
+/// 割当キーを持つエントリの (名前, キー) を列挙する。
+pub(crate) fn keyed<E: KeyedEntry>(entries: &IndexMap<String, E>, modifier: Modifier) -> Vec<(&str, &str)> {
+    entries
+        .iter()
+        .filter_map(|(name, e)| e.key_for(modifier).map(|k| (name.as_str(), k)))
+        .collect()
+}
+
+/// validate に加え、ファイルシステムを参照する検証（フォルダが存在するか）も行う。
+/// 別の PC で作った設定も読み込めるよう、存在しないフォルダは警告にとどめる。
+pub fn validate_deep(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = validate(config);
+    let mut sections = vec![("folders".to_string(), &config.folders)];
+    for (name, profile) in &config.profiles {
+        if let Some(folders) = &profile.folders {
+            let path = format!("profiles.{}.folders", diagnostic::quote_key(name));
+            sections.push((path, folders));
+        }
+    }
+    for (section, folders) in sections {
+        for (name, entry) in folders {
+            let path = expand_home(entry.path());
+            if entry.path().is_empty() || !path.is_dir() {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        DiagnosticKind::MissingFolder,
+                        entry_path(&section, name, "path"),
+                        format!("フォルダ '{}' が見つかりません: {}", name, path.display()),
+                    )
+                    .with_suggestion("存在するフォルダのパスを指定してください"),
+                );
+            }
+        }
+    }
+    diagnostics
 }
 
 // ── Helpers ──
@@ -508,6 +2294,27 @@ pub fn get_search_url<'a>(
         .ok_or_else(|| anyhow::anyhow!("検索エンジン '{}' が config.toml に定義されていません", engine))
 }
 
+/// コマンドの定義を取得する。
+pub fn get_command<'a>(
+    commands: &'a IndexMap<String, CommandEntry>,
+    target: &str,
+) -> Result<&'a CommandEntry> {
+    commands
+        .get(target)
+        .ok_or_else(|| anyhow::anyhow!("コマンド '{}' が config.toml に定義されていません", target))
+}
+
+/// スニペットの本文を取得する。
+pub fn get_snippet_text<'a>(
+    snippets: &'a IndexMap<String, SnippetEntry>,
+    target: &str,
+) -> Result<&'a str> {
+    snippets
+        .get(target)
+        .map(|e| e.text())
+        .ok_or_else(|| anyhow::anyhow!("スニペット '{}' が config.toml に定義されていません", target))
+}
+
 /// フォルダのパスを取得する。
 pub fn get_folder_path<'a>(
     folders: &'a IndexMap<String, FolderEntry>,
@@ -519,6 +2326,20 @@ pub fn get_folder_path<'a>(
         .ok_or_else(|| anyhow::anyhow!("フォルダ '{}' が config.toml に定義されていません", target))
 }
 
+/// "~" または "~/" で始まるパスをホームディレクトリに展開する
+pub fn expand_home(path_str: &str) -> PathBuf {
+    if let Some(rest) = path_str.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    } else if path_str == "~" {
+        if let Some(home) = dirs::home_dir() {
+            return home;
+        }
+    }
+    PathBuf::from(path_str)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -527,7 +2348,7 @@ mod tests {
     fn test_default_config() {
         let config = default_config();
         assert_eq!(config.timestamp.format, "%Y%m%d");
-        assert_eq!(config.timestamp.position, "before");
+        assert_eq!(config.timestamp.position, TimestampPosition::Before);
         assert!(config.search.contains_key("Google"));
         // OS 別デフォルトが正しくパースされ、全セクションが埋まっていること
         assert!(
@@ -553,12 +2374,29 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_invalid_position() {
-        let mut config = default_config();
-        config.timestamp.position = "middle".to_string();
-        let errors = validate(&config);
-        assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("before"));
+    fn test_load_rejects_invalid_position() {
+        let err = toml::from_str::<Config>("[timestamp]\nposition = \"middle\"\n").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("timestamp.position に \"middle\" は指定できません"), "{}", message);
+        assert!(message.contains("\"before\", \"after\""), "{}", message);
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_punctuation_style() {
+        let err = toml::from_str::<Config>("punctuation_style = \"。、\"\n").unwrap_err();
+        assert!(err.to_string().contains("punctuation_style に \"。、\" は指定できません"), "{}", err);
+    }
+
+    #[test]
+    fn test_setting_values_roundtrip() {
+        for position in TimestampPosition::ALL {
+            assert_eq!(position.as_str().parse::<TimestampPosition>(), Ok(position));
+        }
+        for style in PunctuationStyle::ALL {
+            assert_eq!(style.as_str().parse::<PunctuationStyle>(), Ok(style));
+        }
+        assert_eq!(TimestampPosition::Before.toggled(), TimestampPosition::After);
+        assert_eq!(PunctuationStyle::CommaMaru.chars(), ('，', '。'));
     }
 
     #[test]
@@ -590,117 +2428,884 @@ mod tests {
         assert_eq!(config.apps["editor"].dispatch_key(), Some("a"));
     }
 
-    #[test]
-    fn test_dispatch_lookup() {
-        let toml_str = r#"
-            [search]
-            google = {key = "g", url = "https://www.google.com/search?q={query}"}
+    #[test]
+    fn test_dispatch_lookup() {
+        let toml_str = r#"
+            [search]
+            google = {key = "g", url = "https://www.google.com/search?q={query}"}
+
+            [folders]
+            documents = {key = "1", path = "~/Documents"}
+
+            [apps]
+            editor = {key = "a", process = "Code", command = "code"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+
+        match config.dispatch_lookup("g", Modifier::None) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "google"),
+            other => panic!("Expected Search, got {:?}", other),
+        }
+        match config.dispatch_lookup("1", Modifier::None) {
+            Some(DispatchAction::OpenFolder { target }) => assert_eq!(target, "documents"),
+            other => panic!("Expected OpenFolder, got {:?}", other),
+        }
+        match config.dispatch_lookup("a", Modifier::None) {
+            Some(DispatchAction::SwitchApp { target }) => assert_eq!(target, "editor"),
+            other => panic!("Expected SwitchApp, got {:?}", other),
+        }
+        assert!(config.dispatch_lookup("z", Modifier::None).is_none());
+    }
+
+    #[test]
+    fn test_validate_duplicate_keys() {
+        let toml_str = r#"
+            [search]
+            google = {key = "a", url = "https://www.google.com/search?q={query}"}
+
+            [apps]
+            editor = {key = "a", process = "Code", command = "code"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("割当キー 'a'"));
+    }
+
+    // ── A. パース (追加分) ──
+
+    #[test]
+    fn test_parse_without_key() {
+        let toml_str = r#"
+            [search]
+            google = {url = "https://www.google.com/search?q={query}"}
+
+            [folders]
+            documents = {path = "~/Documents"}
+
+            [apps]
+            editor = {process = "Code", command = "code"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.search["google"].dispatch_key().is_none());
+        assert_eq!(config.search["google"].url(), "https://www.google.com/search?q={query}");
+        assert!(config.folders["documents"].dispatch_key().is_none());
+        assert!(config.apps["editor"].dispatch_key().is_none());
+    }
+
+    #[test]
+    fn test_parse_empty_sections() {
+        let toml_str = r#"
+            [search]
+            [folders]
+            [apps]
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.search.is_empty());
+        assert!(config.folders.is_empty());
+        assert!(config.apps.is_empty());
+    }
+
+    #[test]
+    fn test_parse_missing_sections() {
+        let toml_str = r#"
+            [timestamp]
+            format = "%Y%m%d"
+            position = "before"
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.search.is_empty());
+        assert!(config.folders.is_empty());
+        assert!(config.apps.is_empty());
+    }
+
+    // ── B. ディスパッチ検索 (追加分) ──
+
+    #[test]
+    fn test_dispatch_lookup_priority() {
+        // search takes priority over apps when both have the same key
+        let toml_str = r#"
+            [search]
+            google = {key = "a", url = "https://www.google.com/search?q={query}"}
+
+            [apps]
+            editor = {key = "a", process = "Code", command = "code"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        match config.dispatch_lookup("a", Modifier::None) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "google"),
+            other => panic!("Expected Search (priority over Apps), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_lookup_shift_layer() {
+        let toml_str = r#"
+            [search]
+            google = {key = "g", url = "https://www.google.com/search?q={query}"}
+            wiki = {shift_key = "g", url = "https://ja.wikipedia.org/w/index.php?search={query}"}
+
+            [apps]
+            editor = {key = "a", shift_key = "b", process = "Code", command = "code"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        match config.dispatch_lookup("g", Modifier::None) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "google"),
+            other => panic!("Expected Search google, got {:?}", other),
+        }
+        match config.dispatch_lookup("g", Modifier::Shift) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "wiki"),
+            other => panic!("Expected Search wiki, got {:?}", other),
+        }
+        match config.dispatch_lookup("b", Modifier::Shift) {
+            Some(DispatchAction::SwitchApp { target }) => assert_eq!(target, "editor"),
+            other => panic!("Expected SwitchApp, got {:?}", other),
+        }
+        assert!(config.dispatch_lookup("b", Modifier::None).is_none());
+        assert!(config.dispatch_lookup("a", Modifier::Shift).is_none());
+    }
+
+    #[test]
+    fn test_dispatch_lookup_command() {
+        let toml_str = r#"
+            [commands]
+            memo = {key = "p", command = "echo {selection} >> ~/memo.txt"}
+            wc = {shift_key = "p", command = "wc -c {selected_files}", show_output = true}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        match config.dispatch_lookup("p", Modifier::None) {
+            Some(DispatchAction::RunCommand { target }) => assert_eq!(target, "memo"),
+            other => panic!("Expected RunCommand memo, got {:?}", other),
+        }
+        match config.dispatch_lookup("p", Modifier::Shift) {
+            Some(DispatchAction::RunCommand { target }) => assert_eq!(target, "wc"),
+            other => panic!("Expected RunCommand wc, got {:?}", other),
+        }
+        assert!(!config.commands["memo"].show_output);
+        assert!(config.commands["wc"].show_output);
+    }
+
+    #[test]
+    fn test_save_commands_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_commands");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        config.commands.insert(
+            "memo".to_string(),
+            CommandEntry {
+                key: Some("p".parse().unwrap()),
+                shift_key: None,
+                command: "echo {clipboard}".to_string(),
+                show_output: true,
+            },
+        );
+        save(&path, &config).unwrap();
+        let loaded = load_from(&path).unwrap();
+        let memo = &loaded.commands["memo"];
+        assert_eq!(memo.dispatch_key(), Some("p"));
+        assert_eq!(memo.command(), "echo {clipboard}");
+        assert!(memo.show_output);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_save_without_commands_omits_section() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_no_commands");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        save(&path, &default_config()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("[commands]"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_dispatch_lookup_snippet() {
+        let toml_str = r#"
+            [snippets]
+            "署名" = {key = "p", text = "株式会社サンプル\n山田太郎"}
+            "定型返信" = {shift_key = "p", text = "承知しました。{cursor}\nよろしくお願いいたします。"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        match config.dispatch_lookup("p", Modifier::None) {
+            Some(DispatchAction::Snippet { target }) => assert_eq!(target, "署名"),
+            other => panic!("Expected Snippet 署名, got {:?}", other),
+        }
+        match config.dispatch_lookup("p", Modifier::Shift) {
+            Some(DispatchAction::Snippet { target }) => assert_eq!(target, "定型返信"),
+            other => panic!("Expected Snippet 定型返信, got {:?}", other),
+        }
+        assert_eq!(config.snippets["署名"].text(), "株式会社サンプル\n山田太郎");
+    }
+
+    #[test]
+    fn test_save_snippets_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_snippets");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        config.snippets.insert(
+            "署名".to_string(),
+            SnippetEntry {
+                key: Some("p".parse().unwrap()),
+                shift_key: None,
+                text: "--\n山田太郎 ({date:%Y-%m-%d})".to_string(),
+            },
+        );
+        save(&path, &config).unwrap();
+        let loaded = load_from(&path).unwrap();
+        assert_eq!(loaded.snippets["署名"].text(), "--\n山田太郎 ({date:%Y-%m-%d})");
+        assert_eq!(loaded.snippets["署名"].dispatch_key(), Some("p"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_save_symbols_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_symbols");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        config.symbols.insert(",".to_string(), '；');
+        save(&path, &config).unwrap();
+        let loaded = load_from(&path).unwrap();
+        assert_eq!(loaded.symbols, config.symbols);
+
+        // 削除した記号はファイルからも消える
+        config.symbols.shift_remove("/");
+        save(&path, &config).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("\"/\" = "), "{}", content);
+        assert!(content.contains("\",\" = \"；\""), "{}", content);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_validate_symbols() {
+        let mut config = default_config();
+        config.symbols.insert("h".to_string(), '←');
+        config.symbols.insert("［".to_string(), '【');
+        config.symbols.insert("@".to_string(), ' ');
+        // Google の割当キー
+        config.symbols.insert("g".to_string(), '＊');
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::InvalidSymbolKey);
+        assert_eq!(errors[0].path, r#"symbols."h""#);
+        assert_eq!(errors[1].suggestion.as_deref(), Some("'[' の誤りではありませんか"));
+        assert_eq!(errors[2].kind, DiagnosticKind::InvalidSymbol);
+        assert_eq!(errors[3].kind, DiagnosticKind::DuplicateKey);
+        assert!(errors[3].message.contains("記号キー 'g'"));
+    }
+
+    #[test]
+    fn test_load_rejects_multi_char_symbol() {
+        assert!(toml::from_str::<Config>("[symbols]\n\"/\" = \"・・\"\n").is_err());
+    }
+
+    #[test]
+    fn test_editing_action() {
+        let mut editing = EditingConfig::default();
+        assert_eq!(editing.action("h"), Some(EditAction::Left));
+        assert_eq!(editing.action("g"), None);
+        editing.preset = EditingPreset::Emacs;
+        assert_eq!(editing.action("b"), Some(EditAction::Left));
+        assert_eq!(editing.action("j"), None);
+        editing.keys.insert("j".to_string(), EditAction::Down);
+        editing.keys.insert("b".to_string(), EditAction::WordLeft);
+        assert_eq!(editing.action("j"), Some(EditAction::Down));
+        assert_eq!(editing.action("b"), Some(EditAction::WordLeft));
+    }
+
+    #[test]
+    fn test_load_editing() {
+        let toml_str = "[editing]\npreset = \"none\"\n[editing.keys]\nh = \"left\"\nl = \"C-right\"\n";
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let keys: Vec<(&str, EditAction)> =
+            config.editing_keys().iter().map(|(k, a)| (k.name, *a)).collect();
+        assert_eq!(keys, vec![("h", EditAction::Left), ("l", EditAction::WordRight)]);
+
+        let err = toml::from_str::<Config>("[editing.keys]\nh = \"C-x\"\n").unwrap_err();
+        assert!(err.to_string().contains("editing.keys に \"C-x\" は指定できません"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_editing() {
+        let mut config = default_config();
+        config.editing.keys.insert("z".to_string(), EditAction::Home);
+        config.editing.keys.insert("p".to_string(), EditAction::PageUp);
+        config.symbols.insert("p".to_string(), '＊');
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::InvalidEditingKey);
+        assert_eq!(errors[0].path, r#"editing.keys."z""#);
+        assert_eq!(errors[1].kind, DiagnosticKind::DuplicateKey);
+        assert!(errors[1].message.contains("記号キーと重複"));
+
+        // WASD は左手の割当キーと重なる
+        let mut config = default_config();
+        config.editing.preset = EditingPreset::Wasd;
+        let errors = validate(&config);
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|e| e.kind == DiagnosticKind::DuplicateKey));
+        assert!(errors.iter().all(|e| e.path == "editing.preset"));
+        assert!(errors.iter().any(|e| e.message.contains("編集キー 'w'")));
+
+        // 重なる割当キーをプリセットが空けた右手のキーに移せば通る
+        for preset in [EditingPreset::Emacs, EditingPreset::Wasd] {
+            let mut config = default_config();
+            config.editing.preset = preset;
+            let errors = validate(&config);
+            assert!(!errors.is_empty());
+            assert!(errors
+                .iter()
+                .all(|e| e.suggestion.as_deref().is_some_and(|s| s.contains("など"))));
+            let free: Vec<&str> = ["j", "k", "l", "y", "u", "i", "o", "m", ";", "n"]
+                .into_iter()
+                .filter(|k| config.editing.action(k).is_none())
+                .collect();
+            let mut free = free.into_iter();
+            let in_preset = |key: &Option<DispatchKey>| {
+                key.as_ref().is_some_and(|k| config.editing.action(k.as_str()).is_some())
+            };
+            let mut moved = Vec::new();
+            for (name, entry) in &config.search {
+                if in_preset(&entry.key) {
+                    moved.push(("search", name.clone()));
+                }
+            }
+            for (name, entry) in &config.folders {
+                if in_preset(&entry.key) {
+                    moved.push(("folders", name.clone()));
+                }
+            }
+            for (name, entry) in &config.apps {
+                if in_preset(&entry.key) {
+                    moved.push(("apps", name.clone()));
+                }
+            }
+            assert!(!moved.is_empty());
+            for (section, name) in moved {
+                let key = Some(free.next().unwrap().parse::<DispatchKey>().unwrap());
+                match section {
+                    "search" => config.search[&name].key = key,
+                    "folders" => config.folders[&name].key = key,
+                    _ => config.apps[&name].key = key,
+                }
+            }
+            assert_eq!(validate(&config), vec![], "{:?}", preset);
+        }
+    }
+
+    #[test]
+    fn test_save_editing_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_editing");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        save(&path, &config).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("[editing]"));
+
+        config.editing.preset = EditingPreset::Emacs;
+        config.editing.keys.insert(";".to_string(), EditAction::Enter);
+        save(&path, &config).unwrap();
+        assert_eq!(load_from(&path).unwrap().editing, config.editing);
+
+        // 削除したキーはファイルからも消える
+        config.editing.keys.clear();
+        save(&path, &config).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("\";\" = "), "{}", content);
+        assert_eq!(load_from(&path).unwrap().editing, config.editing);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_save_extra_layers_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_extra_layers");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        save(&path, &config).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("[extra_layers]"));
+
+        config.extra_layers.mouse = true;
+        save(&path, &config).unwrap();
+        assert_eq!(load_from(&path).unwrap().extra_layers, config.extra_layers);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_validate_extra_layer_entry_conflict() {
+        let mut config = default_config();
+        config.trigger.key = Some(TriggerKey::Space);
+        assert!(validate(&config).is_empty());
+        config.extra_layers.mouse = true;
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::DuplicateKey);
+        assert_eq!(errors[0].path, "trigger.key");
+        assert!(errors[0].message.contains("マウスレイヤーの切替キー（Space）"));
+    }
+
+    #[test]
+    fn test_save_refuses_unparseable_file() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_unparseable");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let broken = "[search]\nGoogle = {url = \"https://www.google.com/search?q={query}\"\n";
+        std::fs::write(&path, broken).unwrap();
+
+        let err = save(&path, &default_config()).unwrap_err();
+        assert!(format!("{:#}", err).contains("保存しませんでした"), "{:#}", err);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), broken);
+        assert!(storage::list_backups(&path).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_save_trigger_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_trigger");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        save(&path, &config).unwrap();
+        // 既定値のままなら [trigger] は書き出さない
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("[trigger]"));
+
+        config.trigger = TriggerConfig {
+            key: Some(TriggerKey::Space),
+            tap_timeout: 150,
+            hold_timeout: 250,
+            behavior: TapHoldBehavior::TapHoldPress,
+            double_tap: Some(DoubleTap::OneShot),
+        };
+        save(&path, &config).unwrap();
+        let loaded = load_from(&path).unwrap();
+        assert_eq!(loaded.trigger, config.trigger);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_trigger_defaults() {
+        let config: Config = toml::from_str("[trigger]\nkey = \"henkan\"\n").unwrap();
+        assert_eq!(config.trigger_key(), TriggerKey::Henkan);
+        assert_eq!(config.trigger.tap_timeout, 200);
+        assert_eq!(config.trigger.behavior, TapHoldBehavior::TapHold);
+        assert_eq!(default_config().trigger_key(), TriggerKey::default_for(Layout::Jis));
+    }
+
+    #[test]
+    fn test_ansi_layout_defaults() {
+        let toml_str = "layout = \"ansi\"\n[symbols]\n\"/\" = \"・\"\n\"[\" = \"「\"\n\"]\" = \"」\"\n";
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.layout, Layout::Ansi);
+        assert_eq!(config.trigger_key(), TriggerKey::CapsLock);
+        // 既定の記号（/ [ ]）は US 配列でもそのまま使える
+        let symbols: Vec<&str> = config.symbol_outputs().iter().map(|(k, _)| k.name).collect();
+        assert_eq!(symbols, vec!["[", "]", ",", ".", "/"]);
+        assert!(toml::from_str::<Config>("layout = \"dvorak\"\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_ansi_layout() {
+        let mut config = default_config();
+        config.layout = Layout::Ansi;
+        config.symbols.insert("@".to_string(), '＠');
+        config.symbols.insert("'".to_string(), '’');
+        config.trigger.key = Some(TriggerKey::Muhenkan);
+        let diagnostics = validate(&config);
+        assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+        // JIS にしかない @ キーには記号を割り当てられない
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::InvalidSymbolKey);
+        assert_eq!(diagnostics[0].path, r#"symbols."@""#);
+        assert_eq!(diagnostics[1].kind, DiagnosticKind::TriggerKeyNotOnLayout);
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].suggestion.as_deref(), Some("key = \"caps_lock\""));
+        assert!(!has_errors(&[diagnostics[1].clone()]));
+    }
+
+    #[test]
+    fn test_save_layout_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_layout");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = default_config();
+        save(&path, &config).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("layout"));
+
+        config.layout = Layout::Ansi;
+        save(&path, &config).unwrap();
+        assert_eq!(load_from(&path).unwrap().layout, Layout::Ansi);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_validate_trigger_timeout() {
+        let mut config = default_config();
+        config.trigger.tap_timeout = 0;
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::InvalidTriggerTimeout);
+        assert_eq!(errors[0].path, "trigger.tap_timeout");
+        assert_eq!(errors[0].suggestion.as_deref(), Some("tap_timeout = 200"));
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_trigger() {
+        let err = toml::from_str::<Config>("[trigger]\nkey = \"ctrl\"\n").unwrap_err();
+        assert!(err.to_string().contains("trigger.key に \"ctrl\" は指定できません"), "{}", err);
+        assert!(toml::from_str::<Config>("[trigger]\nbehavior = \"hold\"\n").is_err());
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let config: Config = toml::from_str(
+            "[trigger]\ndouble_tap = \"ime\"\n[commands]\n\"ビルド\" = {command = \"make\"}\n",
+        )
+        .unwrap();
+        assert_eq!(config.trigger.double_tap, Some(DoubleTap::Ime));
+        assert!(config.double_tap_action().is_none());
+
+        let mut config = config;
+        config.trigger.double_tap = Some(DoubleTap::from("ビルド".to_string()));
+        match config.double_tap_action() {
+            Some(DispatchAction::RunCommand { target }) => assert_eq!(target, "ビルド"),
+            other => panic!("Expected RunCommand ビルド, got {:?}", other),
+        }
+        assert!(validate(&config).is_empty());
+    }
+
+    #[test]
+    fn test_validate_double_tap_unknown_entry() {
+        let mut config = default_config();
+        config.trigger.double_tap = Some(DoubleTap::Entry("存在しない".to_string()));
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::UnknownDoubleTapTarget);
+        assert_eq!(errors[0].path, "trigger.double_tap");
+        assert!(errors[0].suggestion.as_deref().unwrap().contains("\"one-shot\""));
+    }
+
+    const OVERRIDES_TOML: &str = r#"
+        [search]
+        Google = {key = "g", url = "https://www.google.com/search?q={query}"}
+        "社内Wiki" = {url = "https://wiki.example.com/search?q={query}"}
+
+        [apps]
+        "ブラウザ" = {key = "f", process = "firefox"}
+
+        [snippets]
+        "署名" = {text = "山田"}
+
+        [[overrides]]
+        process = "Firefox"
+        keys = {g = "社内Wiki"}
+        shift_keys = {g = "署名"}
+
+        [[overrides]]
+        process = "chrome.exe"
+        keys = {p = "署名"}
+    "#;
+
+    #[test]
+    fn test_dispatch_lookup_override_matches_foreground() {
+        let config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        let firefox = vec!["Navigator".to_string(), "firefox".to_string()];
+        match config.dispatch_lookup_for("g", Modifier::None, &firefox) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "社内Wiki"),
+            other => panic!("Expected Search 社内Wiki, got {:?}", other),
+        }
+        match config.dispatch_lookup_for("g", Modifier::Shift, &firefox) {
+            Some(DispatchAction::Snippet { target }) => assert_eq!(target, "署名"),
+            other => panic!("Expected Snippet 署名, got {:?}", other),
+        }
+        // 上書きのないキーは全体の割当にフォールバック
+        match config.dispatch_lookup_for("f", Modifier::None, &firefox) {
+            Some(DispatchAction::SwitchApp { target }) => assert_eq!(target, "ブラウザ"),
+            other => panic!("Expected SwitchApp, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_lookup_override_falls_back_elsewhere() {
+        let config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        let terminal = vec!["gnome-terminal".to_string()];
+        match config.dispatch_lookup_for("g", Modifier::None, &terminal) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "Google"),
+            other => panic!("Expected Search Google, got {:?}", other),
+        }
+        assert!(config.dispatch_lookup_for("g", Modifier::Shift, &terminal).is_none());
+    }
+
+    #[test]
+    fn test_override_matches_ignores_case_and_exe() {
+        let config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        assert!(config.overrides[1].matches(&["Chrome.exe".to_string()]));
+        assert!(config.overrides[1].matches(&["chrome".to_string()]));
+        assert!(!config.overrides[1].matches(&["chromium".to_string()]));
+    }
+
+    #[test]
+    fn test_assigned_keys_include_overrides() {
+        let config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        assert!(config.assigned_keys(Modifier::None).any(|k| k == "p"));
+        assert!(config.assigned_keys(Modifier::Shift).any(|k| k == "g"));
+    }
+
+    #[test]
+    fn test_save_overrides_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_overrides");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        save(&path, &config).unwrap();
+        let loaded = load_from(&path).unwrap();
+        assert_eq!(loaded.overrides.len(), 2);
+        assert_eq!(loaded.overrides[0].process, "Firefox");
+        assert_eq!(loaded.overrides[0].keys["g"], "社内Wiki");
+        assert_eq!(loaded.overrides[0].shift_keys["g"], "署名");
+        assert!(loaded.overrides[1].shift_keys.is_empty());
+        assert!(validate(&loaded).is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    const PROFILES_TOML: &str = r#"
+        active_profile = "執筆"
+
+        [search]
+        Google = {key = "g", url = "https://www.google.com/search?q={query}"}
 
-            [folders]
-            documents = {key = "1", path = "~/Documents"}
+        [folders]
+        Downloads = {key = "1", path = "~/Downloads"}
 
-            [apps]
-            editor = {key = "a", process = "Code", command = "code"}
-        "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
+        [apps]
+        "ブラウザ" = {key = "f", process = "firefox"}
 
-        match config.dispatch_lookup("g") {
-            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "google"),
-            other => panic!("Expected Search, got {:?}", other),
+        [profiles."仕事".folders]
+        "共有" = {key = "1", path = "~/Shared"}
+        "議事録" = {key = "2", path = "~/Minutes"}
+
+        [profiles."執筆".search]
+        "辞書" = {key = "g", url = "https://dictionary.goo.ne.jp/srch/all/{query}/m0u/"}
+    "#;
+
+    #[test]
+    fn test_dispatch_lookup_active_profile() {
+        let config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        match config.dispatch_lookup("g", Modifier::None) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "辞書"),
+            other => panic!("Expected Search 辞書, got {:?}", other),
         }
-        match config.dispatch_lookup("1") {
-            Some(DispatchAction::OpenFolder { target }) => assert_eq!(target, "documents"),
-            other => panic!("Expected OpenFolder, got {:?}", other),
+        // プロファイルで置き換えていないセクションはトップレベルを使う
+        match config.dispatch_lookup("1", Modifier::None) {
+            Some(DispatchAction::OpenFolder { target }) => assert_eq!(target, "Downloads"),
+            other => panic!("Expected OpenFolder Downloads, got {:?}", other),
         }
-        match config.dispatch_lookup("a") {
-            Some(DispatchAction::SwitchApp { target }) => assert_eq!(target, "editor"),
-            other => panic!("Expected SwitchApp, got {:?}", other),
+
+        let work = config.with_profile(Some("仕事"));
+        match work.dispatch_lookup("1", Modifier::None) {
+            Some(DispatchAction::OpenFolder { target }) => assert_eq!(target, "共有"),
+            other => panic!("Expected OpenFolder 共有, got {:?}", other),
+        }
+        match work.dispatch_lookup("g", Modifier::None) {
+            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "Google"),
+            other => panic!("Expected Search Google, got {:?}", other),
         }
-        assert!(config.dispatch_lookup("z").is_none());
+        // 標準ではプロファイル専用のキーは未割当
+        assert!(config.with_profile(None).dispatch_lookup("2", Modifier::None).is_none());
     }
 
     #[test]
-    fn test_validate_duplicate_keys() {
-        let toml_str = r#"
-            [search]
-            google = {key = "a", url = "https://www.google.com/search?q={query}"}
+    fn test_next_profile_cycles() {
+        let config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        let standard = config.with_profile(None);
+        assert_eq!(standard.next_profile().as_deref(), Some("仕事"));
+        assert_eq!(config.with_profile(Some("仕事")).next_profile().as_deref(), Some("執筆"));
+        assert_eq!(config.next_profile(), None);
+        assert_eq!(standard.active_profile_label(), DEFAULT_PROFILE_LABEL);
+    }
 
-            [apps]
-            editor = {key = "a", process = "Code", command = "code"}
-        "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
-        let errors = validate(&config);
-        assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("割当キー 'a'"));
+    #[test]
+    fn test_assigned_keys_include_all_profiles() {
+        let config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        assert!(config.assigned_keys(Modifier::None).any(|k| k == "2"));
     }
 
-    // ── A. パース (追加分) ──
+    #[test]
+    fn test_save_profiles_roundtrip() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_profiles");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        save(&path, &config).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("[profiles.\"仕事\".folders]"), "{}", content);
+        assert!(!content.contains("[profiles]\n"), "{}", content);
+
+        let loaded = load_from(&path).unwrap();
+        assert_eq!(loaded.active_profile.as_deref(), Some("執筆"));
+        assert_eq!(loaded.profiles.len(), 2);
+        assert_eq!(loaded.profiles["仕事"].folders.as_ref().unwrap().len(), 2);
+        assert!(loaded.profiles["仕事"].search.is_none());
+        assert_eq!(loaded.active_search()["辞書"].dispatch_key(), Some("g"));
+        assert!(validate(&loaded).is_empty());
+
+        // 標準に戻すと active_profile は書かれない
+        save(&path, &loaded.with_profile(None)).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("active_profile"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // ── C. バリデーション (追加分) ──
 
     #[test]
-    fn test_parse_without_key() {
-        let toml_str = r#"
-            [search]
-            google = {url = "https://www.google.com/search?q={query}"}
+    fn test_validate_override_unknown_entry() {
+        let mut config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        config.overrides[0].keys.insert("t".parse().unwrap(), "存在しない".to_string());
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.message.contains("存在しない")));
+    }
 
-            [folders]
-            documents = {path = "~/Documents"}
+    #[test]
+    fn test_load_rejects_override_reserved_key() {
+        let toml_str = OVERRIDES_TOML.replace("keys = {p = ", "keys = {z = ");
+        let err = toml::from_str::<Config>(&toml_str).unwrap_err();
+        assert!(err.to_string().contains("'z' はタイムスタンプ操作用のキー"), "{}", err);
+    }
 
-            [apps]
-            editor = {process = "Code", command = "code"}
-        "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
-        assert!(config.search["google"].dispatch_key().is_none());
-        assert_eq!(config.search["google"].url(), "https://www.google.com/search?q={query}");
-        assert!(config.folders["documents"].dispatch_key().is_none());
-        assert!(config.apps["editor"].dispatch_key().is_none());
+    #[test]
+    fn test_validate_override_empty_process() {
+        let mut config: Config = toml::from_str(OVERRIDES_TOML).unwrap();
+        config.overrides[0].process = " ".to_string();
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.message.contains("1 番目の process")));
     }
 
     #[test]
-    fn test_parse_empty_sections() {
-        let toml_str = r#"
-            [search]
-            [folders]
-            [apps]
-        "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
-        assert!(config.search.is_empty());
-        assert!(config.folders.is_empty());
-        assert!(config.apps.is_empty());
+    fn test_validate_unknown_active_profile() {
+        let mut config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        config.active_profile = Some("旅行".to_string());
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.message.contains("active_profile '旅行'")));
     }
 
     #[test]
-    fn test_parse_missing_sections() {
-        let toml_str = r#"
-            [timestamp]
-            format = "%Y%m%d"
-            position = "before"
-        "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
-        assert!(config.search.is_empty());
-        assert!(config.folders.is_empty());
-        assert!(config.apps.is_empty());
+    fn test_validate_profile_key_conflict() {
+        let mut config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        // 仕事 プロファイルでは folders の "2" と apps の "2" が重複する
+        config.apps.insert(
+            "メール".to_string(),
+            AppEntry {
+                key: Some("2".parse().unwrap()),
+                shift_key: None,
+                process: "thunderbird".to_string(),
+                command: None,
+            },
+        );
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].message.contains("profiles/仕事/folders/議事録"));
+        assert!(errors[0].message.contains("apps/メール"));
+        assert_eq!(errors[0].path, r#"apps."メール".key"#);
     }
 
-    // ── B. ディスパッチ検索 (追加分) ──
+    #[test]
+    fn test_validate_profile_entry_path() {
+        let mut config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        config.profiles["執筆"].search.as_mut().unwrap()["辞書"].url =
+            "https://dictionary.goo.ne.jp/".to_string();
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].path, r#"profiles."執筆".search."辞書".url"#);
+    }
 
     #[test]
-    fn test_dispatch_lookup_priority() {
-        // search takes priority over apps when both have the same key
-        let toml_str = r#"
-            [search]
-            google = {key = "a", url = "https://www.google.com/search?q={query}"}
+    fn test_validate_reports_shared_error_once() {
+        let mut config: Config = toml::from_str(PROFILES_TOML).unwrap();
+        config.commands.insert(
+            "空".to_string(),
+            CommandEntry {
+                key: None,
+                shift_key: None,
+                command: String::new(),
+                show_output: false,
+            },
+        );
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
 
-            [apps]
-            editor = {key = "a", process = "Code", command = "code"}
-        "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
-        match config.dispatch_lookup("a") {
-            Some(DispatchAction::Search { engine }) => assert_eq!(engine, "google"),
-            other => panic!("Expected Search (priority over Apps), got {:?}", other),
-        }
+    #[test]
+    fn test_validate_empty_snippet() {
+        let mut config = default_config();
+        config.snippets.insert(
+            "empty".to_string(),
+            SnippetEntry {
+                key: None,
+                shift_key: None,
+                text: String::new(),
+            },
+        );
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.message.contains("スニペット 'empty'")));
     }
 
-    // ── C. バリデーション (追加分) ──
+    #[test]
+    fn test_validate_empty_command() {
+        let mut config = default_config();
+        config.commands.insert(
+            "empty".to_string(),
+            CommandEntry {
+                key: None,
+                shift_key: None,
+                command: "  ".to_string(),
+                show_output: false,
+            },
+        );
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.message.contains("コマンド 'empty'")));
+    }
+
+    #[test]
+    fn test_validate_command_key_conflict() {
+        let mut config = default_config();
+        let used = config.assigned_keys(Modifier::None).next().unwrap().to_string();
+        config.commands.insert(
+            "dup".to_string(),
+            CommandEntry {
+                key: Some(used.parse().unwrap()),
+                shift_key: None,
+                command: "true".to_string(),
+                show_output: false,
+            },
+        );
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.message.contains("commands/dup") && e.message.contains("重複")));
+    }
 
     #[test]
     fn test_validate_empty_format() {
@@ -708,7 +3313,7 @@ mod tests {
         config.timestamp.format = String::new();
         let errors = validate(&config);
         assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("フォーマット"));
+        assert!(errors[0].message.contains("フォーマット"));
     }
 
     #[test]
@@ -720,7 +3325,10 @@ mod tests {
         let config: Config = toml::from_str(toml_str).unwrap();
         let errors = validate(&config);
         assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("{query}"));
+        assert!(errors[0].message.contains("{query}"));
+        assert_eq!(errors[0].kind, DiagnosticKind::MissingQueryPlaceholder);
+        assert_eq!(errors[0].path, r#"search."bad".url"#);
+        assert!(errors[0].suggestion.is_some());
     }
 
     #[test]
@@ -733,20 +3341,23 @@ mod tests {
         let config: Config = toml::from_str(toml_str).unwrap();
         let errors = validate(&config);
         assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("割当キー 'g'"));
-        assert!(errors[0].contains("search/google"));
-        assert!(errors[0].contains("search/ejje"));
+        assert!(errors[0].message.contains("割当キー 'g'"));
+        assert!(errors[0].message.contains("search/google"));
+        assert!(errors[0].message.contains("search/ejje"));
+        // 後から出てきたエントリを指す
+        assert_eq!(errors[0].kind, DiagnosticKind::DuplicateKey);
+        assert_eq!(errors[0].path, r#"search."ejje".key"#);
     }
 
     #[test]
     fn test_validate_multiple_errors() {
         let mut config = default_config();
         config.timestamp.format = String::new();
-        config.timestamp.position = "middle".to_string();
         config.search.insert(
             "bad".to_string(),
             SearchEntry {
-                key: Some("g".to_string()),
+                key: Some("g".parse().unwrap()),
+                shift_key: None,
                 url: "https://example.com/no-placeholder".to_string(),
             },
         );
@@ -757,11 +3368,22 @@ mod tests {
     #[test]
     fn test_validate_all_keys_assigned() {
         let mut config = Config {
+            config_version: CONFIG_VERSION,
             search: IndexMap::new(),
             folders: IndexMap::new(),
             apps: IndexMap::new(),
+            commands: IndexMap::new(),
+            snippets: IndexMap::new(),
+            overrides: Vec::new(),
+            active_profile: None,
+            profiles: IndexMap::new(),
             timestamp: TimestampConfig::default(),
-            punctuation_style: default_punctuation_style(),
+            trigger: TriggerConfig::default(),
+        layout: Layout::default(),
+        editing: EditingConfig::default(),
+        extra_layers: ExtraLayersConfig::default(),
+            punctuation_style: PunctuationStyle::default(),
+            symbols: IndexMap::new(),
         };
         // Assign all 15 dispatch keys across sections
         let keys = DISPATCH_KEYS;
@@ -771,7 +3393,8 @@ mod tests {
                 config.search.insert(
                     name,
                     SearchEntry {
-                        key: Some(key.to_string()),
+                        key: Some(key.parse().unwrap()),
+                        shift_key: None,
                         url: format!("https://example.com/{}?q={{query}}", key),
                     },
                 );
@@ -779,7 +3402,8 @@ mod tests {
                 config.folders.insert(
                     name,
                     FolderEntry {
-                        key: Some(key.to_string()),
+                        key: Some(key.parse().unwrap()),
+                        shift_key: None,
                         path: format!("~/{}", key),
                     },
                 );
@@ -787,7 +3411,8 @@ mod tests {
                 config.apps.insert(
                     name,
                     AppEntry {
-                        key: Some(key.to_string()),
+                        key: Some(key.parse().unwrap()),
+                        shift_key: None,
                         process: format!("app_{}", key),
                         command: None,
                     },
@@ -798,13 +3423,151 @@ mod tests {
         assert!(errors.is_empty(), "Expected no errors, got: {:?}", errors);
     }
 
+    #[test]
+    fn test_validate_spare_key_allowed() {
+        let toml_str = r#"
+            [search]
+            google = {key = "p", url = "https://www.google.com/search?q={query}"}
+
+            [folders]
+            documents = {key = "0", path = "~/Documents"}
+
+            [apps]
+            editor = {key = "/", process = "Code", command = "code"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let errors = validate(&config);
+        assert!(errors.is_empty(), "Expected no errors, got: {:?}", errors);
+    }
+
+    #[test]
+    fn test_validate_shift_layer_is_separate() {
+        // 同じ物理キーでも通常レイヤーと Shift レイヤーは重複にならない
+        let toml_str = r#"
+            [search]
+            google = {key = "g", url = "https://www.google.com/search?q={query}"}
+            wiki = {shift_key = "g", url = "https://ja.wikipedia.org/w/index.php?search={query}"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(validate(&config).is_empty());
+    }
+
+    #[test]
+    fn test_validate_duplicate_shift_keys() {
+        let toml_str = r#"
+            [search]
+            wiki = {shift_key = "g", url = "https://ja.wikipedia.org/w/index.php?search={query}"}
+
+            [folders]
+            documents = {shift_key = "g", path = "~/Documents"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("割当キー 'Shift+g'"));
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_key() {
+        let toml_str = r#"
+            [search]
+            google = {key = "ctrl", url = "https://www.google.com/search?q={query}"}
+        "#;
+        let message = toml::from_str::<Config>(toml_str).unwrap_err().to_string();
+        assert!(message.contains("'ctrl' は割当キーに使用できません"), "{}", message);
+        // どのエントリの値か分かるよう位置も含まれる
+        assert!(message.contains("google"), "{}", message);
+    }
+
+    #[test]
+    fn test_load_rejects_reserved_key() {
+        let toml_str = r#"
+            [apps]
+            editor = {key = "x", process = "Code", command = "code"}
+        "#;
+        let err = toml::from_str::<Config>(toml_str).unwrap_err();
+        assert!(err.to_string().contains("割当キーにできません"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_timestamp_format_with_colon() {
+        let mut config = default_config();
+        config.timestamp.format = "%Y%m%d_%H:%M".to_string();
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::InvalidTimestampFormat);
+        assert_eq!(errors[0].path, "timestamp.format");
+    }
+
+    #[test]
+    fn test_validate_timestamp_invalid_specifier() {
+        let mut config = default_config();
+        config.timestamp.format = "%Y%Q".to_string();
+        let errors = validate(&config);
+        assert!(errors.iter().any(|e| e.kind == DiagnosticKind::InvalidTimestampFormat));
+    }
+
+    #[test]
+    fn test_validate_unparsable_url() {
+        let toml_str = r#"
+            [search]
+            google = {key = "g", url = "www.google.com/search?q={query}"}
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let errors = validate(&config);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].kind, DiagnosticKind::InvalidUrl);
+        assert_eq!(errors[0].path, r#"search."google".url"#);
+    }
+
+    #[test]
+    fn test_load_key_suggests_normalized() {
+        let toml_str = r#"
+            [search]
+            google = {key = "Ｇ", url = "https://www.google.com/search?q={query}"}
+        "#;
+        let err = toml::from_str::<Config>(toml_str).unwrap_err();
+        assert!(err.to_string().contains("'g' の誤りではありませんか"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_deep_warns_missing_folder() {
+        let dir = std::env::temp_dir().join("muhenkan_test_validate_deep");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut config = default_config();
+        config.folders.clear();
+        config.folders.insert(
+            "ある".to_string(),
+            FolderEntry {
+                key: Some("1".parse().unwrap()),
+                shift_key: None,
+                path: dir.to_string_lossy().into_owned(),
+            },
+        );
+        config.folders.insert(
+            "ない".to_string(),
+            FolderEntry {
+                key: Some("2".parse().unwrap()),
+                shift_key: None,
+                path: dir.join("missing").to_string_lossy().into_owned(),
+            },
+        );
+        let diagnostics = validate_deep(&config);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::MissingFolder);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].path, r#"folders."ない".path"#);
+        assert!(!has_errors(&diagnostics));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     // ── D. Save/Load ラウンドトリップ (追加分) ──
 
     #[test]
     fn test_roundtrip_save_load_detailed() {
         let toml_str = r#"
             [search]
-            google = {key = "g", url = "https://www.google.com/search?q={query}"}
+            google = {key = "g", shift_key = "p", url = "https://www.google.com/search?q={query}"}
 
             [folders]
             documents = {key = "1", path = "~/Documents"}
@@ -828,6 +3591,8 @@ mod tests {
         // Verify search
         assert_eq!(loaded.search["google"].url(), "https://www.google.com/search?q={query}");
         assert_eq!(loaded.search["google"].dispatch_key(), Some("g"));
+        assert_eq!(loaded.search["google"].key_for(Modifier::Shift), Some("p"));
+        assert_eq!(loaded.folders["documents"].key_for(Modifier::Shift), None);
 
         // Verify folders
         assert_eq!(loaded.folders["documents"].path(), "~/Documents");
@@ -840,7 +3605,7 @@ mod tests {
 
         // Verify timestamp
         assert_eq!(loaded.timestamp.format, "%Y%m%d");
-        assert_eq!(loaded.timestamp.position, "before");
+        assert_eq!(loaded.timestamp.position, TimestampPosition::Before);
 
         // Cleanup
         std::fs::remove_dir_all(&dir).ok();
@@ -867,31 +3632,108 @@ mod tests {
     }
 
     #[test]
-    fn test_save_sorts_by_dispatch_key() {
-        // 名前のアルファベット順と割当キー順が異なるデータ
+    fn test_save_keeps_entry_order() {
+        // 名前のアルファベット順とも割当キー順とも異なる並び
         let toml_str = r#"
             [search]
             gamma = {key = "g", url = "https://gamma.com/?q={query}"}
             alpha = {key = "t", url = "https://alpha.com/?q={query}"}
             beta = {key = "r", url = "https://beta.com/?q={query}"}
         "#;
-        let config: Config = toml::from_str(toml_str).unwrap();
+        let mut config: Config = toml::from_str(toml_str).unwrap();
 
-        let dir = std::env::temp_dir().join("muhenkan_test_sort_key");
+        let dir = std::env::temp_dir().join("muhenkan_test_entry_order");
         std::fs::create_dir_all(&dir).unwrap();
         let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
 
         save(&path, &config).unwrap();
         let loaded = load_from(&path).unwrap();
+        let names: Vec<&String> = loaded.search.keys().collect();
+        assert_eq!(names, vec!["gamma", "alpha", "beta"]);
 
-        // dispatch key 順: g < r < t → gamma, beta, alpha
+        // GUI で並べ替えた順に保存される
+        config.search.move_index(2, 0);
+        save(&path, &config).unwrap();
+        let loaded = load_from(&path).unwrap();
         let names: Vec<&String> = loaded.search.keys().collect();
-        assert_eq!(names, vec!["gamma", "beta", "alpha"]);
+        assert_eq!(names, vec!["beta", "gamma", "alpha"]);
 
         // Cleanup
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[test]
+    fn test_save_unchanged_shipped_config_is_identical() {
+        let dir = std::env::temp_dir().join("muhenkan_test_save_identical");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let original = include_str!("../../config/default-linux.toml");
+        std::fs::write(&path, original).unwrap();
+
+        let config = load_from(&path).unwrap();
+        save(&path, &config).unwrap();
+        // 省略されていた delimiter が追加される以外は、コメント・書式・並び順とも変わらない
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}delimiter = \"_\"\n", original)
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_save_updates_entries_in_place() {
+        let original = r#"# 検索
+[search]
+# 普段使い
+Google = {key = "g", url = "https://www.google.com/search?q={query}"}  # 日本語
+# 削除されるエントリ
+Bing   = {key = "b", url = "https://www.bing.com/search?q={query}"}
+
+[folders]
+Downloads = {key = "1", path = "~/Downloads"}
+# Work = {key = "2", path = "~/work"}
+
+[timestamp]
+format = "%Y%m%d"  # 年月日
+position = "before"
+"#;
+        let dir = std::env::temp_dir().join("muhenkan_test_save_in_place");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, original).unwrap();
+
+        let mut config = load_from(&path).unwrap();
+        config.search.shift_remove("Bing");
+        config.search["Google"].url = "https://www.google.co.jp/search?q={query}".to_string();
+        config.search.insert(
+            "Wiki".to_string(),
+            SearchEntry {
+                key: Some("w".parse().unwrap()),
+                shift_key: None,
+                url: "https://ja.wikipedia.org/w/index.php?search={query}".to_string(),
+            },
+        );
+        config.timestamp.format = "%Y-%m-%d".to_string();
+        save(&path, &config).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# 普段使い\nGoogle = {key = \"g\", url = \"https://www.google.co.jp/search?q={query}\"}  # 日本語\n"), "{}", content);
+        assert!(!content.contains("Bing"), "{}", content);
+        assert!(!content.contains("削除されるエントリ"), "{}", content);
+        assert!(content.contains("Wiki = { key = \"w\""), "{}", content);
+        assert!(content.contains("# Work = {key = \"2\", path = \"~/work\"}"), "{}", content);
+        assert!(content.contains("format = \"%Y-%m-%d\"  # 年月日"), "{}", content);
+        assert!(content.contains("# 検索\n[search]\n"), "{}", content);
+
+        let loaded = load_from(&path).unwrap();
+        let names: Vec<&String> = loaded.search.keys().collect();
+        assert_eq!(names, vec!["Google", "Wiki"]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     // ── E. ヘルパー関数 ──
 
     #[test]
@@ -900,7 +3742,8 @@ mod tests {
         search.insert(
             "google".to_string(),
             SearchEntry {
-                key: Some("g".to_string()),
+                key: Some("g".parse().unwrap()),
+                shift_key: None,
                 url: "https://www.google.com/search?q={query}".to_string(),
             },
         );
@@ -922,7 +3765,8 @@ mod tests {
         folders.insert(
             "docs".to_string(),
             FolderEntry {
-                key: Some("1".to_string()),
+                key: Some("1".parse().unwrap()),
+                shift_key: None,
                 path: "~/Documents".to_string(),
             },
         );
@@ -938,10 +3782,44 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("nonexistent"));
     }
 
+    #[test]
+    fn test_expand_home_with_tilde_prefix() {
+        let result = expand_home("~/Documents");
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(result, home.join("Documents"));
+    }
+
+    #[test]
+    fn test_expand_home_tilde_only() {
+        let result = expand_home("~");
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(result, home);
+    }
+
+    #[test]
+    fn test_expand_home_absolute_path_unchanged() {
+        let result = expand_home("/tmp/test");
+        assert_eq!(result, PathBuf::from("/tmp/test"));
+    }
+
+    #[test]
+    fn test_expand_home_relative_path_unchanged() {
+        let result = expand_home("relative/path");
+        assert_eq!(result, PathBuf::from("relative/path"));
+    }
+
+    #[test]
+    fn test_expand_home_tilde_in_middle_unchanged() {
+        // "foo/~/bar" のような場合は展開しない
+        let result = expand_home("foo/~/bar");
+        assert_eq!(result, PathBuf::from("foo/~/bar"));
+    }
+
     #[test]
     fn test_app_entry_command_fallback() {
         let entry = AppEntry {
-            key: Some("a".to_string()),
+            key: Some("a".parse().unwrap()),
+            shift_key: None,
             process: "Code".to_string(),
             command: None,
         };
@@ -951,7 +3829,8 @@ mod tests {
 
         // When command is explicitly set, it takes priority
         let entry2 = AppEntry {
-            key: Some("a".to_string()),
+            key: Some("a".parse().unwrap()),
+            shift_key: None,
             process: "Code".to_string(),
             command: Some("code".to_string()),
         };
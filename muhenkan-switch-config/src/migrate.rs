@@ -0,0 +1,292 @@
+//! config.toml の形式バージョン（config_version）の管理と旧形式からの移行。
+//!
+//! 移行は toml_edit のドキュメント上で1バージョンずつ行い、コメントは保持する。
+//! ファイルを書き換える場合は元のファイルをバックアップしてから保存する。
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// 現在の設定ファイルの形式バージョン。
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 移行ステップ（移行元バージョン → +1）。変更内容の説明を返す。
+type Step = fn(&mut DocumentMut, DocumentRole) -> Result<Vec<String>>;
+
+/// 移行ステップの一覧。STEPS[n] がバージョン n → n+1 の移行。
+const STEPS: &[Step] = &[v0_to_v1];
+
+/// 移行結果の報告。
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    /// 変更内容（ユーザー向けの説明）
+    pub changes: Vec<String>,
+    /// 移行前のファイルのバックアップ先
+    pub backup: Option<PathBuf>,
+}
+
+impl std::fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "config.toml を形式バージョン {} から {} に移行しました",
+            self.from_version, self.to_version
+        )?;
+        if let Some(backup) = &self.backup {
+            write!(f, "（元のファイル: {}）", backup.display())?;
+        }
+        for change in &self.changes {
+            write!(f, "\n- {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// 移行するドキュメントの使われ方。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentRole {
+    /// 単独で読み込む設定ファイル
+    Standalone,
+    /// 下のレイヤーに重ねるファイル（無いセクションは下のレイヤーの値を使うので補わない）
+    Overlay,
+}
+
+pub(crate) fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
+/// ドキュメントの config_version を返す（未記載は 0 = 旧形式）。
+pub fn document_version(doc: &DocumentMut) -> Result<u32> {
+    match doc.get("config_version") {
+        None => Ok(0),
+        Some(item) => item
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .context("config_version は 0 以上の整数で指定してください"),
+    }
+}
+
+/// ドキュメントを現在の形式まで移行する。移行しなかった場合は None。
+pub fn migrate_document(doc: &mut DocumentMut) -> Result<Option<MigrationReport>> {
+    migrate_document_as(doc, DocumentRole::Standalone)
+}
+
+/// 使われ方を指定してドキュメントを現在の形式まで移行する。移行しなかった場合は None。
+pub fn migrate_document_as(
+    doc: &mut DocumentMut,
+    role: DocumentRole,
+) -> Result<Option<MigrationReport>> {
+    let from_version = document_version(doc)?;
+    if from_version > CONFIG_VERSION {
+        anyhow::bail!(
+            "config_version {} はこのバージョンの muhenkan-switch では読み込めません（対応: {} まで）",
+            from_version,
+            CONFIG_VERSION
+        );
+    }
+    if from_version == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let mut changes = Vec::new();
+    for (version, step) in STEPS.iter().enumerate().skip(from_version as usize) {
+        let step_changes = step(doc, role).with_context(|| {
+            format!("config.toml の形式バージョン {} からの移行に失敗しました", version)
+        })?;
+        changes.extend(step_changes);
+    }
+    doc["config_version"] = toml_edit::value(i64::from(CONFIG_VERSION));
+
+    Ok(Some(MigrationReport {
+        from_version,
+        to_version: CONFIG_VERSION,
+        changes,
+        backup: None,
+    }))
+}
+
+/// config.toml を現在の形式に移行して保存する。元のファイルは backups/ にバックアップする。
+/// 移行が不要なら何もせず None を返す。
+pub fn migrate_file(path: &Path) -> Result<Option<MigrationReport>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("設定ファイルの読み込みに失敗しました: {}", path.display()))?;
+    let mut doc = content
+        .parse::<DocumentMut>()
+        .with_context(|| format!("設定ファイルの解析に失敗しました: {}", path.display()))?;
+    let Some(mut report) = migrate_document(&mut doc)? else {
+        return Ok(None);
+    };
+
+    // 移行後の内容が読み込めることを確認してから書き換える
+    toml::from_str::<crate::Config>(&doc.to_string())
+        .context("移行後の設定ファイルを読み込めませんでした")?;
+
+    // 元のファイルは storage が backups/ にバックアップする
+    report.backup = crate::storage::write(path, &doc.to_string())?;
+    Ok(Some(report))
+}
+
+/// migrate_file のエラーが TOML として解析できないファイルによるものか。
+/// 読み込みエラーや新しい config_version はファイル自体は壊れていない。
+pub fn is_parse_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<toml_edit::TomlError>().is_some()
+}
+
+// ── 移行ステップ ──
+
+/// v0 → v1:
+/// - 値だけの旧形式のエントリ（`google = "https://..."`）をインラインテーブルに変換する
+/// - 旧バージョンが生成した folders / apps の無い設定にデフォルトのフォルダ・アプリを補う
+///   （下のレイヤーに重ねるファイルは除く）
+fn v0_to_v1(doc: &mut DocumentMut, role: DocumentRole) -> Result<Vec<String>> {
+    let mut changes = Vec::new();
+
+    for (section, field) in [("search", "url"), ("folders", "path"), ("apps", "process")] {
+        let Some(table) = doc.get_mut(section).and_then(Item::as_table_mut) else {
+            continue;
+        };
+        for (name, item) in table.iter_mut() {
+            let Some(value) = item.as_str().map(str::to_string) else {
+                continue;
+            };
+            let mut inline = toml_edit::InlineTable::new();
+            inline.insert(field, Value::from(value.as_str()));
+            *item = toml_edit::value(inline);
+            changes.push(format!(
+                "[{}] の '{}' を {{{} = \"...\"}} 形式に変換しました",
+                section,
+                name.get(),
+                field
+            ));
+        }
+    }
+
+    let is_empty = |doc: &DocumentMut, section: &str| {
+        doc.get(section)
+            .and_then(Item::as_table_like)
+            .is_none_or(|t| t.is_empty())
+    };
+    if role == DocumentRole::Standalone && is_empty(doc, "folders") && is_empty(doc, "apps") {
+        let defaults = crate::default_config();
+        let mut folders = Table::new();
+        crate::fill_folders(&mut folders, &defaults.folders);
+        doc["folders"] = Item::Table(folders);
+        let mut apps = Table::new();
+        crate::fill_apps(&mut apps, &defaults.apps);
+        doc["apps"] = Item::Table(apps);
+        changes.push("[folders] と [apps] が空だったためデフォルトの設定を追加しました".to_string());
+    }
+
+    Ok(changes)
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyedEntry;
+
+    fn parse(s: &str) -> DocumentMut {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let mut doc = parse("config_version = 1\n[folders]\n");
+        assert!(migrate_document(&mut doc).unwrap().is_none());
+        assert_eq!(doc.to_string(), "config_version = 1\n[folders]\n");
+    }
+
+    #[test]
+    fn test_newer_version_errors() {
+        let mut doc = parse("config_version = 99\n");
+        let err = migrate_document(&mut doc).unwrap_err();
+        assert!(err.to_string().contains("99"));
+    }
+
+    #[test]
+    fn test_invalid_version_errors() {
+        let mut doc = parse("config_version = \"1\"\n");
+        assert!(migrate_document(&mut doc).is_err());
+    }
+
+    #[test]
+    fn test_v0_converts_plain_values() {
+        let mut doc = parse(
+            "# 検索\n[search]\ngoogle = \"https://www.google.com/search?q={query}\"\n\
+             [folders]\ndocs = {key = \"1\", path = \"~/Documents\"}\n",
+        );
+        let report = migrate_document(&mut doc).unwrap().unwrap();
+        assert_eq!((report.from_version, report.to_version), (0, CONFIG_VERSION));
+        assert_eq!(report.changes.len(), 1, "{:?}", report.changes);
+
+        let text = doc.to_string();
+        assert!(text.contains("# 検索"), "コメントは保持される: {}", text);
+        let config: crate::Config = toml::from_str(&text).unwrap();
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.search["google"].url(), "https://www.google.com/search?q={query}");
+        assert_eq!(config.folders["docs"].dispatch_key(), Some("1"));
+    }
+
+    #[test]
+    fn test_v0_fills_missing_folders_and_apps() {
+        let mut doc = parse(
+            "[search]\nGoogle = {key = \"g\", url = \"https://www.google.com/search?q={query}\"}\n",
+        );
+        let report = migrate_document(&mut doc).unwrap().unwrap();
+        assert!(report.changes.iter().any(|c| c.contains("デフォルト")));
+
+        let config: crate::Config = toml::from_str(&doc.to_string()).unwrap();
+        let defaults = crate::default_config();
+        assert_eq!(config.search.len(), 1, "ユーザーの検索設定は残る");
+        assert_eq!(config.folders.len(), defaults.folders.len());
+        assert_eq!(config.apps.len(), defaults.apps.len());
+    }
+
+    #[test]
+    fn test_is_parse_error() {
+        let dir = std::env::temp_dir().join("muhenkan_test_migrate_parse_error");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[search\n").unwrap();
+        assert!(is_parse_error(&migrate_file(&path).unwrap_err()));
+        std::fs::write(&path, "config_version = 99\n").unwrap();
+        assert!(!is_parse_error(&migrate_file(&path).unwrap_err()));
+        assert!(!is_parse_error(&migrate_file(&dir.join("missing.toml")).unwrap_err()));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_migrate_file_keeps_backup() {
+        let dir = std::env::temp_dir().join("muhenkan_test_migrate_file");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let original = "[search]\ngoogle = \"https://www.google.com/search?q={query}\"\n";
+        std::fs::write(&path, original).unwrap();
+
+        let report = migrate_file(&path).unwrap().unwrap();
+        let backup = report.backup.clone().unwrap();
+        assert_eq!(backup.parent(), Some(crate::storage::backup_dir(&path).as_path()));
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), original);
+        assert!(report.to_string().contains("- [search] の 'google'"));
+        // バックアップは backups/ の1件だけ（GUI の一覧・自動復元の対象）
+        let backups = crate::storage::list_backups(&path).unwrap();
+        assert_eq!(backups.iter().map(|b| &b.path).collect::<Vec<_>>(), vec![&backup]);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3, "config.toml, .lock, backups/");
+
+        // 移行済みなら何もしない
+        assert!(migrate_file(&path).unwrap().is_none());
+
+        // 2回目の移行ではバックアップを上書きしない
+        std::fs::write(&path, original).unwrap();
+        let report = migrate_file(&path).unwrap().unwrap();
+        assert_ne!(report.backup.unwrap(), backup);
+        assert_eq!(crate::storage::list_backups(&path).unwrap().len(), 2);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,86 @@
+//! config.toml の JSON Schema（エディタの補完・検証用）。
+//!
+//! Config の型から生成する。VS Code の Even Better TOML (Taplo) などは、
+//! config.toml 先頭の `#:schema ./config.schema.json` からこのスキーマを読み込む。
+
+use schemars::generate::SchemaSettings;
+
+use crate::Config;
+
+/// 同梱するスキーマのファイル名（config.toml と同じディレクトリに置く）。
+pub const FILE_NAME: &str = "config.schema.json";
+
+/// スキーマを JSON（整形済み、末尾改行付き）で返す。
+pub fn generate() -> String {
+    // Taplo などが対応している draft-07 で出力する
+    let mut schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Config>();
+    schema.insert("title".into(), "muhenkan-switch config.toml".into());
+    let mut json = serde_json::to_string_pretty(&schema).expect("スキーマは常に JSON に変換できる");
+    json.push('\n');
+    json
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn schema() -> Value {
+        serde_json::from_str(&generate()).unwrap()
+    }
+
+    #[test]
+    fn test_enums() {
+        let schema = schema();
+        let defs = &schema["definitions"];
+        assert_eq!(
+            defs["TimestampConfig"]["properties"]["position"]["enum"],
+            serde_json::json!(["before", "after"])
+        );
+        assert_eq!(
+            schema["properties"]["punctuation_style"]["enum"],
+            serde_json::json!(["、。", "，．", "，。", "、．"])
+        );
+        let keys = defs["SearchEntry"]["properties"]["key"]["enum"].as_array().unwrap();
+        for key in crate::DISPATCH_KEYS {
+            assert!(keys.contains(&Value::from(*key)), "{}", key);
+        }
+        // overrides のキーも割当可能なキーに限る
+        let overrides = &defs["OverrideEntry"]["properties"]["keys"];
+        assert_eq!(overrides["additionalProperties"], Value::Bool(false));
+        assert!(overrides["properties"]["g"].is_object());
+        assert!(overrides["properties"]["z"].is_null());
+    }
+
+    #[test]
+    fn test_entries_reject_unknown_fields() {
+        let schema = schema();
+        for def in ["SearchEntry", "FolderEntry", "AppEntry", "TimestampConfig"] {
+            assert_eq!(
+                schema["definitions"][def]["additionalProperties"],
+                Value::Bool(false),
+                "{}",
+                def
+            );
+        }
+        assert_eq!(schema["additionalProperties"], Value::Bool(false));
+        // key は省略可能
+        let required = &schema["definitions"]["SearchEntry"]["required"];
+        assert_eq!(required, &serde_json::json!(["url"]));
+    }
+
+    /// 同梱の config/config.schema.json が生成結果と一致していること。
+    #[test]
+    fn test_shipped_schema_is_up_to_date() {
+        let shipped = include_str!("../../config/config.schema.json");
+        assert_eq!(
+            shipped.replace("\r\n", "\n"),
+            generate(),
+            "config/config.schema.json を `muhenkan-switch-core generate-schema -o config/config.schema.json` で再生成してください"
+        );
+    }
+}
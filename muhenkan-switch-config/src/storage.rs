@@ -0,0 +1,282 @@
+//! config.toml の書き込み（排他ロック・アトミックな置き換え・バックアップ）。
+//!
+//! GUI の保存と CLI の toggle-timestamp-position などが同時に書き込んでも壊れないよう、
+//! 書き込みは `config.toml.lock` のアドバイザリロックを取ってから行う。
+//! 内容は一時ファイルに書いてから rename で置き換え、途中で落ちても元のファイルが残る。
+//! 置き換える前の内容は `backups/` に直近 [`BACKUP_LIMIT`] 件まで残す。
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 残すバックアップの件数。
+pub const BACKUP_LIMIT: usize = 10;
+
+/// バックアップを置くディレクトリ名（config.toml と同じ場所）。
+const BACKUP_DIR: &str = "backups";
+
+/// バックアップのファイル名に使う日時の形式。
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+/// 保持中のロック。drop で解放される。
+pub struct ConfigLock {
+    _file: File,
+}
+
+/// config.toml の排他ロックを取る。他のプロセスが保持中なら解放まで待つ。
+pub fn lock(path: &Path) -> Result<ConfigLock> {
+    let lock_path = sidecar(path, "lock");
+    if let Some(dir) = lock_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("ディレクトリの作成に失敗しました: {}", dir.display()))?;
+    }
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("ロックファイルを開けませんでした: {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("設定ファイルをロックできませんでした: {}", path.display()))?;
+    Ok(ConfigLock { _file: file })
+}
+
+/// ロックを取って内容を書き込む。置き換え前の内容のバックアップを返す（新規作成・同じ内容なら None）。
+pub fn write(path: &Path, content: &str) -> Result<Option<PathBuf>> {
+    let _lock = lock(path)?;
+    write_locked(path, content)
+}
+
+/// ロック取得済みの前提で内容を書き込む。
+/// 内容が変わる場合は元の内容をバックアップしてから一時ファイル経由で置き換える。
+pub(crate) fn write_locked(path: &Path, content: &str) -> Result<Option<PathBuf>> {
+    let mut backup_path = None;
+    if let Ok(current) = std::fs::read_to_string(path) {
+        if current == content {
+            return Ok(None);
+        }
+        backup_path = Some(backup(path, &current)?);
+    }
+
+    let tmp = sidecar(path, "tmp");
+    let result = (|| -> std::io::Result<()> {
+        let mut file = File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        std::fs::remove_file(&tmp).ok();
+    }
+    result.with_context(|| format!("設定ファイルの書き込みに失敗しました: {}", path.display()))?;
+    Ok(backup_path)
+}
+
+/// config.toml のバックアップ。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// 保存日時（表示用、"2025-01-02 03:04:05"）
+    pub saved_at: String,
+}
+
+/// バックアップのディレクトリ。
+pub fn backup_dir(path: &Path) -> PathBuf {
+    path.with_file_name(BACKUP_DIR)
+}
+
+/// バックアップを新しい順に返す。ディレクトリが無ければ空。
+pub fn list_backups(path: &Path) -> Result<Vec<Backup>> {
+    let dir = backup_dir(path);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("バックアップの一覧を取得できませんでした: {}", dir.display()))
+        }
+    };
+    let prefix = format!("{}.", file_stem(path));
+    let mut backups: Vec<(String, Backup)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let stamp = name.strip_prefix(&prefix)?.strip_suffix(".toml")?.to_string();
+            let time = chrono::NaiveDateTime::parse_from_str(&stamp, BACKUP_TIME_FORMAT).ok()?;
+            let backup = Backup {
+                path: entry.path(),
+                saved_at: time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            };
+            Some((stamp, backup))
+        })
+        .collect();
+    backups.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+}
+
+/// バックアップの内容で config.toml を置き換える。
+/// 読み込めないバックアップは拒否する。置き換え前の内容もバックアップされる。
+pub fn restore_backup(path: &Path, backup: &Path) -> Result<()> {
+    if backup.parent() != Some(backup_dir(path).as_path()) {
+        anyhow::bail!("バックアップではないファイルは復元できません: {}", backup.display());
+    }
+    let content = std::fs::read_to_string(backup)
+        .with_context(|| format!("バックアップの読み込みに失敗しました: {}", backup.display()))?;
+    crate::load_from(backup)
+        .with_context(|| format!("バックアップを読み込めませんでした: {}", backup.display()))?;
+    write(path, &content)?;
+    Ok(())
+}
+
+/// 読み込める最新のバックアップで config.toml を置き換える。
+/// 復元したバックアップを返す。復元できるものが無ければ None。
+pub fn restore_latest_backup(path: &Path) -> Result<Option<Backup>> {
+    for backup in list_backups(path)? {
+        if crate::load_from(&backup.path).is_ok() {
+            restore_backup(path, &backup.path)?;
+            return Ok(Some(backup));
+        }
+    }
+    Ok(None)
+}
+
+/// 置き換え前の内容を backups/ に保存し、古いものを削除する。保存したバックアップのパスを返す。
+fn backup(path: &Path, content: &str) -> Result<PathBuf> {
+    let dir = backup_dir(path);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("ディレクトリの作成に失敗しました: {}", dir.display()))?;
+    // 同じミリ秒に複数回保存した場合も上書きしないよう、空いている日時までずらす
+    let mut time = chrono::Local::now();
+    let mut target;
+    loop {
+        let stamp = time.format(BACKUP_TIME_FORMAT).to_string();
+        target = dir.join(format!("{}.{}.toml", file_stem(path), stamp));
+        if !target.exists() {
+            break;
+        }
+        time += chrono::Duration::milliseconds(1);
+    }
+    std::fs::write(&target, content)
+        .with_context(|| format!("バックアップの作成に失敗しました: {}", target.display()))?;
+    for old in list_backups(path)?.into_iter().skip(BACKUP_LIMIT) {
+        std::fs::remove_file(&old.path).ok();
+    }
+    Ok(target)
+}
+
+/// "config.toml" → "config"
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "config".to_string())
+}
+
+/// "config.toml" → "config.toml.<ext>"
+fn sidecar(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", ext));
+    path.with_file_name(name)
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PunctuationStyle;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("muhenkan_test_storage_{}", name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write_creates_file_without_backup() {
+        let dir = test_dir("create");
+        let path = dir.join("config.toml");
+        write(&path, "config_version = 1\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "config_version = 1\n");
+        assert!(list_backups(&path).unwrap().is_empty());
+        assert!(!sidecar(&path, "tmp").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_write_keeps_previous_versions() {
+        let dir = test_dir("rolling");
+        let path = dir.join("config.toml");
+        for i in 0..BACKUP_LIMIT + 3 {
+            write(&path, &format!("# {}\n", i)).unwrap();
+            // 同じ内容なら書き込まずバックアップも増えない
+            write(&path, &format!("# {}\n", i)).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let backups = list_backups(&path).unwrap();
+        assert_eq!(backups.len(), BACKUP_LIMIT);
+        // 新しい順
+        let newest = std::fs::read_to_string(&backups[0].path).unwrap();
+        assert_eq!(newest, format!("# {}\n", BACKUP_LIMIT + 1));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let dir = test_dir("restore");
+        let path = dir.join("config.toml");
+        write(&path, "config_version = 1\npunctuation_style = \"、。\"\n").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write(&path, "config_version = 1\npunctuation_style = \"，．\"\n").unwrap();
+        let backups = list_backups(&path).unwrap();
+        assert_eq!(backups.len(), 1);
+
+        restore_backup(&path, &backups[0].path).unwrap();
+        assert_eq!(crate::load_from(&path).unwrap().punctuation_style, PunctuationStyle::TenMaru);
+        // 復元前の内容もバックアップされている
+        assert_eq!(list_backups(&path).unwrap().len(), 2);
+
+        assert!(restore_backup(&path, &path).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_restore_latest_skips_broken_backup() {
+        let dir = test_dir("latest");
+        let path = dir.join("config.toml");
+        write(&path, "config_version = 1\npunctuation_style = \"，．\"\n").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write(&path, "punctuation_style = [\n").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write(&path, "broken = \n").unwrap();
+
+        let restored = restore_latest_backup(&path).unwrap().unwrap();
+        assert!(restored.path.starts_with(backup_dir(&path)));
+        assert_eq!(crate::load_from(&path).unwrap().punctuation_style, PunctuationStyle::CommaPeriod);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_lock_serializes_writers() {
+        let dir = test_dir("lock");
+        let path = dir.join("config.toml");
+        write(&path, "0").unwrap();
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let _lock = lock(&path).unwrap();
+                    let n: u32 = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+                    write_locked(&path, &(n + 1).to_string()).unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "8");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,11 +1,13 @@
-use crate::Config;
+use crate::keys::{self, KeyRole};
+use crate::extra_layers;
+use crate::{Config, KeyedEntry, Layout, Modifier, DISPATCH_KEYS};
 
 /// キーの物理配置を定義する構造体。
 struct KeyDef {
     /// 物理キー名（大文字表示用）
     label: &'static str,
     /// config 検索用（小文字）
-    dispatch_key: Option<&'static str>,
+    name: &'static str,
     /// SVG 上の x 座標
     x: f64,
     /// SVG 上の y 座標
@@ -16,44 +18,22 @@ struct KeyDef {
 
 #[derive(Clone, Copy, PartialEq)]
 enum KeyCategory {
-    /// 左手割当キー（config で色が決まる）
+    /// 割当可能キー（config で色が決まる）
     Dispatch,
     /// タイムスタンプキー（V, C, X）
     Timestamp,
-    /// 右手テキスト編集キー（固定）
+    /// 右手テキスト編集キー（[editing] で操作が決まる）
     TextEdit,
-    /// 無変換レイヤーで未使用のキー
-    Unused,
+    /// 記号入力キー（[symbols] で出力が決まる）
+    Symbol,
 }
 
-/// 右手キーの固定機能名。
-/// 右手キーの固定機能名（kanata/muhenkan.kbd の mh-layer 定義に準拠）。
-fn text_edit_label(key: &str, punctuation_style: &str) -> &'static str {
-    match key {
-        // hjkl: Vim 風カーソル移動
-        "H" => "←",
-        "J" => "↓",
-        "K" => "↑",
-        "L" => "→",
-        // uiyo: 単語移動 / 行頭行末
-        "U" => "単語←",
-        "I" => "単語→",
-        "Y" => "Home",
-        "O" => "End",
-        // nm;: 削除
-        "N" => "BS",
-        "M" => "Del",
-        ";" => "Esc",
-        // ,.: 句読点（4パターン対応）
-        "," => match punctuation_style {
-            "，．" | "，。" => "全角，",
-            _ => "、",
-        },
-        "." => match punctuation_style {
-            "，．" | "、．" => "全角．",
-            _ => "。",
-        },
-        _ => "",
+/// 記号キーのラベル。全角の英数字・記号は半角と区別できるよう「全角」を付ける。
+fn symbol_label(symbol: char) -> String {
+    if ('\u{FF01}'..='\u{FF5E}').contains(&symbol) {
+        format!("全角{}", symbol)
+    } else {
+        symbol.to_string()
     }
 }
 
@@ -75,6 +55,9 @@ const KEY_PITCH: f64 = KEY_W + KEY_GAP; // 56.0
 const CORNER_R: f64 = 6.0;
 const FONT_SIZE_TOP: f64 = 13.0;
 const FONT_SIZE_BOTTOM: f64 = 11.0;
+/// タイトル行の高さ
+const TITLE_H: f64 = 28.0;
+const FONT_SIZE_TITLE: f64 = 15.0;
 
 /// QWERTY 物理配列の (row, col) から SVG 座標を計算するヘルパー。
 /// row: 0=数字行, 1=Q行, 2=A行(ホーム), 3=Z行
@@ -90,98 +73,77 @@ fn qwerty_pos(row: usize, col: usize, hand_gap: f64) -> (f64, f64) {
         _ => KEY_PITCH * 1.25,  // Z行（Shift 幅分）
     };
     let x = pad + row_offset + col as f64 * KEY_PITCH + hand_gap;
-    let y = pad + row as f64 * KEY_PITCH;
+    let y = pad + TITLE_H + row as f64 * KEY_PITCH;
     (x, y)
 }
 
-/// JIS キーボードの QWERTY 物理配列に基づくキー定義を生成する。
-fn key_definitions() -> Vec<KeyDef> {
+/// JIS / US キーボードの QWERTY 物理配列に基づくキー定義を生成する（keys::KEYS から）。
+fn key_definitions(layout: Layout) -> Vec<KeyDef> {
     // 左右の手の間（col 4→5 の間）に挿入するギャップ
     let gap = KEY_PITCH * 1.5;
 
-    // ヘルパー: 左手キー (gap なし)
-    let l = |row, col| -> (f64, f64) { qwerty_pos(row, col, 0.0) };
-    // ヘルパー: 右手キー (gap あり)
-    let r = |row, col| -> (f64, f64) { qwerty_pos(row, col, gap) };
-
-    vec![
-        // ── 数字行 (row 0) ──
-        //   1(c0) 2(c1) 3(c2) 4(c3) 5(c4)  6(c5) 7(c6) 8(c7) 9(c8) 0(c9)
-        KeyDef { label: "1", dispatch_key: Some("1"), x: l(0,0).0, y: l(0,0).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "2", dispatch_key: Some("2"), x: l(0,1).0, y: l(0,1).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "3", dispatch_key: Some("3"), x: l(0,2).0, y: l(0,2).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "4", dispatch_key: Some("4"), x: l(0,3).0, y: l(0,3).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "5", dispatch_key: Some("5"), x: l(0,4).0, y: l(0,4).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "6", dispatch_key: None, x: r(0,5).0, y: r(0,5).1, category: KeyCategory::Unused },
-        KeyDef { label: "7", dispatch_key: None, x: r(0,6).0, y: r(0,6).1, category: KeyCategory::Unused },
-        KeyDef { label: "8", dispatch_key: None, x: r(0,7).0, y: r(0,7).1, category: KeyCategory::Unused },
-        KeyDef { label: "9", dispatch_key: None, x: r(0,8).0, y: r(0,8).1, category: KeyCategory::Unused },
-        KeyDef { label: "0", dispatch_key: None, x: r(0,9).0, y: r(0,9).1, category: KeyCategory::Unused },
-        // ── Q行 (row 1) ──
-        //   Q(c0) W(c1) E(c2) R(c3) T(c4)  |  Y(c5) U(c6) I(c7) O(c8) P(c9)
-        KeyDef { label: "Q", dispatch_key: Some("q"), x: l(1,0).0, y: l(1,0).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "W", dispatch_key: Some("w"), x: l(1,1).0, y: l(1,1).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "E", dispatch_key: Some("e"), x: l(1,2).0, y: l(1,2).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "R", dispatch_key: Some("r"), x: l(1,3).0, y: l(1,3).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "T", dispatch_key: Some("t"), x: l(1,4).0, y: l(1,4).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "Y", dispatch_key: None, x: r(1,5).0, y: r(1,5).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "U", dispatch_key: None, x: r(1,6).0, y: r(1,6).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "I", dispatch_key: None, x: r(1,7).0, y: r(1,7).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "O", dispatch_key: None, x: r(1,8).0, y: r(1,8).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "P", dispatch_key: None, x: r(1,9).0, y: r(1,9).1, category: KeyCategory::Unused },
-        // ── A行 / ホーム行 (row 2) ──
-        //   A(c0) S(c1) D(c2) F(c3) G(c4)  |  H(c5) J(c6) K(c7) L(c8) ;(c9)
-        KeyDef { label: "A", dispatch_key: Some("a"), x: l(2,0).0, y: l(2,0).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "S", dispatch_key: Some("s"), x: l(2,1).0, y: l(2,1).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "D", dispatch_key: Some("d"), x: l(2,2).0, y: l(2,2).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "F", dispatch_key: Some("f"), x: l(2,3).0, y: l(2,3).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "G", dispatch_key: Some("g"), x: l(2,4).0, y: l(2,4).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "H", dispatch_key: None, x: r(2,5).0, y: r(2,5).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "J", dispatch_key: None, x: r(2,6).0, y: r(2,6).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "K", dispatch_key: None, x: r(2,7).0, y: r(2,7).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "L", dispatch_key: None, x: r(2,8).0, y: r(2,8).1, category: KeyCategory::TextEdit },
-        KeyDef { label: ";", dispatch_key: None, x: r(2,9).0, y: r(2,9).1, category: KeyCategory::TextEdit },
-        // ── Z行 / 下行 (row 3) ──
-        //   Z(c0) X(c1) C(c2) V(c3) B(c4)  |  N(c5) M(c6) ,(c7) .(c8) /(c9)
-        KeyDef { label: "Z", dispatch_key: None, x: l(3,0).0, y: l(3,0).1, category: KeyCategory::Timestamp },
-        KeyDef { label: "X", dispatch_key: None, x: l(3,1).0, y: l(3,1).1, category: KeyCategory::Timestamp },
-        KeyDef { label: "C", dispatch_key: None, x: l(3,2).0, y: l(3,2).1, category: KeyCategory::Timestamp },
-        KeyDef { label: "V", dispatch_key: None, x: l(3,3).0, y: l(3,3).1, category: KeyCategory::Timestamp },
-        KeyDef { label: "B", dispatch_key: Some("b"), x: l(3,4).0, y: l(3,4).1, category: KeyCategory::Dispatch },
-        KeyDef { label: "N", dispatch_key: None, x: r(3,5).0, y: r(3,5).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "M", dispatch_key: None, x: r(3,6).0, y: r(3,6).1, category: KeyCategory::TextEdit },
-        KeyDef { label: ",", dispatch_key: None, x: r(3,7).0, y: r(3,7).1, category: KeyCategory::TextEdit },
-        KeyDef { label: ".", dispatch_key: None, x: r(3,8).0, y: r(3,8).1, category: KeyCategory::TextEdit },
-        KeyDef { label: "/", dispatch_key: None, x: r(3,9).0, y: r(3,9).1, category: KeyCategory::Unused },
-    ]
+    keys::layout_keys(layout)
+        .map(|k| {
+            let hand_gap = if k.col >= 5 { gap } else { 0.0 };
+            let (x, y) = qwerty_pos(k.row, k.col, hand_gap);
+            let category = match k.role {
+                KeyRole::Dispatch => KeyCategory::Dispatch,
+                KeyRole::Timestamp => KeyCategory::Timestamp,
+                KeyRole::TextEdit => KeyCategory::TextEdit,
+                KeyRole::Symbol => KeyCategory::Symbol,
+            };
+            KeyDef { label: k.label, name: k.name, x, y, category }
+        })
+        .collect()
 }
 
-/// config の割当キーに対する割当カテゴリとエントリ名を返す。
-fn lookup_dispatch<'a>(config: &'a Config, key: &str) -> Option<(&'static str, &'a str)> {
-    for (name, entry) in &config.folders {
-        if entry.dispatch_key() == Some(key) {
+/// config の割当キー（指定レイヤー）に対する割当カテゴリとエントリ名を返す。
+fn lookup_dispatch<'a>(
+    config: &'a Config,
+    key: &str,
+    modifier: Modifier,
+) -> Option<(&'static str, &'a str)> {
+    for (name, entry) in config.active_folders() {
+        if entry.key_for(modifier) == Some(key) {
             return Some(("folder", name.as_str()));
         }
     }
-    for (name, entry) in &config.search {
-        if entry.dispatch_key() == Some(key) {
+    for (name, entry) in config.active_search() {
+        if entry.key_for(modifier) == Some(key) {
             return Some(("search", name.as_str()));
         }
     }
-    for (name, entry) in &config.apps {
-        if entry.dispatch_key() == Some(key) {
+    for (name, entry) in config.active_apps() {
+        if entry.key_for(modifier) == Some(key) {
             return Some(("app", name.as_str()));
         }
     }
+    for (name, entry) in &config.commands {
+        if entry.key_for(modifier) == Some(key) {
+            return Some(("command", name.as_str()));
+        }
+    }
+    for (name, entry) in &config.snippets {
+        if entry.key_for(modifier) == Some(key) {
+            return Some(("snippet", name.as_str()));
+        }
+    }
     None
 }
 
+/// SVG のタイトル（例: "無変換 + キー"）。
+fn title(config: &Config) -> String {
+    format!("{} + キー", config.trigger_key().label())
+}
+
 /// カテゴリに応じた塗り色を返す。
 fn fill_color(category: &str) -> &'static str {
     match category {
         "folder" => "#fff2cc",
         "search" => "#ffe6cc",
         "app" => "#f8cecc",
+        "command" => "#d5e8d4",
+        "snippet" => "#d0f0ef",
         "timestamp" => "#e1d5e7",
         "textedit" => "#dae8fc",
         "unused" => "#d9d9d9",
@@ -197,15 +159,95 @@ fn xml_escape(s: &str) -> String {
         .replace('"', "&quot;")
 }
 
+/// キー1つ分（矩形と上下のラベル）を描画する。y は図の上端からのずれ。
+fn push_key(svg: &mut String, key: &KeyDef, dy: f64, fill: &str, bottom_label: &str, shift: Option<&str>) {
+    let y = key.y + dy;
+
+    // キー矩形
+    svg.push_str(&format!(
+        r##"<rect x="{x}" y="{y}" width="{w}" height="{h}" rx="{r}" fill="{fill}" stroke="#999" stroke-width="1"/>"##,
+        x = key.x,
+        w = KEY_W,
+        h = KEY_H,
+        r = CORNER_R,
+    ));
+
+    // 上段ラベル（物理キー名）
+    let top_y = y + 18.0;
+    let cx = key.x + KEY_W / 2.0;
+    svg.push_str(&format!(
+        r##"<text x="{cx}" y="{top_y}" text-anchor="middle" font-size="{fs}" font-weight="bold" fill="#333">{label}</text>"##,
+        fs = FONT_SIZE_TOP,
+        label = xml_escape(key.label),
+    ));
+
+    // Shift レイヤーのラベルがある場合は下段ラベルを少し上に詰める
+    let lift = if shift.is_some() { 3.0 } else { 0.0 };
+
+    // 下段ラベル（機能名/エントリ名）
+    if !bottom_label.is_empty() {
+        // " (" を含む長いラベルは2行に分割
+        if let Some(pos) = bottom_label.find(" (") {
+            let line1 = xml_escape(&bottom_label[..pos]);
+            let line2 = xml_escape(&bottom_label[pos + 1..]);
+            let y1 = y + 33.0 - lift;
+            let y2 = y + 44.0 - lift;
+            let fs = FONT_SIZE_BOTTOM - 2.0;
+            svg.push_str(&format!(
+                r##"<text x="{cx}" y="{y1}" text-anchor="middle" font-size="{fs}" fill="#666">{line1}</text>"##,
+            ));
+            svg.push_str(&format!(
+                r##"<text x="{cx}" y="{y2}" text-anchor="middle" font-size="{fs}" fill="#666">{line2}</text>"##,
+            ));
+        } else {
+            let bot_y = y + 38.0 - lift;
+            let escaped = xml_escape(bottom_label);
+            let char_count = bottom_label.chars().count();
+            let fs = if char_count > 6 {
+                FONT_SIZE_BOTTOM - 2.0
+            } else {
+                FONT_SIZE_BOTTOM
+            };
+            svg.push_str(&format!(
+                r##"<text x="{cx}" y="{bot_y}" text-anchor="middle" font-size="{fs}" fill="#666">{escaped}</text>"##,
+            ));
+        }
+    }
+    // Shift レイヤーのラベル（最下段、⇧ 付き）
+    if let Some(name) = shift {
+        let shift_y = y + 49.0;
+        let fs = FONT_SIZE_BOTTOM - 3.0;
+        let escaped = xml_escape(&format!("⇧{}", name));
+        svg.push_str(&format!(
+            r##"<text x="{cx}" y="{shift_y}" text-anchor="middle" font-size="{fs}" fill="#888">{escaped}</text>"##,
+        ));
+    }
+}
+
+/// 図のタイトルを描画する。
+fn push_title(svg: &mut String, dy: f64, title: &str) {
+    svg.push_str(&format!(
+        r##"<text x="20" y="{y}" font-size="{fs}" font-weight="bold" fill="#333">{title}</text>"##,
+        y = dy + 20.0 + FONT_SIZE_TITLE,
+        fs = FONT_SIZE_TITLE,
+        title = xml_escape(title),
+    ));
+}
+
 /// config.toml の内容からキーボードレイアウト SVG を生成する。
+/// 追加レイヤー（[extra_layers]）を有効にしている場合は、その図を下に並べる。
 pub fn generate(config: &Config) -> String {
-    let keys = key_definitions();
+    let keys = key_definitions(config.layout);
+    let symbols = config.symbol_outputs();
+    let editing = config.editing_keys();
+    let layers = extra_layers::enabled(config);
 
-    // SVG サイズを計算
+    // SVG サイズを計算（図1枚分の高さ × 図の数）
     let max_x = keys.iter().map(|k| k.x + KEY_W).fold(0.0f64, f64::max);
     let max_y = keys.iter().map(|k| k.y + KEY_H).fold(0.0f64, f64::max);
     let svg_w = max_x + 20.0;
-    let svg_h = max_y + 20.0;
+    let diagram_h = max_y + 20.0;
+    let svg_h = diagram_h * (1 + layers.len()) as f64;
 
     let mut svg = format!(
         r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {svg_w} {svg_h}" width="{svg_w}" height="{svg_h}" font-family="sans-serif">"#,
@@ -216,77 +258,66 @@ pub fn generate(config: &Config) -> String {
         r##"<rect width="{svg_w}" height="{svg_h}" fill="#ffffff" rx="8"/>"##,
     ));
 
+    // タイトル（トリガーキー）
+    push_title(&mut svg, 0.0, &title(config));
+
     for key in &keys {
-        let (fill, bottom_label) = match key.category {
-            KeyCategory::TextEdit => {
-                (fill_color("textedit"), text_edit_label(key.label, &config.punctuation_style).to_string())
+        let symbol = symbols.iter().find(|(k, _)| k.name == key.name).map(|(_, c)| *c);
+        let edit = editing.iter().find(|(k, _)| k.name == key.name).map(|(_, a)| *a);
+        // Shift レイヤーの割当（無変換+Shift+キー）
+        let shift = match key.category {
+            KeyCategory::Dispatch | KeyCategory::TextEdit if symbol.is_none() && edit.is_none() => {
+                lookup_dispatch(config, key.name, Modifier::Shift)
             }
-            KeyCategory::Timestamp => {
-                (fill_color("timestamp"), timestamp_label(key.label).to_string())
-            }
-            KeyCategory::Unused => {
+            _ => None,
+        };
+        let (fill, bottom_label) = match (symbol, edit, key.category) {
+            // 記号・編集操作を割り当てたキーは割当可能なキーでもそちらを優先する
+            (Some(symbol), _, _) => (fill_color("textedit"), symbol_label(symbol)),
+            (None, Some(action), _) => (fill_color("textedit"), action.label().to_string()),
+            (None, None, KeyCategory::Symbol) => {
                 (fill_color("unused"), String::new())
             }
-            KeyCategory::Dispatch => {
-                if let Some(dk) = key.dispatch_key {
-                    if let Some((cat, name)) = lookup_dispatch(config, dk) {
-                        (fill_color(cat), name.to_string())
-                    } else {
-                        (fill_color("unassigned"), String::new())
-                    }
-                } else {
+            (None, None, KeyCategory::Timestamp) => {
+                (fill_color("timestamp"), timestamp_label(key.label).to_string())
+            }
+            // 編集操作のないテキスト編集キーも割当キーにできる
+            (None, None, KeyCategory::Dispatch | KeyCategory::TextEdit) => {
+                if let Some((cat, name)) = lookup_dispatch(config, key.name, Modifier::None) {
+                    (fill_color(cat), name.to_string())
+                } else if let Some((cat, _)) = shift {
+                    (fill_color(cat), String::new())
+                } else if DISPATCH_KEYS.contains(&key.name) {
                     (fill_color("unassigned"), String::new())
+                } else {
+                    // 割当可能だが kbd でディスパッチに回していないキー
+                    (fill_color("unused"), String::new())
                 }
             }
         };
 
-        // キー矩形
-        svg.push_str(&format!(
-            r##"<rect x="{x}" y="{y}" width="{w}" height="{h}" rx="{r}" fill="{fill}" stroke="#999" stroke-width="1"/>"##,
-            x = key.x,
-            y = key.y,
-            w = KEY_W,
-            h = KEY_H,
-            r = CORNER_R,
-        ));
-
-        // 上段ラベル（物理キー名）
-        let top_y = key.y + 18.0;
-        let cx = key.x + KEY_W / 2.0;
-        svg.push_str(&format!(
-            r##"<text x="{cx}" y="{top_y}" text-anchor="middle" font-size="{fs}" font-weight="bold" fill="#333">{label}</text>"##,
-            fs = FONT_SIZE_TOP,
-            label = xml_escape(key.label),
-        ));
+        push_key(&mut svg, key, 0.0, fill, &bottom_label, shift.map(|(_, name)| name));
+    }
 
-        // 下段ラベル（機能名/エントリ名）
-        if !bottom_label.is_empty() {
-            // " (" を含む長いラベルは2行に分割
-            if let Some(pos) = bottom_label.find(" (") {
-                let line1 = xml_escape(&bottom_label[..pos]);
-                let line2 = xml_escape(&bottom_label[pos + 1..]);
-                let y1 = key.y + 33.0;
-                let y2 = key.y + 44.0;
-                let fs = FONT_SIZE_BOTTOM - 2.0;
-                svg.push_str(&format!(
-                    r##"<text x="{cx}" y="{y1}" text-anchor="middle" font-size="{fs}" fill="#666">{line1}</text>"##,
-                ));
-                svg.push_str(&format!(
-                    r##"<text x="{cx}" y="{y2}" text-anchor="middle" font-size="{fs}" fill="#666">{line2}</text>"##,
-                ));
-            } else {
-                let bot_y = key.y + 38.0;
-                let escaped = xml_escape(&bottom_label);
-                let char_count = bottom_label.chars().count();
-                let fs = if char_count > 6 {
-                    FONT_SIZE_BOTTOM - 2.0
-                } else {
-                    FONT_SIZE_BOTTOM
-                };
-                svg.push_str(&format!(
-                    r##"<text x="{cx}" y="{bot_y}" text-anchor="middle" font-size="{fs}" fill="#666">{escaped}</text>"##,
-                ));
-            }
+    // 追加レイヤー（マウス・テンキー）
+    for (i, layer) in layers.iter().enumerate() {
+        let dy = diagram_h * (i + 1) as f64;
+        push_title(
+            &mut svg,
+            dy,
+            &format!(
+                "{}（{} + {} で切り替え、Esc で戻る）",
+                layer.title,
+                config.trigger_key().label(),
+                layer.entry_label
+            ),
+        );
+        for key in &keys {
+            let (fill, label) = match layer.action(key.name) {
+                Some((_, label)) => (fill_color("textedit"), label),
+                None => (fill_color("unused"), ""),
+            };
+            push_key(&mut svg, key, dy, fill, label, None);
         }
     }
 
@@ -323,6 +354,125 @@ mod tests {
         assert!(svg.contains("Home"), "SVG should contain 'Home'");
     }
 
+    #[test]
+    fn test_svg_editing_labels_follow_config() {
+        let mut config = default_config();
+        config.editing.keys.insert("u".to_string(), crate::EditAction::PageUp);
+        let svg = generate(&config);
+        assert!(svg.contains(">PgUp</text>"));
+        assert!(!svg.contains(">単語←</text>"));
+
+        config.editing.preset = crate::EditingPreset::None;
+        let svg = generate(&config);
+        assert!(!svg.contains(">←</text>"));
+    }
+
+    #[test]
+    fn test_svg_shows_symbols() {
+        let mut config = default_config();
+        config.punctuation_style = crate::PunctuationStyle::CommaPeriod;
+        config.symbols.insert("[".to_string(), '「');
+        config.symbols.insert("/".to_string(), '・');
+        let svg = generate(&config);
+        assert!(svg.contains(">全角，<") && svg.contains(">全角．<"), "punctuation labels");
+        assert!(svg.contains(">「<") && svg.contains(">・<"), "symbol labels");
+    }
+
+    #[test]
+    fn test_svg_title_follows_trigger() {
+        let mut config = default_config();
+        config.trigger.key = Some(crate::TriggerKey::Henkan);
+        assert!(generate(&config).contains(">変換 + キー</text>"));
+    }
+
+    #[test]
+    fn test_svg_extra_layers() {
+        let mut config = default_config();
+        let single = generate(&config);
+        config.extra_layers.mouse = true;
+        config.extra_layers.numpad = true;
+        let svg = generate(&config);
+        assert!(svg.contains(">マウス（無変換 + Space で切り替え、Esc で戻る）</text>"));
+        assert!(svg.contains(">テンキー（無変換 + Tab で切り替え、Esc で戻る）</text>"));
+        assert!(svg.contains(">左クリック</text>"));
+        // 図ごとにすべてのキーを描く
+        let count = |svg: &str| svg.matches("<rect").count();
+        assert_eq!(count(&svg) - 1, (count(&single) - 1) * 3);
+    }
+
+    #[test]
+    fn test_svg_ansi_layout() {
+        let mut config = default_config();
+        assert!(generate(&config).contains(">@</text>"));
+        config.layout = Layout::Ansi;
+        let svg = generate(&config);
+        assert!(svg.contains(">CapsLock + キー</text>"));
+        assert!(svg.contains(">'</text>") && svg.contains(">\\</text>"));
+        assert!(!svg.contains(">@</text>") && !svg.contains(">:</text>"));
+    }
+
+    #[test]
+    fn test_svg_spare_key_assigned() {
+        let mut config = default_config();
+        let unused_fill = r##"fill="#d9d9d9""##;
+        assert!(generate(&config).contains(unused_fill));
+        config.apps.insert(
+            "SpareApp".to_string(),
+            crate::AppEntry {
+                key: Some("p".parse().unwrap()),
+                shift_key: None,
+                process: "spare".to_string(),
+                command: None,
+            },
+        );
+        let svg = generate(&config);
+        assert!(svg.contains("SpareApp"), "SVG should contain the spare key entry");
+    }
+
+    #[test]
+    fn test_svg_shows_shift_label() {
+        let mut config = default_config();
+        config.search.insert(
+            "Wiki".to_string(),
+            crate::SearchEntry {
+                key: None,
+                shift_key: Some("g".parse().unwrap()),
+                url: "https://ja.wikipedia.org/w/index.php?search={query}".to_string(),
+            },
+        );
+        let svg = generate(&config);
+        assert!(svg.contains("Google"), "base label should remain");
+        assert!(svg.contains("⇧Wiki"), "SVG should contain the shift label");
+    }
+
+    #[test]
+    fn test_svg_uses_active_profile() {
+        let mut config = default_config();
+        let search = [(
+            "辞書".to_string(),
+            crate::SearchEntry {
+                key: Some("g".parse().unwrap()),
+                shift_key: None,
+                url: "https://dictionary.goo.ne.jp/srch/all/{query}/m0u/".to_string(),
+            },
+        )]
+        .into_iter()
+        .collect();
+        config.profiles.insert(
+            "執筆".to_string(),
+            crate::Profile {
+                search: Some(search),
+                ..Default::default()
+            },
+        );
+        assert!(!generate(&config).contains("辞書"));
+
+        config.active_profile = Some("執筆".to_string());
+        let svg = generate(&config);
+        assert!(svg.contains("辞書"), "SVG should show the profile's search");
+        assert!(!svg.contains("Google"), "replaced section should be hidden");
+    }
+
     #[test]
     fn test_empty_config_no_error() {
         let config: Config = toml::from_str("[search]\n[folders]\n[apps]\n").unwrap();
@@ -336,7 +486,7 @@ mod tests {
         let config = default_config();
         let svg = generate(&config);
         let rect_count = svg.matches("<rect ").count();
-        let expected_keys = key_definitions().len();
+        let expected_keys = key_definitions(Layout::Jis).len();
         assert_eq!(
             rect_count,
             expected_keys + 1,
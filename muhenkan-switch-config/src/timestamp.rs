@@ -0,0 +1,145 @@
+//! タイムスタンプのフォーマットの検証とプレビュー。
+//!
+//! GUI のプレビューと validate が同じ判定を使うよう、ここにまとめる。
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+
+use crate::diagnostic::{Diagnostic, DiagnosticKind};
+use crate::{TimestampConfig, TimestampPosition};
+
+/// ファイル名に使えない文字（Windows 基準）。
+pub const FORBIDDEN_FILENAME_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// フォーマットで日時を整形する。無効な指定子を含む場合は None。
+pub fn render(format: &str, at: &DateTime<Local>) -> Option<String> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return None;
+    }
+    use std::fmt::Write;
+    let mut out = String::new();
+    write!(out, "{}", at.format(format)).ok()?;
+    Some(out)
+}
+
+/// フォーマットを検証する。問題があれば timestamp.format の診断を返す。
+pub fn check_format(format: &str) -> Option<Diagnostic> {
+    const PATH: &str = "timestamp.format";
+    if format.is_empty() {
+        return Some(
+            Diagnostic::error(
+                DiagnosticKind::EmptyTimestampFormat,
+                PATH,
+                "タイムスタンプのフォーマットを入力してください",
+            )
+            .with_suggestion(format!("例: \"{}\"", crate::default_format())),
+        );
+    }
+    let Some(rendered) = render(format, &Local::now()) else {
+        return Some(
+            Diagnostic::error(
+                DiagnosticKind::InvalidTimestampFormat,
+                PATH,
+                format!("タイムスタンプのフォーマット \"{}\" に無効な指定子が含まれています", format),
+            )
+            .with_suggestion("%Y %m %d %H %M %S などの chrono の指定子を使ってください"),
+        );
+    };
+    let forbidden: Vec<char> = rendered
+        .chars()
+        .filter(|c| FORBIDDEN_FILENAME_CHARS.contains(c) || c.is_control())
+        .collect();
+    if !forbidden.is_empty() {
+        let shown: String = forbidden
+            .iter()
+            .map(|c| c.escape_default().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Some(
+            Diagnostic::error(
+                DiagnosticKind::InvalidTimestampFormat,
+                PATH,
+                format!(
+                    "タイムスタンプ \"{}\" にファイル名に使えない文字が含まれます: {}",
+                    rendered.escape_default(),
+                    shown
+                ),
+            )
+            .with_suggestion("区切りには - や _ を使ってください（例: %H:%M → %H%M）"),
+        );
+    }
+    None
+}
+
+/// "FileName.txt" にタイムスタンプを付けた例を返す。
+pub fn preview(timestamp: &TimestampConfig, at: &DateTime<Local>) -> Result<String, Diagnostic> {
+    if let Some(diagnostic) = check_format(&timestamp.format) {
+        return Err(diagnostic);
+    }
+    let ts = render(&timestamp.format, at).unwrap_or_default();
+    let (stem, ext) = ("FileName", ".txt");
+    Ok(if timestamp.position == TimestampPosition::After {
+        format!("{}{}{}{}", stem, timestamp.delimiter, ts, ext)
+    } else {
+        format!("{}{}{}{}", ts, timestamp.delimiter, stem, ext)
+    })
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap()
+    }
+
+    fn timestamp(format: &str, position: TimestampPosition) -> TimestampConfig {
+        TimestampConfig {
+            format: format.to_string(),
+            position,
+            delimiter: "_".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_valid_and_invalid() {
+        assert_eq!(render("%Y%m%d", &at()).as_deref(), Some("20250102"));
+        assert_eq!(render("%Y-%Q", &at()), None);
+        assert_eq!(render("%", &at()), None);
+    }
+
+    #[test]
+    fn test_check_format_rejects_filename_illegal_output() {
+        let diagnostic = check_format("%H:%M").unwrap();
+        assert_eq!(diagnostic.kind, DiagnosticKind::InvalidTimestampFormat);
+        assert!(diagnostic.message.contains(':'), "{}", diagnostic.message);
+        assert!(check_format("%Y/%m/%d").is_some());
+        assert!(check_format("%Y%m%d%n").is_some());
+        assert!(check_format("%Y-%m-%d_%H%M").is_none());
+    }
+
+    #[test]
+    fn test_check_format_empty_and_invalid() {
+        assert_eq!(check_format("").unwrap().kind, DiagnosticKind::EmptyTimestampFormat);
+        assert_eq!(
+            check_format("%Q").unwrap().kind,
+            DiagnosticKind::InvalidTimestampFormat
+        );
+    }
+
+    #[test]
+    fn test_preview_position() {
+        assert_eq!(
+            preview(&timestamp("%Y%m%d", TimestampPosition::Before), &at()).unwrap(),
+            "20250102_FileName.txt"
+        );
+        assert_eq!(
+            preview(&timestamp("%Y%m%d", TimestampPosition::After), &at()).unwrap(),
+            "FileName_20250102.txt"
+        );
+        assert!(preview(&timestamp("%H:%M", TimestampPosition::Before), &at()).is_err());
+    }
+}
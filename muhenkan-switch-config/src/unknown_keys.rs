@@ -0,0 +1,313 @@
+//! config.toml の不明なキー（typo など）の検出。
+//!
+//! serde は知らないフィールドを黙って無視するため、`postion = "after"` のような
+//! 書き間違いに気づけない。ここでは TOML を既知のキーの構造と照合し、
+//! 不明なキーを行番号と「もしかして」の候補付きの警告として返す。
+
+use toml_edit::{ImDocument, Item, TableLike};
+
+use crate::diagnostic::{quote_key, Diagnostic, DiagnosticKind};
+
+/// 既知のキーの構造。
+enum Node {
+    /// 値（中身は検査しない）
+    Value,
+    /// 決まったキーだけを持つテーブル
+    Fields(&'static [(&'static str, Node)]),
+    /// 任意の名前（ユーザーが付けるエントリ名など）→ 子
+    Map(&'static Node),
+    /// テーブルの配列（[[overrides]]）
+    Array(&'static Node),
+}
+
+use Node::{Array, Fields, Map, Value};
+
+const SEARCH_ENTRY: Node = Fields(&[("key", Value), ("shift_key", Value), ("url", Value)]);
+const FOLDER_ENTRY: Node = Fields(&[("key", Value), ("shift_key", Value), ("path", Value)]);
+const APP_ENTRY: Node = Fields(&[
+    ("key", Value),
+    ("shift_key", Value),
+    ("process", Value),
+    ("command", Value),
+]);
+const COMMAND_ENTRY: Node = Fields(&[
+    ("key", Value),
+    ("shift_key", Value),
+    ("command", Value),
+    ("show_output", Value),
+]);
+const SNIPPET_ENTRY: Node = Fields(&[("key", Value), ("shift_key", Value), ("text", Value)]);
+const OVERRIDE_ENTRY: Node = Fields(&[
+    ("process", Value),
+    ("keys", Map(&Value)),
+    ("shift_keys", Map(&Value)),
+]);
+const PROFILE: Node = Fields(&[
+    ("search", Map(&SEARCH_ENTRY)),
+    ("folders", Map(&FOLDER_ENTRY)),
+    ("apps", Map(&APP_ENTRY)),
+]);
+
+/// config.toml のトップレベル。Config にフィールドを追加したらここにも追加する。
+const ROOT: Node = Fields(&[
+    ("config_version", Value),
+    ("active_profile", Value),
+    ("punctuation_style", Value),
+    ("layout", Value),
+    ("symbols", Map(&Value)),
+    ("search", Map(&SEARCH_ENTRY)),
+    ("folders", Map(&FOLDER_ENTRY)),
+    ("apps", Map(&APP_ENTRY)),
+    ("commands", Map(&COMMAND_ENTRY)),
+    ("snippets", Map(&SNIPPET_ENTRY)),
+    ("overrides", Array(&OVERRIDE_ENTRY)),
+    ("profiles", Map(&PROFILE)),
+    (
+        "timestamp",
+        Fields(&[("format", Value), ("position", Value), ("delimiter", Value)]),
+    ),
+    ("editing", Fields(&[("preset", Value), ("keys", Map(&Value))])),
+    ("extra_layers", Fields(&[("mouse", Value), ("numpad", Value)])),
+    (
+        "trigger",
+        Fields(&[
+            ("key", Value),
+            ("tap_timeout", Value),
+            ("hold_timeout", Value),
+            ("behavior", Value),
+            ("double_tap", Value),
+        ]),
+    ),
+]);
+
+/// TOML の内容から不明なキーを探す。解析できない内容は読み込み時にエラーになるため空を返す。
+pub fn check(content: &str) -> Vec<Diagnostic> {
+    let Ok(doc) = ImDocument::parse(content) else {
+        return Vec::new();
+    };
+    let mut diagnostics = Vec::new();
+    walk(doc.as_table(), &ROOT, "", content, &mut diagnostics);
+    diagnostics
+}
+
+fn walk(table: &dyn TableLike, node: &Node, path: &str, content: &str, out: &mut Vec<Diagnostic>) {
+    for (key, item) in table.iter() {
+        match node {
+            Value | Array(_) => {}
+            Map(child) => {
+                let child_path = join(path, &quote_key(key));
+                visit(item, child, &child_path, content, out);
+            }
+            Fields(fields) => {
+                let child_path = join(path, &display_key(key));
+                match fields.iter().find(|(name, _)| *name == key) {
+                    Some((_, child)) => visit(item, child, &child_path, content, out),
+                    None => {
+                        let names: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+                        let line = table
+                            .get_key_value(key)
+                            .and_then(|(k, _)| k.span())
+                            .map(|span| line_of(content, span.start));
+                        out.push(unknown(key, child_path, &names, line));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn visit(item: &Item, node: &Node, path: &str, content: &str, out: &mut Vec<Diagnostic>) {
+    match (node, item) {
+        (Array(child), Item::ArrayOfTables(array)) => {
+            for (i, table) in array.iter().enumerate() {
+                walk(table, child, &format!("{}[{}]", path, i), content, out);
+            }
+        }
+        (Fields(_) | Map(_), _) => {
+            if let Some(table) = item.as_table_like() {
+                walk(table, node, path, content, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn unknown(key: &str, path: String, known: &[&str], line: Option<usize>) -> Diagnostic {
+    let suggestion = match closest(key, known) {
+        Some(name) => format!("'{}' の誤りではありませんか", name),
+        None => format!("使用できるキー: {}", known.join(", ")),
+    };
+    let mut diagnostic = Diagnostic::warning(
+        DiagnosticKind::UnknownKey,
+        path,
+        format!("不明なキー '{}' は無視されます", key),
+    )
+    .with_suggestion(suggestion);
+    diagnostic.line = line;
+    diagnostic
+}
+
+/// 編集距離が十分近い既知のキーを返す。
+fn closest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let key = key.to_lowercase();
+    let threshold = (key.chars().count() / 3).max(1);
+    known
+        .iter()
+        .map(|name| (edit_distance(&key, name), *name))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// レーベンシュタイン距離（文字単位）。
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+/// 裸のキーとして書けるものはそのまま、それ以外は引用符付きで表記する。
+fn display_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        quote_key(key)
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// バイト位置の行番号（1 始まり）。
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_misspelled_timestamp_field() {
+        let diagnostics = check("[timestamp]\nformat = \"%Y%m%d\"\npostion = \"after\"\n");
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        let d = &diagnostics[0];
+        assert_eq!(d.kind, DiagnosticKind::UnknownKey);
+        assert!(!d.is_error());
+        assert_eq!(d.path, "timestamp.postion");
+        assert_eq!(d.line, Some(3));
+        assert_eq!(d.suggestion.as_deref(), Some("'position' の誤りではありませんか"));
+    }
+
+    #[test]
+    fn test_misspelled_inline_entry_field() {
+        let content = "[apps]\n\"エディタ\" = {key = \"a\", process = \"Code\", comand = \"code\"}\n";
+        let diagnostics = check(content);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].path, r#"apps."エディタ".comand"#);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("'command' の誤りではありませんか")
+        );
+    }
+
+    #[test]
+    fn test_unknown_section_and_nested_tables() {
+        let content = "\
+[serach]
+Google = {url = \"https://www.google.com/search?q={query}\"}
+
+[[overrides]]
+process = \"firefox\"
+key = {g = \"Google\"}
+
+[profiles.\"仕事\".folders]
+\"共有\" = {path = \"~/Shared\", shortcut = \"1\"}
+";
+        let paths: Vec<String> = check(content).into_iter().map(|d| d.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "serach",
+                "overrides[0].key",
+                r#"profiles."仕事".folders."共有".shortcut"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_suggestion_for_distant_key() {
+        let diagnostics = check("[timestamp]\nzzz = 1\n");
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("使用できるキー: format, position, delimiter")
+        );
+    }
+
+    #[test]
+    fn test_shipped_configs_have_no_unknown_keys() {
+        for content in [
+            include_str!("../../config/default.toml"),
+            include_str!("../../config/default-linux.toml"),
+            include_str!("../../config/default-macos.toml"),
+            include_str!("../../config/default-windows.toml"),
+        ] {
+            assert!(check(content).is_empty(), "{:?}", check(content));
+        }
+    }
+
+    #[test]
+    fn test_saved_config_has_no_unknown_keys() {
+        // Config にフィールドを追加したら ROOT にも追加しているかの確認
+        let dir = std::env::temp_dir().join("muhenkan_test_unknown_keys_saved");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::remove_file(&path).ok();
+        let mut config = crate::default_config();
+        config.overrides.push(crate::OverrideEntry {
+            process: "firefox".to_string(),
+            keys: [("g".parse().unwrap(), "Google".to_string())].into_iter().collect(),
+            shift_keys: [("g".parse().unwrap(), "Google".to_string())].into_iter().collect(),
+        });
+        config.active_profile = Some("仕事".to_string());
+        config.profiles.insert(
+            "仕事".to_string(),
+            crate::Profile {
+                search: Some(config.search.clone()),
+                folders: Some(config.folders.clone()),
+                apps: Some(config.apps.clone()),
+            },
+        );
+        crate::save(&path, &config).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(check(&content).is_empty(), "{:?}", check(&content));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("postion", "position"), 1);
+        assert_eq!(edit_distance("comand", "command"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,112 @@
+//! config.toml の検証（CI やエディタ連携向け）。
+//! 不明なキー（typo など）やフォルダの存在も確認する。
+//! 読み込めない設定（構文エラーや不正な値）もエラーの診断として出力する。
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::{self, Diagnostic, DiagnosticKind};
+
+/// 設定を検証して診断を出力する。エラーが無ければ true。
+pub fn run(config_file: Option<&Path>, json: bool) -> Result<bool> {
+    let diagnostics = match config::load_lenient_with(config_file) {
+        Ok((config, mut diagnostics)) => {
+            diagnostics.extend(config::validate_deep(&config));
+            diagnostics
+        }
+        Err(e) => vec![Diagnostic::error(DiagnosticKind::ParseError, "", format!("{:#}", e))],
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        print!("{}", format(&diagnostics));
+    }
+    Ok(!config::has_errors(&diagnostics))
+}
+
+/// 診断を1件ずつ「[ファイル:行: ]重大度: パス: メッセージ」の形式で整形する。
+fn format(diagnostics: &[Diagnostic]) -> String {
+    if diagnostics.is_empty() {
+        return "問題は見つかりませんでした\n".to_string();
+    }
+    let mut out = String::new();
+    for d in diagnostics {
+        let severity = if d.is_error() { "エラー" } else { "警告" };
+        match (&d.file, d.line) {
+            (Some(file), Some(line)) => out.push_str(&format!("{}:{}: ", file.display(), line)),
+            (Some(file), None) => out.push_str(&format!("{}: ", file.display())),
+            _ => {}
+        }
+        if d.path.is_empty() {
+            out.push_str(&format!("{}: {}\n", severity, d.message));
+        } else {
+            out.push_str(&format!("{}: {}: {}\n", severity, d.path, d.message));
+        }
+        if let Some(suggestion) = &d.suggestion {
+            out.push_str(&format!("  → {}\n", suggestion));
+        }
+    }
+    out
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_includes_path_and_suggestion() {
+        let diagnostics = [Diagnostic::error(
+            DiagnosticKind::MissingQueryPlaceholder,
+            r#"search."bad".url"#,
+            "URL に {query} がありません",
+        )
+        .with_suggestion("{query} を入れてください")];
+        assert_eq!(
+            format(&diagnostics),
+            "エラー: search.\"bad\".url: URL に {query} がありません\n  → {query} を入れてください\n"
+        );
+    }
+
+    #[test]
+    fn format_includes_location() {
+        let mut diagnostic = Diagnostic::warning(
+            DiagnosticKind::UnknownKey,
+            "timestamp.postion",
+            "不明なキー 'postion' は無視されます",
+        );
+        diagnostic.file = Some("config.toml".into());
+        diagnostic.line = Some(3);
+        assert!(format(&[diagnostic])
+            .starts_with("config.toml:3: 警告: timestamp.postion: 不明なキー"));
+    }
+
+    #[test]
+    fn format_empty() {
+        assert_eq!(format(&[]), "問題は見つかりませんでした\n");
+    }
+
+    #[test]
+    fn run_reports_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "config_version = 1\n[search]\nbad = {key = \"g\", url = \"https://example.com/\"}\n",
+        )
+        .unwrap();
+        assert!(!run(Some(&path), true).unwrap());
+
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("../config/default-linux.toml");
+        assert!(run(Some(&fixture), false).unwrap());
+    }
+
+    #[test]
+    fn run_reports_invalid_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "config_version = 1\n[timestamp]\nposition = \"middle\"\n").unwrap();
+        assert!(!run(Some(&path), true).unwrap());
+    }
+}
@@ -3,13 +3,20 @@ pub fn get_foreground_explorer_hwnd() -> Option<isize> {
     imp::get_foreground_explorer_hwnd()
 }
 
+/// 前面ウィンドウのアプリ識別名を返す（[[overrides]] の process との照合用）。
+/// Windows: 実行ファイル名 / Linux: プロセス名と WM_CLASS / macOS: アプリ名。
+/// 取得できなければ空。
+pub fn get_foreground_app_names() -> Vec<String> {
+    imp::get_foreground_app_names()
+}
+
 // ── Platform: Windows ──
 
 #[cfg(target_os = "windows")]
 mod imp {
     use std::ffi::OsString;
     use std::os::windows::ffi::OsStringExt;
-    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::Foundation::{CloseHandle, HWND};
     use windows::Win32::System::Diagnostics::ToolHelp::{
         CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
         TH32CS_SNAPPROCESS,
@@ -17,6 +24,22 @@ mod imp {
     use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
 
     pub(super) fn get_foreground_explorer_hwnd() -> Option<isize> {
+        let (hwnd, exe_name) = foreground_process()?;
+        if exe_name.to_ascii_lowercase() == "explorer.exe" {
+            Some(hwnd.0 as isize)
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn get_foreground_app_names() -> Vec<String> {
+        foreground_process()
+            .map(|(_, exe_name)| vec![exe_name])
+            .unwrap_or_default()
+    }
+
+    /// 前面ウィンドウとそのプロセスの実行ファイル名を返す
+    fn foreground_process() -> Option<(HWND, String)> {
         unsafe {
             let hwnd = GetForegroundWindow();
             let mut pid: u32 = 0;
@@ -35,7 +58,7 @@ mod imp {
                 ..Default::default()
             };
 
-            let mut is_explorer = false;
+            let mut exe_name = None;
             if Process32FirstW(snapshot, &mut entry).is_ok() {
                 loop {
                     if entry.th32ProcessID == pid {
@@ -44,10 +67,11 @@ mod imp {
                             .iter()
                             .position(|&c| c == 0)
                             .unwrap_or(entry.szExeFile.len());
-                        let exe_name = OsString::from_wide(&entry.szExeFile[..exe_len])
-                            .to_string_lossy()
-                            .to_ascii_lowercase();
-                        is_explorer = exe_name == "explorer.exe";
+                        exe_name = Some(
+                            OsString::from_wide(&entry.szExeFile[..exe_len])
+                                .to_string_lossy()
+                                .into_owned(),
+                        );
                         break;
                     }
                     if Process32NextW(snapshot, &mut entry).is_err() {
@@ -57,11 +81,7 @@ mod imp {
             }
             let _ = CloseHandle(snapshot);
 
-            if is_explorer {
-                Some(hwnd.0 as isize)
-            } else {
-                None
-            }
+            exe_name.map(|name| (hwnd, name))
         }
     }
 }
@@ -87,25 +107,8 @@ mod imp {
     /// xdotool + xprop で前面ウィンドウがファイルマネージャか判定する。
     /// ファイルマネージャならウィンドウ ID を返す（hwnd として使用）。
     pub(super) fn get_foreground_explorer_hwnd() -> Option<isize> {
-        let wid_output = Command::new("xdotool")
-            .arg("getactivewindow")
-            .output()
-            .ok()?;
-        if !wid_output.status.success() {
-            return None;
-        }
-        let wid = String::from_utf8_lossy(&wid_output.stdout)
-            .trim()
-            .to_string();
-        if wid.is_empty() {
-            return None;
-        }
-
-        let xprop_output = Command::new("xprop")
-            .args(["-id", &wid, "WM_CLASS"])
-            .output()
-            .ok()?;
-        let wm_class = String::from_utf8_lossy(&xprop_output.stdout).to_ascii_lowercase();
+        let wid = active_window_id()?;
+        let wm_class = wm_class(&wid)?.to_ascii_lowercase();
 
         if FILE_MANAGERS.iter().any(|fm| wm_class.contains(fm)) {
             wid.parse::<isize>().ok()
@@ -113,6 +116,50 @@ mod imp {
             None
         }
     }
+
+    /// 前面ウィンドウのプロセス名（/proc/PID/comm）と WM_CLASS のインスタンス名・クラス名
+    pub(super) fn get_foreground_app_names() -> Vec<String> {
+        let Some(wid) = active_window_id() else {
+            return vec![];
+        };
+        let mut names = Vec::new();
+        if let Some(comm) = xdotool(&["getwindowpid", &wid])
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+        {
+            names.push(comm.trim().to_string());
+        }
+        if let Some(wm_class) = wm_class(&wid) {
+            names.extend(super::parse_wm_class(&wm_class));
+        }
+        names
+    }
+
+    /// xdotool を実行し、成功すれば標準出力（trim 済み・空でない）を返す
+    fn xdotool(args: &[&str]) -> Option<String> {
+        let output = Command::new("xdotool").args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if stdout.is_empty() {
+            None
+        } else {
+            Some(stdout)
+        }
+    }
+
+    fn active_window_id() -> Option<String> {
+        xdotool(&["getactivewindow"])
+    }
+
+    /// xprop の WM_CLASS 出力（例: `WM_CLASS(STRING) = "Navigator", "firefox"`）
+    fn wm_class(wid: &str) -> Option<String> {
+        let output = Command::new("xprop")
+            .args(["-id", wid, "WM_CLASS"])
+            .output()
+            .ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
 }
 
 // ── Platform: macOS ──
@@ -125,4 +172,58 @@ mod imp {
     pub(super) fn get_foreground_explorer_hwnd() -> Option<isize> {
         None
     }
+
+    /// System Events から最前面のアプリ名を取得する
+    pub(super) fn get_foreground_app_names() -> Vec<String> {
+        let output = std::process::Command::new("osascript")
+            .args([
+                "-e",
+                r#"tell application "System Events" to get name of first application process whose frontmost is true"#,
+            ])
+            .output();
+        match output {
+            Ok(o) if o.status.success() => {
+                let name = String::from_utf8_lossy(&o.stdout).trim().to_string();
+                if name.is_empty() {
+                    vec![]
+                } else {
+                    vec![name]
+                }
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// ── Common helpers ──
+
+/// xprop の WM_CLASS 出力からインスタンス名とクラス名を取り出す。
+#[cfg(any(target_os = "linux", test))]
+fn parse_wm_class(output: &str) -> Vec<String> {
+    let Some((_, values)) = output.split_once('=') else {
+        return vec![];
+    };
+    values
+        .split(',')
+        .map(|v| v.trim().trim_matches('"').to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wm_class_instance_and_class() {
+        let output = "WM_CLASS(STRING) = \"Navigator\", \"firefox\"\n";
+        assert_eq!(parse_wm_class(output), vec!["Navigator", "firefox"]);
+    }
+
+    #[test]
+    fn parse_wm_class_not_found() {
+        assert!(parse_wm_class("WM_CLASS:  not found.\n").is_empty());
+    }
 }
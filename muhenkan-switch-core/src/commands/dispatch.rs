@@ -1,15 +1,42 @@
 use anyhow::Result;
 
-use crate::config::{Config, DispatchAction};
+use crate::config::{Config, DispatchAction, Modifier};
 
-pub fn run(key: &str, config: &Config) -> Result<()> {
+pub fn run(key: &str, modifier: Modifier, config: &Config) -> Result<()> {
+    // アプリ別の上書きがあるときだけ前面アプリを調べる
+    let foreground = if config.overrides.is_empty() {
+        Vec::new()
+    } else {
+        super::context::get_foreground_app_names()
+    };
     let action = config
-        .dispatch_lookup(key)
-        .ok_or_else(|| anyhow::anyhow!("キー '{}' に割り当てられたアクションがありません。無変換+F1 で開く GUI の設定画面でキーを割り当ててください", key))?;
+        .dispatch_lookup_for(key, modifier, &foreground)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "キー '{}' に割り当てられたアクションがありません。{}+F1 で開く GUI の設定画面でキーを割り当ててください",
+                modifier.describe(key),
+                config.trigger_key().label()
+            )
+        })?;
+    run_action(action, config)
+}
+
+/// トリガーキーのダブルタップに割り当てたエントリを実行する（trigger.double_tap）。
+pub fn run_double_tap(config: &Config) -> Result<()> {
+    let action = config.double_tap_action().ok_or_else(|| {
+        anyhow::anyhow!(
+            "trigger.double_tap に実行できるエントリがありません。config.toml の [trigger] を確認してください"
+        )
+    })?;
+    run_action(action, config)
+}
 
+fn run_action(action: DispatchAction, config: &Config) -> Result<()> {
     match action {
         DispatchAction::Search { engine } => super::search::run(&engine, config),
         DispatchAction::OpenFolder { target } => super::open_folder::run(&target, config),
         DispatchAction::SwitchApp { target } => super::switch_app::run(&target, config),
+        DispatchAction::RunCommand { target } => super::run_command::run(&target, config),
+        DispatchAction::Snippet { target } => super::snippet::run(&target, config),
     }
 }
@@ -8,6 +8,14 @@ pub fn simulate_type(text: &str) -> Result<()> {
     imp::simulate_type(text)
 }
 
+/// カーソルを左に count 文字分移動する（スニペットのカーソル位置指定用）。
+pub fn move_cursor_left(count: usize) -> Result<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    imp::move_cursor_left(count)
+}
+
 /// クリップボードの内容をプレーンテキストとして貼り付ける。
 /// リッチテキストの書式を除去して貼り付けたい場合に使用。
 pub fn plain_paste() -> Result<()> {
@@ -29,7 +37,7 @@ mod imp {
     use std::mem;
     use windows::Win32::UI::Input::KeyboardAndMouse::{
         SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
-        VIRTUAL_KEY, VK_C, VK_CONTROL, VK_V,
+        VIRTUAL_KEY, VK_C, VK_CONTROL, VK_LEFT, VK_V,
     };
 
     pub(super) fn simulate_copy() -> Result<()> {
@@ -57,6 +65,8 @@ mod imp {
 
     pub(super) fn simulate_type(text: &str) -> Result<()> {
         let mut inputs: Vec<INPUT> = Vec::new();
+        // 改行は Unicode 入力の LF だと無視されるアプリが多いため CR として送る
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
         for c in text.encode_utf16() {
             let mut down = INPUT::default();
             down.r#type = INPUT_KEYBOARD;
@@ -88,6 +98,38 @@ mod imp {
         Ok(())
     }
 
+    pub(super) fn move_cursor_left(count: usize) -> Result<()> {
+        let mut inputs: Vec<INPUT> = Vec::new();
+        for _ in 0..count {
+            let mut down = INPUT::default();
+            down.r#type = INPUT_KEYBOARD;
+            down.Anonymous.ki = KEYBDINPUT {
+                wVk: VK_LEFT,
+                ..Default::default()
+            };
+            let mut up = INPUT::default();
+            up.r#type = INPUT_KEYBOARD;
+            up.Anonymous.ki = KEYBDINPUT {
+                wVk: VK_LEFT,
+                dwFlags: KEYEVENTF_KEYUP,
+                ..Default::default()
+            };
+            inputs.push(down);
+            inputs.push(up);
+        }
+        unsafe {
+            let sent = SendInput(&inputs, mem::size_of::<INPUT>() as i32);
+            if sent != inputs.len() as u32 {
+                anyhow::bail!(
+                    "SendInput に失敗しました: {} 件中 {} 件のみ送信されました",
+                    inputs.len(),
+                    sent
+                );
+            }
+        }
+        Ok(())
+    }
+
     /// Send Ctrl+<key> via Win32 SendInput.
     fn send_ctrl_key(vk: VIRTUAL_KEY) -> Result<()> {
         unsafe {
@@ -189,6 +231,17 @@ mod imp {
         Ok(())
     }
 
+    pub(super) fn move_cursor_left(count: usize) -> Result<()> {
+        if super::super::is_wayland() {
+            anyhow::bail!(
+                "Wayland ではカーソル移動は未対応です。\n\
+                 X11 セッションに切り替えてください。"
+            );
+        }
+        let count = count.to_string();
+        run_xdotool(&["key", "--clearmodifiers", "--repeat", &count, "Left"])
+    }
+
     pub(super) fn get_selected_text() -> Result<String> {
         if super::super::is_wayland() {
             // PRIMARY セレクションから選択テキストを直接読み取り
@@ -258,13 +311,40 @@ mod imp {
     }
 
     pub(super) fn simulate_type(text: &str) -> Result<()> {
+        // keystroke は IME の影響を受け、本文を AppleScript に埋め込むことにもなるため、
+        // クリップボード経由で貼り付ける
+        let mut clipboard = arboard::Clipboard::new()?;
+        let saved = clipboard.get_text().ok();
+        clipboard.set_text(text)?;
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        Command::new("osascript")
+            .args([
+                "-e",
+                r#"tell application "System Events" to keystroke "v" using command down"#,
+            ])
+            .output()?;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        if let Some(prev) = saved {
+            let _ = clipboard.set_text(prev);
+        }
+        Ok(())
+    }
+
+    pub(super) fn move_cursor_left(count: usize) -> Result<()> {
+        // key code 123 = ←
+        let repeat = format!("repeat {} times", count);
         Command::new("osascript")
             .args([
                 "-e",
-                &format!(
-                    r#"tell application "System Events" to keystroke "{}""#,
-                    text
-                ),
+                r#"tell application "System Events""#,
+                "-e",
+                &repeat,
+                "-e",
+                "key code 123",
+                "-e",
+                "end repeat",
+                "-e",
+                "end tell",
             ])
             .output()?;
         Ok(())
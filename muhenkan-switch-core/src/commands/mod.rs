@@ -1,9 +1,14 @@
+pub mod check;
 pub mod context;
 pub mod dispatch;
 pub mod keys;
 pub mod open_folder;
 pub mod open_gui;
+pub mod profile;
+pub mod run_command;
 pub mod search;
+pub mod show_config;
+pub mod snippet;
 pub mod switch_app;
 pub mod timestamp;
 pub mod timestamp_settings;
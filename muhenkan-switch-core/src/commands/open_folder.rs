@@ -1,17 +1,16 @@
 use anyhow::Result;
-use std::path::PathBuf;
 
 use crate::config::{self, Config};
 
 pub fn run(target: &str, config: &Config) -> Result<()> {
-    let path_str = config::get_folder_path(&config.folders, target)?;
+    let path_str = config::get_folder_path(config.active_folders(), target)?;
 
     if path_str.is_empty() {
         anyhow::bail!("フォルダ '{}' のパスが config.toml に設定されていません", target);
     }
 
     // ~ をホームディレクトリに展開
-    let path = expand_home(path_str);
+    let path = config::expand_home(path_str);
 
     if !path.exists() {
         anyhow::bail!("フォルダが見つかりません: {}", path.display());
@@ -21,70 +20,32 @@ pub fn run(target: &str, config: &Config) -> Result<()> {
     Ok(())
 }
 
-/// "~" または "~/" で始まるパスをホームディレクトリに展開する
-fn expand_home(path_str: &str) -> PathBuf {
-    if let Some(rest) = path_str.strip_prefix("~/") {
-        if let Some(home) = dirs::home_dir() {
-            return home.join(rest);
-        }
-    } else if path_str == "~" {
-        if let Some(home) = dirs::home_dir() {
-            return home;
-        }
-    }
-    PathBuf::from(path_str)
-}
-
 // ── Tests ──
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // ── expand_home ──
-
-    #[test]
-    fn expand_home_with_tilde_prefix() {
-        let result = expand_home("~/Documents");
-        let home = dirs::home_dir().unwrap();
-        assert_eq!(result, home.join("Documents"));
-    }
-
-    #[test]
-    fn expand_home_tilde_only() {
-        let result = expand_home("~");
-        let home = dirs::home_dir().unwrap();
-        assert_eq!(result, home);
-    }
-
-    #[test]
-    fn expand_home_absolute_path_unchanged() {
-        let result = expand_home("/tmp/test");
-        assert_eq!(result, PathBuf::from("/tmp/test"));
-    }
-
-    #[test]
-    fn expand_home_relative_path_unchanged() {
-        let result = expand_home("relative/path");
-        assert_eq!(result, PathBuf::from("relative/path"));
-    }
-
-    #[test]
-    fn expand_home_tilde_in_middle_unchanged() {
-        // "foo/~/bar" のような場合は展開しない
-        let result = expand_home("foo/~/bar");
-        assert_eq!(result, PathBuf::from("foo/~/bar"));
-    }
-
     // ── run (統合テスト) ──
 
     #[test]
     fn run_missing_folder_errors() {
         let config = Config {
-            punctuation_style: "、。".to_string(),
+            config_version: crate::config::CONFIG_VERSION,
+            punctuation_style: Default::default(),
+            symbols: Default::default(),
+            trigger: Default::default(),
+            layout: Default::default(),
+            editing: Default::default(),
+            extra_layers: Default::default(),
             search: Default::default(),
             folders: Default::default(),
             apps: Default::default(),
+            commands: Default::default(),
+            snippets: Default::default(),
+            overrides: Default::default(),
+            active_profile: None,
+            profiles: Default::default(),
             timestamp: Default::default(),
         };
         let result = run("nonexistent", &config);
@@ -100,15 +61,27 @@ mod tests {
         folders.insert(
             "test".to_string(),
             FolderEntry {
-                key: Some("9".to_string()),
+                key: Some("9".parse().unwrap()),
+                shift_key: None,
                 path: "/tmp/__muhenkan_test_nonexistent_dir_12345__".to_string(),
             },
         );
         let config = Config {
-            punctuation_style: "、。".to_string(),
+            config_version: crate::config::CONFIG_VERSION,
+            punctuation_style: Default::default(),
+            symbols: Default::default(),
+            trigger: Default::default(),
+            layout: Default::default(),
+            editing: Default::default(),
+            extra_layers: Default::default(),
             search: Default::default(),
             folders,
             apps: Default::default(),
+            commands: Default::default(),
+            snippets: Default::default(),
+            overrides: Default::default(),
+            active_profile: None,
+            profiles: Default::default(),
             timestamp: Default::default(),
         };
         let result = run("test", &config);
@@ -124,15 +97,27 @@ mod tests {
         folders.insert(
             "unknown".to_string(),
             FolderEntry {
-                key: Some("9".to_string()),
+                key: Some("9".parse().unwrap()),
+                shift_key: None,
                 path: "".to_string(),
             },
         );
         let config = Config {
-            punctuation_style: "、。".to_string(),
+            config_version: crate::config::CONFIG_VERSION,
+            punctuation_style: Default::default(),
+            symbols: Default::default(),
+            trigger: Default::default(),
+            layout: Default::default(),
+            editing: Default::default(),
+            extra_layers: Default::default(),
             search: Default::default(),
             folders,
             apps: Default::default(),
+            commands: Default::default(),
+            snippets: Default::default(),
+            overrides: Default::default(),
+            active_profile: None,
+            profiles: Default::default(),
             timestamp: Default::default(),
         };
         let result = run("unknown", &config);
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use std::path::Path;
+
+use super::toast::Toast;
+use crate::config;
+
+/// 選択中のプロファイルを切り替えて config.toml に保存する。
+/// name を省略すると 標準 → 各プロファイル（定義順）→ 標準 の順に切り替える。
+/// 割当キーは全プロファイル分が kbd に含まれるため、kanata の再起動は不要。
+pub fn switch(name: Option<&str>, config_file: Option<&Path>) -> Result<()> {
+    let config = config::update_with(config_file, |config| {
+        if config.profiles.is_empty() {
+            anyhow::bail!("config.toml に profiles が定義されていません");
+        }
+
+        config.active_profile = match name {
+            // 標準 に戻す
+            Some(n) if n == config::DEFAULT_PROFILE_LABEL => None,
+            Some(n) => {
+                if !config.profiles.contains_key(n) {
+                    anyhow::bail!("プロファイル '{}' が config.toml に定義されていません", n);
+                }
+                Some(n.to_string())
+            }
+            None => config.next_profile(),
+        };
+        Ok(())
+    })?;
+
+    Toast::notify(&format!("プロファイル: {}", config.active_profile_label()));
+
+    Ok(())
+}
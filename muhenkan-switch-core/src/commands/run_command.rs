@@ -0,0 +1,248 @@
+//! [commands] セクションに登録された任意コマンドの実行。
+//!
+//! コマンド文字列中のプレースホルダを展開してシェル経由で実行する。
+//! 展開した値はシェル用にクォートされるため、プレースホルダを引用符で囲む必要はない。
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::process::{Command, Output};
+
+use super::toast::Toast;
+use crate::config::{self, Config};
+
+const CLIPBOARD: &str = "{clipboard}";
+const SELECTION: &str = "{selection}";
+const SELECTED_FILES: &str = "{selected_files}";
+const TIMESTAMP: &str = "{timestamp}";
+
+/// 対応するプレースホルダ（値の取得順）。
+/// 選択テキストの取得はクリップボードを上書きするため {clipboard} を先に読む。
+const PLACEHOLDERS: &[&str] = &[CLIPBOARD, SELECTION, SELECTED_FILES, TIMESTAMP];
+
+pub fn run(target: &str, config: &Config) -> Result<()> {
+    let entry = config::get_command(&config.commands, target)?;
+    let command = expand(entry.command(), |name| resolve(name, config))?;
+
+    if entry.show_output {
+        let output = shell(&command)
+            .output()
+            .with_context(|| format!("コマンド '{}' の実行に失敗しました", target))?;
+        Toast::notify(&format_output(target, &output));
+    } else {
+        shell(&command)
+            .spawn()
+            .with_context(|| format!("コマンド '{}' の実行に失敗しました", target))?;
+    }
+    Ok(())
+}
+
+/// プレースホルダの値を取得する。{selected_files} は選択ファイルごとに1要素。
+fn resolve(name: &str, config: &Config) -> Result<Vec<String>> {
+    let values = match name {
+        CLIPBOARD => {
+            let mut clipboard = arboard::Clipboard::new()?;
+            vec![clipboard.get_text().unwrap_or_default()]
+        }
+        SELECTION => vec![super::keys::get_selected_text()?.trim().to_string()],
+        SELECTED_FILES => match super::context::get_foreground_explorer_hwnd() {
+            Some(hwnd) => super::timestamp::get_selected_paths(hwnd)?
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect(),
+            None => vec![],
+        },
+        TIMESTAMP => vec![Local::now().format(&config.timestamp.format).to_string()],
+        _ => unreachable!("未対応のプレースホルダ: {}", name),
+    };
+    Ok(values)
+}
+
+/// テンプレート中のプレースホルダを展開する。
+/// 使われているプレースホルダの値だけを取得し、展開結果は再走査しない。
+fn expand<F>(template: &str, mut resolve: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<Vec<String>>,
+{
+    let mut values: Vec<(&str, String)> = Vec::new();
+    for &name in PLACEHOLDERS {
+        if template.contains(name) {
+            let args: Vec<String> = resolve(name)?.iter().map(|v| quote(v)).collect();
+            values.push((name, args.join(" ")));
+        }
+    }
+
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find('{') {
+        expanded.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        match values.iter().find(|(name, _)| tail.starts_with(name)) {
+            Some((name, value)) => {
+                expanded.push_str(value);
+                rest = &tail[name.len()..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// トーストに表示するメッセージを組み立てる。
+fn format_output(target: &str, output: &Output) -> String {
+    if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = stdout.trim();
+        if stdout.is_empty() {
+            format!("\u{2713} {}", target)
+        } else {
+            stdout.to_string()
+        }
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim() {
+            "" => format!("\u{2717} {} ({})", target, output.status),
+            msg => format!("\u{2717} {}: {}", target, msg),
+        }
+    }
+}
+
+// ── Platform: Windows ──
+
+/// cmd.exe 用のクォート（" は "" にエスケープ）
+#[cfg(target_os = "windows")]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// コンソールウィンドウを出さずに cmd.exe 経由で実行する。
+#[cfg(target_os = "windows")]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").raw_arg(command).creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+// ── Platform: Linux / macOS ──
+
+/// POSIX sh 用のシングルクォート
+#[cfg(not(target_os = "windows"))]
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake(name: &str) -> Result<Vec<String>> {
+        Ok(match name {
+            CLIPBOARD => vec!["clip".to_string()],
+            SELECTION => vec!["hello world".to_string()],
+            SELECTED_FILES => vec!["/tmp/a.txt".to_string(), "/tmp/b c.txt".to_string()],
+            TIMESTAMP => vec!["20260216".to_string()],
+            _ => unreachable!(),
+        })
+    }
+
+    #[test]
+    fn expand_without_placeholders() {
+        let result = expand("echo hi", |_| panic!("呼ばれないはず")).unwrap();
+        assert_eq!(result, "echo hi");
+    }
+
+    #[test]
+    fn expand_resolves_only_used_placeholders() {
+        let mut called = Vec::new();
+        expand("echo {timestamp}", |name| {
+            called.push(name.to_string());
+            fake(name)
+        })
+        .unwrap();
+        assert_eq!(called, vec![TIMESTAMP]);
+    }
+
+    #[test]
+    fn expand_keeps_unknown_braces() {
+        let result = expand("awk '{print $1}' {unknown}", fake).unwrap();
+        assert_eq!(result, "awk '{print $1}' {unknown}");
+    }
+
+    #[test]
+    fn expand_does_not_rescan_values() {
+        let result = expand("echo {selection}", |_| Ok(vec!["{timestamp}".to_string()])).unwrap();
+        assert_eq!(result, format!("echo {}", quote("{timestamp}")));
+    }
+
+    #[test]
+    fn expand_selected_files_as_separate_args() {
+        let result = expand("ls {selected_files}", fake).unwrap();
+        assert_eq!(
+            result,
+            format!("ls {} {}", quote("/tmp/a.txt"), quote("/tmp/b c.txt"))
+        );
+    }
+
+    #[test]
+    fn expand_empty_selected_files() {
+        let result = expand("ls {selected_files}", |_| Ok(vec![])).unwrap();
+        assert_eq!(result, "ls ");
+    }
+
+    #[test]
+    fn expand_propagates_resolve_error() {
+        let result = expand("echo {selection}", |_| anyhow::bail!("取得失敗"));
+        assert!(result.is_err());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn quote_escapes_single_quote() {
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn shell_runs_expanded_command() {
+        let command = expand("printf %s {selection}", fake).unwrap();
+        let output = shell(&command).output().unwrap();
+        assert_eq!(format_output("echo", &output), "hello world");
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn format_output_reports_failure() {
+        let output = shell("echo oops >&2; exit 1").output().unwrap();
+        assert_eq!(format_output("fail", &output), "\u{2717} fail: oops");
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn format_output_empty_stdout() {
+        let output = shell("true").output().unwrap();
+        assert_eq!(format_output("noop", &output), "\u{2713} noop");
+    }
+
+    #[test]
+    fn run_missing_command_errors() {
+        let mut config = crate::config::default_config();
+        config.commands.clear();
+        let result = run("nonexistent", &config);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("定義されていません"));
+    }
+}
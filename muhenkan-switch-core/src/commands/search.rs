@@ -4,7 +4,7 @@ use crate::config::{self, Config};
 
 pub fn run(engine: &str, config: &Config) -> Result<()> {
     // 検索エンジンのURLテンプレートを取得
-    let url_template = config::get_search_url(&config.search, engine)?;
+    let url_template = config::get_search_url(config.active_search(), engine)?;
 
     // 選択テキストを取得
     let query = super::keys::get_selected_text()?;
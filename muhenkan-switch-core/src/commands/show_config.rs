@@ -0,0 +1,68 @@
+//! 設定レイヤーと各値の由来の表示。
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::layers::{self, ConfigLayer, LayerKind, LayeredConfig};
+
+/// config_file を指定した場合はそのファイルだけを1つのレイヤーとして表示する。
+pub fn run(config_file: Option<&Path>) -> Result<()> {
+    let layered = match config_file {
+        Some(path) => layers::merge_layers(vec![ConfigLayer {
+            kind: LayerKind::Explicit,
+            path: Some(path.to_path_buf()),
+        }])?,
+        None => layers::load_layered()?,
+    };
+    print!("{}", format(&layered));
+    Ok(())
+}
+
+/// レイヤー一覧（優先度の高い順）と、値ごとの由来レイヤー番号を整形する。
+fn format(layered: &LayeredConfig) -> String {
+    let mut out = String::from("設定レイヤー（優先度の高い順）:\n");
+    for (i, layer) in layered.layers.iter().enumerate() {
+        out.push_str(&format!("  [{}] {}\n", i + 1, layer));
+    }
+    out.push_str("\n値の由来:\n");
+    for (key, &index) in &layered.origins {
+        out.push_str(&format!("  [{}] {}\n", index + 1, key));
+    }
+    out
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn format_lists_layers_and_origins() {
+        let layered = LayeredConfig {
+            config: crate::config::default_config(),
+            layers: vec![
+                ConfigLayer {
+                    kind: LayerKind::User,
+                    path: Some(PathBuf::from("/home/u/.config/muhenkan-switch/config.toml")),
+                },
+                ConfigLayer {
+                    kind: LayerKind::ExeDir,
+                    path: Some(PathBuf::from("/opt/muhenkan-switch/config.toml")),
+                },
+            ],
+            origins: [
+                ("search.Google".to_string(), 0),
+                ("timestamp.format".to_string(), 1),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let text = format(&layered);
+        assert!(text.contains("[1] ユーザー設定 (/home/u/.config/muhenkan-switch/config.toml)"));
+        assert!(text.contains("[2] 実行ファイルのディレクトリ (/opt/muhenkan-switch/config.toml)"));
+        assert!(text.contains("  [1] search.Google\n"));
+        assert!(text.contains("  [2] timestamp.format\n"));
+    }
+}
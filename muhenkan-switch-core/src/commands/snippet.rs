@@ -0,0 +1,215 @@
+//! [snippets] セクションに登録された定型文の入力。
+//!
+//! 変数を展開した本文を `keys::simulate_type` で入力し、
+//! `{cursor}` があればその位置までカーソルを戻す。
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::fmt::Write;
+
+use crate::config::{self, Config};
+
+const CLIPBOARD: &str = "{clipboard}";
+const SELECTION: &str = "{selection}";
+const CURSOR: &str = "{cursor}";
+const DATE: &str = "{date}";
+const DATE_WITH_FORMAT: &str = "{date:";
+
+/// 外部から値を取得する変数（値の取得順）。
+/// 選択テキストの取得はクリップボードを上書きするため {clipboard} を先に読む。
+const VARIABLES: &[&str] = &[CLIPBOARD, SELECTION];
+
+/// 展開済みのスニペット。
+#[derive(Debug, PartialEq)]
+struct Snippet {
+    text: String,
+    /// 入力後にカーソルを左へ戻す文字数
+    cursor_back: usize,
+}
+
+pub fn run(target: &str, config: &Config) -> Result<()> {
+    let template = config::get_snippet_text(&config.snippets, target)?;
+    let snippet = expand(template, &Local::now(), &config.timestamp.format, resolve)?;
+    super::keys::simulate_type(&snippet.text)?;
+    super::keys::move_cursor_left(snippet.cursor_back)
+}
+
+fn resolve(name: &str) -> Result<String> {
+    match name {
+        CLIPBOARD => {
+            let mut clipboard = arboard::Clipboard::new()?;
+            Ok(clipboard.get_text().unwrap_or_default())
+        }
+        SELECTION => super::keys::get_selected_text(),
+        _ => unreachable!("未対応の変数: {}", name),
+    }
+}
+
+/// テンプレート中の変数を展開する。
+/// {date} は [timestamp] の format、{date:FMT} は FMT で現在日時を書式化する。
+/// {cursor} は最初の1つだけを有効とし、展開結果は再走査しない。
+fn expand<F>(
+    template: &str,
+    now: &DateTime<Local>,
+    default_format: &str,
+    mut resolve: F,
+) -> Result<Snippet>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut values: Vec<(&str, String)> = Vec::new();
+    for &name in VARIABLES {
+        if template.contains(name) {
+            values.push((name, resolve(name)?));
+        }
+    }
+
+    let mut text = String::with_capacity(template.len());
+    let mut cursor: Option<usize> = None;
+    let mut rest = template;
+    while let Some(pos) = rest.find('{') {
+        text.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        if let Some(after) = tail.strip_prefix(CURSOR) {
+            cursor.get_or_insert(text.len());
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix(DATE) {
+            format_date(&mut text, now, default_format)?;
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix(DATE_WITH_FORMAT) {
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("スニペットの {{date:...}} が閉じられていません"))?;
+            format_date(&mut text, now, &after[..end])?;
+            rest = &after[end + 1..];
+        } else if let Some((name, value)) = values.iter().find(|(name, _)| tail.starts_with(name)) {
+            text.push_str(value);
+            rest = &tail[name.len()..];
+        } else {
+            text.push('{');
+            rest = &tail[1..];
+        }
+    }
+    text.push_str(rest);
+
+    // CRLF はカーソル移動では1文字として数える
+    let cursor_back = cursor
+        .map(|idx| text[idx..].replace("\r\n", "\n").chars().count())
+        .unwrap_or(0);
+    Ok(Snippet { text, cursor_back })
+}
+
+/// chrono の書式で日時を追記する。不正な書式はパニックせずエラーにする。
+fn format_date(out: &mut String, now: &DateTime<Local>, format: &str) -> Result<()> {
+    write!(out, "{}", now.format(format))
+        .map_err(|_| anyhow::anyhow!("スニペットの日付フォーマット '{}' が不正です", format))
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 2, 16, 14, 25, 30).unwrap()
+    }
+
+    fn fake(name: &str) -> Result<String> {
+        Ok(match name {
+            CLIPBOARD => "クリップ".to_string(),
+            SELECTION => "選択".to_string(),
+            _ => unreachable!(),
+        })
+    }
+
+    fn expand_text(template: &str) -> Snippet {
+        expand(template, &now(), "%Y%m%d", fake).unwrap()
+    }
+
+    #[test]
+    fn expand_plain_text() {
+        let snippet = expand("お世話になっております。", &now(), "%Y%m%d", |_| {
+            panic!("呼ばれないはず")
+        })
+        .unwrap();
+        assert_eq!(snippet.text, "お世話になっております。");
+        assert_eq!(snippet.cursor_back, 0);
+    }
+
+    #[test]
+    fn expand_date_with_format() {
+        assert_eq!(expand_text("日付: {date:%Y-%m-%d}").text, "日付: 2026-02-16");
+    }
+
+    #[test]
+    fn expand_date_uses_timestamp_format() {
+        assert_eq!(expand_text("{date}_memo").text, "20260216_memo");
+    }
+
+    #[test]
+    fn expand_clipboard_and_selection() {
+        let mut called = Vec::new();
+        let snippet = expand("「{selection}」{clipboard}", &now(), "%Y%m%d", |name| {
+            called.push(name.to_string());
+            fake(name)
+        })
+        .unwrap();
+        assert_eq!(snippet.text, "「選択」クリップ");
+        assert_eq!(called, vec![CLIPBOARD, SELECTION]);
+    }
+
+    #[test]
+    fn expand_cursor_position() {
+        let snippet = expand_text("「{cursor}」");
+        assert_eq!(snippet.text, "「」");
+        assert_eq!(snippet.cursor_back, 1);
+    }
+
+    #[test]
+    fn expand_cursor_counts_multiline() {
+        let snippet = expand_text("件名: {cursor}\r\nよろしくお願いします。");
+        assert_eq!(snippet.text, "件名: \r\nよろしくお願いします。");
+        assert_eq!(snippet.cursor_back, 12);
+    }
+
+    #[test]
+    fn expand_only_first_cursor() {
+        let snippet = expand_text("a{cursor}b{cursor}c");
+        assert_eq!(snippet.text, "abc");
+        assert_eq!(snippet.cursor_back, 2);
+    }
+
+    #[test]
+    fn expand_keeps_unknown_braces() {
+        assert_eq!(expand_text("{name} さん").text, "{name} さん");
+    }
+
+    #[test]
+    fn expand_does_not_rescan_values() {
+        let snippet = expand("{clipboard}", &now(), "%Y%m%d", |_| Ok("{cursor}".to_string())).unwrap();
+        assert_eq!(snippet.text, "{cursor}");
+        assert_eq!(snippet.cursor_back, 0);
+    }
+
+    #[test]
+    fn expand_unclosed_date_errors() {
+        assert!(expand("{date:%Y", &now(), "%Y%m%d", fake).is_err());
+    }
+
+    #[test]
+    fn expand_invalid_date_format_errors() {
+        let result = expand("{date:%Q}", &now(), "%Y%m%d", fake);
+        assert!(result.unwrap_err().to_string().contains("%Q"));
+    }
+
+    #[test]
+    fn run_missing_snippet_errors() {
+        let mut config = crate::config::default_config();
+        config.snippets.clear();
+        let result = run("nonexistent", &config);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("定義されていません"));
+    }
+}
@@ -7,7 +7,7 @@ use crate::config::Config;
 
 pub fn run(target: &str, config: &Config) -> Result<()> {
     let entry = config
-        .apps
+        .active_apps()
         .get(target)
         .ok_or_else(|| anyhow::anyhow!("アプリ '{}' が config.toml に定義されていません", target))?;
 
@@ -257,6 +257,7 @@ mod imp {
     /// 2. xdotool search --class (WM_CLASS でマッチ)
     /// 3. xdotool search --name (ウィンドウタイトルでマッチ)
     /// 4. pgrep + xdotool search --pid (バイナリ名から PID 経由でマッチ)
+    ///
     /// NOTE: Wayland でのアプリ切り替えは標準 API が未整備のため非対応 (#105)
     pub(super) fn activate_window(app: &str, launch: Option<&str>) -> Result<()> {
         let activated = try_wmctrl(app)
@@ -436,10 +437,21 @@ mod tests {
     #[test]
     fn run_missing_app_errors() {
         let config = Config {
-            punctuation_style: "、。".to_string(),
+            config_version: crate::config::CONFIG_VERSION,
+            punctuation_style: Default::default(),
+            symbols: Default::default(),
+            trigger: Default::default(),
+            layout: Default::default(),
+            editing: Default::default(),
+            extra_layers: Default::default(),
             search: Default::default(),
             folders: Default::default(),
             apps: Default::default(),
+            commands: Default::default(),
+            snippets: Default::default(),
+            overrides: Default::default(),
+            active_profile: None,
+            profiles: Default::default(),
             timestamp: Default::default(),
         };
         let result = run("nonexistent", &config);
@@ -3,7 +3,7 @@ use chrono::Local;
 use std::path::{Path, PathBuf};
 
 use super::toast::Toast;
-use crate::config::Config;
+use crate::config::{Config, TimestampPosition};
 
 pub fn run(action: &str, config: &Config) -> Result<()> {
     let explorer_hwnd = super::context::get_foreground_explorer_hwnd();
@@ -18,7 +18,7 @@ pub fn run(action: &str, config: &Config) -> Result<()> {
             let toast = Toast::show("処理中...");
             let result = explorer_rename_prepend(
                 &config.timestamp.format,
-                &config.timestamp.position,
+                config.timestamp.position,
                 delimiter,
                 hwnd,
             );
@@ -36,7 +36,7 @@ pub fn run(action: &str, config: &Config) -> Result<()> {
             let toast = Toast::show("処理中...");
             let result = explorer_duplicate(
                 &config.timestamp.format,
-                &config.timestamp.position,
+                config.timestamp.position,
                 delimiter,
                 hwnd,
             );
@@ -49,7 +49,7 @@ pub fn run(action: &str, config: &Config) -> Result<()> {
             let toast = Toast::show("処理中...");
             let result = explorer_rename_remove(
                 &config.timestamp.format,
-                &config.timestamp.position,
+                config.timestamp.position,
                 delimiter,
                 hwnd,
             );
@@ -84,6 +84,11 @@ fn format_toast_result(result: &Result<Vec<PathBuf>>) -> String {
 
 // ── Explorer コンテキスト ──
 
+/// ファイルマネージャで選択中のファイルパスを取得する
+pub fn get_selected_paths(hwnd: isize) -> Result<Vec<PathBuf>> {
+    imp::get_selected_paths(hwnd)
+}
+
 /// ファイルの更新日時からタイムスタンプ文字列を生成
 fn file_modified_timestamp(path: &Path, format: &str) -> Result<String> {
     let modified = path.metadata()?.modified()?;
@@ -94,7 +99,7 @@ fn file_modified_timestamp(path: &Path, format: &str) -> Result<String> {
 /// V: ファイル名にタイムスタンプを付加してリネーム（ファイル更新日時を使用）
 fn explorer_rename_prepend(
     format: &str,
-    position: &str,
+    position: TimestampPosition,
     delimiter: &str,
     hwnd: isize,
 ) -> Result<Vec<PathBuf>> {
@@ -112,7 +117,7 @@ fn explorer_rename_prepend(
 /// C: タイムスタンプ付きファイル名で複製（ファイル更新日時を使用）
 fn explorer_duplicate(
     format: &str,
-    position: &str,
+    position: TimestampPosition,
     delimiter: &str,
     hwnd: isize,
 ) -> Result<Vec<PathBuf>> {
@@ -130,7 +135,7 @@ fn explorer_duplicate(
 /// X: ファイル名からファイル更新日時のタイムスタンプを除去してリネーム
 fn explorer_rename_remove(
     format: &str,
-    position: &str,
+    position: TimestampPosition,
     delimiter: &str,
     hwnd: isize,
 ) -> Result<Vec<PathBuf>> {
@@ -150,7 +155,7 @@ fn explorer_rename_remove(
 fn build_timestamped_path(
     src: &Path,
     timestamp: &str,
-    position: &str,
+    position: TimestampPosition,
     delimiter: &str,
 ) -> PathBuf {
     let stem = src.file_stem().unwrap_or_default().to_string_lossy();
@@ -159,7 +164,7 @@ fn build_timestamped_path(
         .map(|e| format!(".{}", e.to_string_lossy()))
         .unwrap_or_default();
 
-    let new_name = if position == "after" {
+    let new_name = if position == TimestampPosition::After {
         format!("{}{}{}{}", stem, delimiter, timestamp, ext)
     } else {
         format!("{}{}{}{}", timestamp, delimiter, stem, ext)
@@ -172,7 +177,7 @@ fn build_timestamped_path(
 fn build_removed_timestamp_path(
     src: &Path,
     timestamp: &str,
-    position: &str,
+    position: TimestampPosition,
     delimiter: &str,
 ) -> Option<PathBuf> {
     let stem = src.file_stem()?.to_string_lossy();
@@ -181,7 +186,7 @@ fn build_removed_timestamp_path(
         .map(|e| format!(".{}", e.to_string_lossy()))
         .unwrap_or_default();
 
-    let new_stem = if position == "after" {
+    let new_stem = if position == TimestampPosition::After {
         let suffix = format!("{}{}", delimiter, timestamp);
         stem.strip_suffix(&*suffix)?.to_string()
     } else {
@@ -401,35 +406,35 @@ mod tests {
     #[test]
     fn build_timestamped_path_prefix() {
         let src = Path::new("/tmp/report.pdf");
-        let result = build_timestamped_path(src, "20240101", "before", "_");
+        let result = build_timestamped_path(src, "20240101", TimestampPosition::Before, "_");
         assert_eq!(result, PathBuf::from("/tmp/20240101_report.pdf"));
     }
 
     #[test]
     fn build_timestamped_path_suffix() {
         let src = Path::new("/tmp/report.pdf");
-        let result = build_timestamped_path(src, "20240101", "after", "_");
+        let result = build_timestamped_path(src, "20240101", TimestampPosition::After, "_");
         assert_eq!(result, PathBuf::from("/tmp/report_20240101.pdf"));
     }
 
     #[test]
     fn build_removed_timestamp_path_prefix() {
         let src = Path::new("/tmp/20240101_report.pdf");
-        let result = build_removed_timestamp_path(src, "20240101", "before", "_");
+        let result = build_removed_timestamp_path(src, "20240101", TimestampPosition::Before, "_");
         assert_eq!(result, Some(PathBuf::from("/tmp/report.pdf")));
     }
 
     #[test]
     fn build_removed_timestamp_path_suffix() {
         let src = Path::new("/tmp/report_20240101.pdf");
-        let result = build_removed_timestamp_path(src, "20240101", "after", "_");
+        let result = build_removed_timestamp_path(src, "20240101", TimestampPosition::After, "_");
         assert_eq!(result, Some(PathBuf::from("/tmp/report.pdf")));
     }
 
     #[test]
     fn build_removed_timestamp_path_no_match() {
         let src = Path::new("/tmp/report.pdf");
-        let result = build_removed_timestamp_path(src, "20240101", "before", "_");
+        let result = build_removed_timestamp_path(src, "20240101", TimestampPosition::Before, "_");
         assert_eq!(result, None);
     }
 }
@@ -1,28 +1,23 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
+use std::path::Path;
 
 use super::toast::Toast;
+use crate::config;
 
 /// タイムスタンプの position (before/after) をトグルして config.toml に保存する。
 /// Toast には現在の設定でファイル名がどう変化するかの例を表示する
 /// （GUI 設定画面のプレビューと同様の形式）。
-pub fn toggle_position() -> Result<()> {
-    let path = muhenkan_switch_config::config_path()
-        .context("config.toml が見つかりません")?;
-
-    let mut config = muhenkan_switch_config::load_from(&path)?;
-
-    config.timestamp.position = match config.timestamp.position.as_str() {
-        "before" => "after".to_string(),
-        _ => "before".to_string(),
-    };
-
-    muhenkan_switch_config::save(&path, &config)?;
+pub fn toggle_position(config_file: Option<&Path>) -> Result<()> {
+    let config = config::update_with(config_file, |config| {
+        config.timestamp.position = config.timestamp.position.toggled();
+        Ok(())
+    })?;
 
     let ts = chrono::Local::now()
         .format(&config.timestamp.format)
         .to_string();
     let delimiter = &config.timestamp.delimiter;
-    let (label, example) = if config.timestamp.position == "after" {
+    let (label, example) = if config.timestamp.position == config::TimestampPosition::After {
         ("後", format!("FileName{delimiter}{ts}.txt"))
     } else {
         ("前", format!("{ts}{delimiter}FileName.txt"))
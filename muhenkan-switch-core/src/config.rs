@@ -1 +1,76 @@
 pub use muhenkan_switch_config::*;
+
+use anyhow::Result;
+use std::path::Path;
+
+/// --config で指定されたファイル、なければ自動検出した config.toml（レイヤー）を読み込む。
+pub fn load_with(explicit: Option<&Path>) -> Result<Config> {
+    match explicit {
+        Some(path) => load_from(path),
+        None => load(),
+    }
+}
+
+/// load_with と同じく読み込み、あわせて不明なキーの警告を返す。
+pub fn load_lenient_with(explicit: Option<&Path>) -> Result<(Config, Vec<Diagnostic>)> {
+    match explicit {
+        Some(path) => load_from_lenient(path),
+        None => load_lenient(),
+    }
+}
+
+/// 設定を変更して保存する。--config の指定があればそのファイル、
+/// なければ書き込み先のレイヤー（下のレイヤーと違う値だけを書く）。
+pub fn update_with(
+    explicit: Option<&Path>,
+    f: impl FnOnce(&mut Config) -> Result<()>,
+) -> Result<Config> {
+    match explicit {
+        Some(path) => update(path, f),
+        None => layers::update(f),
+    }
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_with_explicit_fixture() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../config/default-linux.toml");
+        let config = load_with(Some(&path)).unwrap();
+        assert!(config.search.contains_key("Google"));
+    }
+
+    #[test]
+    fn load_with_missing_file_errors() {
+        assert!(load_with(Some(Path::new("/nonexistent/config.toml"))).is_err());
+    }
+
+    #[test]
+    fn load_lenient_with_reports_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "config_version = 1\n[timestamp]\npostion = \"after\"\n").unwrap();
+        let (config, warnings) = load_lenient_with(Some(&path)).unwrap();
+        assert_eq!(config.timestamp.position, TimestampPosition::Before);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].file.as_deref(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn update_with_explicit_writes_that_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "config_version = 1\n[timestamp]\nposition = \"after\"\n").unwrap();
+        let config = update_with(Some(&path), |config| {
+            config.timestamp.position = config.timestamp.position.toggled();
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(config.timestamp.position, TimestampPosition::Before);
+        assert_eq!(load_with(Some(&path)).unwrap().timestamp.position, TimestampPosition::Before);
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 mod commands;
 mod config;
@@ -13,6 +14,10 @@ use commands::toast::Toast;
     version
 )]
 struct Cli {
+    /// config.toml パス（省略時は自動検出。全サブコマンド共通）
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -37,6 +42,18 @@ enum Commands {
         #[arg(long)]
         target: String,
     },
+    /// 登録済みのコマンドを実行
+    RunCommand {
+        /// コマンド名 (config.toml の [commands] セクションのキー)
+        #[arg(long)]
+        target: String,
+    },
+    /// 登録済みのスニペットを入力
+    Snippet {
+        /// スニペット名 (config.toml の [snippets] セクションのキー)
+        #[arg(long)]
+        target: String,
+    },
     /// タイムスタンプ操作
     Timestamp {
         /// アクション: paste, copy, cut
@@ -47,19 +64,49 @@ enum Commands {
     Dispatch {
         /// 割当キー (config.toml の key フィールドに対応)
         key: String,
+        /// 無変換+Shift+キー として実行 (config.toml の shift_key フィールドに対応)
+        #[arg(long)]
+        shift: bool,
     },
+    /// トリガーキーのダブルタップに割り当てたエントリを実行
+    DoubleTap,
     /// タイムスタンプの position (before/after) をトグルする
     ToggleTimestampPosition,
+    /// プロファイルを切り替える（名前省略時は次のプロファイル）
+    SwitchProfile {
+        /// 切り替え先のプロファイル名（"標準" でトップレベルの設定に戻す）
+        name: Option<String>,
+    },
     /// GUI 設定ウィンドウを前面に出す（未起動なら起動する）
     OpenGui,
+    /// 読み込んだ設定ファイル（レイヤー）と各値の由来を表示
+    ShowConfig,
+    /// config.toml を検証する（エラーがあれば終了コード 1）
+    Check {
+        /// 診断を JSON で出力
+        #[arg(long)]
+        json: bool,
+    },
     /// キーボードレイアウト図を SVG で生成
     GenerateSvg {
         /// 出力ファイルパス（省略時は stdout）
         #[arg(short, long)]
         output: Option<String>,
-        /// config.toml パス（省略時は自動検出）
+    },
+    /// kanata 用の kbd ファイルを config.toml から生成
+    GenerateKbd {
+        /// 出力ファイルパス（省略時は stdout）
         #[arg(short, long)]
-        config: Option<String>,
+        output: Option<String>,
+        /// --config を指定しても kbd の cmd に付けない（配布用の kbd を生成する場合）
+        #[arg(long)]
+        no_config_arg: bool,
+    },
+    /// エディタの補完・検証用に config.toml の JSON Schema を生成
+    GenerateSchema {
+        /// 出力ファイルパス（省略時は stdout）
+        #[arg(short, long)]
+        output: Option<String>,
     },
 }
 
@@ -73,43 +120,115 @@ fn main() {
 
 fn run() -> Result<()> {
     let cli = Cli::parse();
+    let config_file = cli.config.as_deref();
 
     // config 不要なコマンドは先に処理
     if let Commands::OpenGui = cli.command {
         return commands::open_gui::run();
     }
+    if let Commands::GenerateSchema { output } = &cli.command {
+        let schema = config::schema::generate();
+        match output {
+            Some(path) => std::fs::write(path, &schema)?,
+            None => print!("{}", schema),
+        }
+        return Ok(());
+    }
+    // config.toml を直接読み書きするコマンド
     if let Commands::ToggleTimestampPosition = cli.command {
-        return commands::timestamp_settings::toggle_position();
+        return commands::timestamp_settings::toggle_position(config_file);
     }
-
-    // GenerateSvg は独自の config 読み込みを行う
-    if let Commands::GenerateSvg {
-        ref output,
-        ref config,
-    } = cli.command
-    {
-        let cfg = match config {
-            Some(path) => muhenkan_switch_config::load_from(std::path::Path::new(path))?,
-            None => muhenkan_switch_config::load()?,
-        };
-        let svg = muhenkan_switch_config::svg::generate(&cfg);
-        match output {
-            Some(path) => std::fs::write(path, &svg)?,
-            None => print!("{}", svg),
+    if let Commands::SwitchProfile { name } = &cli.command {
+        return commands::profile::switch(name.as_deref(), config_file);
+    }
+    if let Commands::ShowConfig = cli.command {
+        return commands::show_config::run(config_file);
+    }
+    if let Commands::Check { json } = cli.command {
+        if !commands::check::run(config_file, json)? {
+            std::process::exit(1);
         }
         return Ok(());
     }
 
-    let config = config::load()?;
+    let config = config::load_with(config_file)?;
 
     match cli.command {
         Commands::Search { engine } => commands::search::run(&engine, &config),
         Commands::SwitchApp { target } => commands::switch_app::run(&target, &config),
         Commands::OpenFolder { target } => commands::open_folder::run(&target, &config),
+        Commands::RunCommand { target } => commands::run_command::run(&target, &config),
+        Commands::Snippet { target } => commands::snippet::run(&target, &config),
         Commands::Timestamp { action } => commands::timestamp::run(&action, &config),
-        Commands::Dispatch { key } => commands::dispatch::run(&key, &config),
+        Commands::Dispatch { key, shift } => {
+            let modifier = if shift {
+                config::Modifier::Shift
+            } else {
+                config::Modifier::None
+            };
+            commands::dispatch::run(&key, modifier, &config)
+        }
+        Commands::DoubleTap => commands::dispatch::run_double_tap(&config),
+        Commands::GenerateSvg { output } => {
+            let svg = config::svg::generate(&config);
+            match output {
+                Some(path) => std::fs::write(path, &svg)?,
+                None => print!("{}", svg),
+            }
+            Ok(())
+        }
+        Commands::GenerateKbd { output, no_config_arg } => {
+            // kanata はカレントディレクトリが異なるため絶対パスにする
+            let config_path = match config_file {
+                Some(path) if !no_config_arg => Some(std::path::absolute(path)?),
+                _ => None,
+            };
+            let kbd = config::kbd::generate_with(&config, config_path.as_deref());
+            match output {
+                Some(path) => std::fs::write(path, &kbd)?,
+                None => print!("{}", kbd),
+            }
+            Ok(())
+        }
         Commands::OpenGui
-        | Commands::GenerateSvg { .. }
-        | Commands::ToggleTimestampPosition => unreachable!(),
+        | Commands::GenerateSchema { .. }
+        | Commands::ToggleTimestampPosition
+        | Commands::SwitchProfile { .. }
+        | Commands::ShowConfig
+        | Commands::Check { .. } => unreachable!(),
+    }
+}
+
+// ── Tests ──
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_flag_is_global() {
+        for args in [
+            ["muhenkan-switch-core", "--config", "fixture.toml", "dispatch", "g"],
+            ["muhenkan-switch-core", "dispatch", "g", "--config", "fixture.toml"],
+            ["muhenkan-switch-core", "dispatch", "g", "-c", "fixture.toml"],
+        ] {
+            let cli = Cli::try_parse_from(args).unwrap();
+            assert_eq!(cli.config.as_deref(), Some(std::path::Path::new("fixture.toml")));
+        }
+    }
+
+    #[test]
+    fn generate_kbd_accepts_config_after_subcommand() {
+        let cli = Cli::try_parse_from([
+            "muhenkan-switch-core",
+            "generate-kbd",
+            "-c",
+            "config/default-linux.toml",
+            "-o",
+            "muhenkan.kbd",
+        ])
+        .unwrap();
+        assert!(cli.config.is_some());
+        assert!(matches!(cli.command, Commands::GenerateKbd { output: Some(_), no_config_arg: false }));
     }
 }
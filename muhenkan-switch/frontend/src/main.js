@@ -5,14 +5,11 @@ const { message, ask } = window.__TAURI__.dialog;
 // ── State ──
 let config = null;       // Current config from backend
 let guiSettings = {};    // GUI-only settings
+let triggerLabel = "無変換"; // Trigger key name from [trigger]
+let savedSnapshot = null; // 最後に読み込み・保存した時点の編集内容
 
-// ── Available dispatch keys (must match kbd file) ──
-const DISPATCH_KEYS = [
-  "1", "2", "3", "4", "5",
-  "q", "w", "e", "r", "t",
-  "a", "s", "d", "f", "g",
-  "z", "b",
-];
+// ── Available dispatch keys (loaded from backend: keys::assignable_keys) ──
+let DISPATCH_KEYS = [];
 
 // ── Presets (loaded from backend) ──
 let APP_PRESETS = {};
@@ -69,24 +66,81 @@ document.querySelectorAll(".tab").forEach((tab) => {
 // ── Load config on startup ──
 async function loadConfig() {
   try {
-    [config, APP_PRESETS, SEARCH_PRESETS] = await Promise.all([
+    [config, APP_PRESETS, SEARCH_PRESETS, triggerLabel, DISPATCH_KEYS] = await Promise.all([
       invoke("get_config"),
       invoke("get_app_presets"),
       invoke("get_search_presets"),
+      invoke("get_trigger_label"),
+      invoke("get_assignable_keys"),
     ]);
     renderConfig();
+    markSaved();
   } catch (e) {
     console.error("設定の読み込みに失敗:", e);
   }
+  await refreshConfigWarnings();
+  await refreshBackups();
 }
 
+// ── 未保存の変更の検出 ──
+// プロファイルの選択はその場で保存されるため比較しない
+function editedSnapshot() {
+  const { active_profile, profiles, ...edited } = collectConfig();
+  return JSON.stringify(edited);
+}
+
+function markSaved() {
+  savedSnapshot = editedSnapshot();
+}
+
+function hasUnsavedChanges() {
+  return savedSnapshot !== null && editedSnapshot() !== savedSnapshot;
+}
+
+// ── config.toml の不明なキー（typo など）の警告 ──
+async function refreshConfigWarnings() {
+  let warnings = [];
+  try {
+    warnings = await invoke("get_config_warnings");
+  } catch (e) {
+    console.error("設定の警告の取得に失敗:", e);
+  }
+  const list = document.getElementById("config-warnings-list");
+  list.innerHTML = "";
+  for (const d of warnings) {
+    const li = document.createElement("li");
+    const file = d.file ? d.file.split(/[\\/]/).pop() : "config.toml";
+    const location = d.line ? `${file}:${d.line}` : file;
+    li.textContent = `${location}: ${formatDiagnostic(d)}`;
+    list.appendChild(li);
+  }
+  document.getElementById("config-warnings").hidden = warnings.length === 0;
+}
+
+document.getElementById("btn-dismiss-warnings").addEventListener("click", () => {
+  document.getElementById("config-warnings").hidden = true;
+});
+
 // ── Render config to UI ──
 function renderConfig() {
   if (!config) return;
 
+  // Profiles
+  renderProfiles();
+
   // Punctuation style
   document.getElementById("punctuation-style").value = config.punctuation_style || "、。";
 
+  // Keyboard layout
+  document.getElementById("keyboard-layout").value = config.layout || "jis";
+
+  // Editing preset
+  document.getElementById("editing-preset").value = config.editing?.preset || "vim";
+
+  // Extra layers
+  document.getElementById("extra-layer-mouse").checked = !!config.extra_layers?.mouse;
+  document.getElementById("extra-layer-numpad").checked = !!config.extra_layers?.numpad;
+
   // Timestamp
   renderTimestamp();
 
@@ -98,8 +152,71 @@ function renderConfig() {
 
   // Apps
   renderAppsList();
+
+  // Commands
+  renderCommandsList();
+
+  // Snippets
+  renderSnippetsList();
 }
 
+// ── Profiles ──
+const profileSelect = document.getElementById("active-profile");
+
+function renderProfiles() {
+  const names = Object.keys(config.profiles || {});
+  document.getElementById("profile-fieldset").hidden = names.length === 0;
+  profileSelect.innerHTML = "";
+  for (const [value, label] of [["", "標準"], ...names.map((n) => [n, n])]) {
+    const opt = document.createElement("option");
+    opt.value = value;
+    opt.textContent = label;
+    profileSelect.appendChild(opt);
+  }
+  profileSelect.value = config.active_profile || "";
+}
+
+profileSelect.addEventListener("change", async () => {
+  const name = profileSelect.value || null;
+  try {
+    await invoke("set_active_profile", { name });
+    config.active_profile = name;
+  } catch (e) {
+    await message("プロファイルの切り替えに失敗しました:\n" + e, { title: "エラー", kind: "error" });
+    profileSelect.value = config.active_profile || "";
+  }
+});
+
+// トレイでの切り替えを反映する（編集中の内容は保持する）
+listen("config-saved", async () => {
+  try {
+    const latest = await invoke("get_config");
+    config.active_profile = latest.active_profile;
+    config.profiles = latest.profiles;
+    renderProfiles();
+  } catch (e) {
+    console.error("設定の再読み込みに失敗:", e);
+  }
+  await refreshBackups();
+});
+
+// エディタなど GUI 以外で編集された config.toml を反映する（検証済みの設定が届く）
+// 未保存の変更がある場合は、破棄してよいか確認する
+listen("config-changed", async (event) => {
+  if (hasUnsavedChanges()) {
+    const yes = await ask(
+      "config.toml が GUI の外で変更されました。\n未保存の変更を破棄して、変更後の設定を読み込みますか？",
+      { title: "設定の再読み込み", kind: "warning" },
+    );
+    if (!yes) return;
+  }
+  config = event.payload;
+  renderConfig();
+  markSaved();
+  await refreshConfigWarnings();
+  await refreshBackups();
+});
+
 // ── Timestamp ──
 function renderTimestamp() {
   // Format
@@ -203,20 +320,20 @@ document.querySelectorAll('input[name="ts-position"]').forEach((radio) => {
 });
 
 // ── Dispatch key dropdown helper ──
-function createDispatchKeySelect(selectedKey = "") {
+function createDispatchKeySelect(selectedKey = "", shift = false) {
   const select = document.createElement("select");
-  select.className = "dispatch-key-select";
-  select.title = "無変換+キー";
+  select.className = shift ? "shift-key-select" : "dispatch-key-select";
+  select.title = shift ? `${triggerLabel}+Shift+キー` : `${triggerLabel}+キー`;
 
   const noneOpt = document.createElement("option");
   noneOpt.value = "";
-  noneOpt.textContent = "—";
+  noneOpt.textContent = shift ? "⇧—" : "—";
   select.appendChild(noneOpt);
 
   for (const k of DISPATCH_KEYS) {
     const opt = document.createElement("option");
     opt.value = k;
-    opt.textContent = k.toUpperCase();
+    opt.textContent = shift ? `⇧${k.toUpperCase()}` : k.toUpperCase();
     select.appendChild(opt);
   }
 
@@ -229,11 +346,11 @@ function renderSearchList() {
   const container = document.getElementById("search-list");
   container.innerHTML = "";
   for (const [name, entry] of Object.entries(config.search || {})) {
-    addSearchRow(container, name, entry.url, entry.key || "");
+    addSearchRow(container, name, entry.url, entry.key || "", entry.shift_key || "");
   }
 }
 
-function addSearchRow(container, name = "", url = "", dispatchKey = "") {
+function addSearchRow(container, name = "", url = "", dispatchKey = "", shiftKey = "") {
   const row = document.createElement("div");
   row.className = "list-row";
   row.innerHTML = `
@@ -244,6 +361,7 @@ function addSearchRow(container, name = "", url = "", dispatchKey = "") {
   `;
   const keySelect = createDispatchKeySelect(dispatchKey);
   row.insertBefore(keySelect, row.firstChild);
+  keySelect.insertAdjacentElement("afterend", createDispatchKeySelect(shiftKey, true));
 
   row.querySelector(".btn-pick-search").addEventListener("click", async () => {
     const selected = await showSearchPicker();
@@ -266,11 +384,11 @@ function renderFoldersList() {
   const container = document.getElementById("folders-list");
   container.innerHTML = "";
   for (const [name, entry] of Object.entries(config.folders || {})) {
-    addFolderRow(container, name, entry.path, entry.key || "");
+    addFolderRow(container, name, entry.path, entry.key || "", entry.shift_key || "");
   }
 }
 
-function addFolderRow(container, name = "", path = "", dispatchKey = "") {
+function addFolderRow(container, name = "", path = "", dispatchKey = "", shiftKey = "") {
   const row = document.createElement("div");
   row.className = "list-row";
   row.innerHTML = `
@@ -281,6 +399,7 @@ function addFolderRow(container, name = "", path = "", dispatchKey = "") {
   `;
   const keySelect = createDispatchKeySelect(dispatchKey);
   row.insertBefore(keySelect, row.firstChild);
+  keySelect.insertAdjacentElement("afterend", createDispatchKeySelect(shiftKey, true));
   row.querySelector(".btn-remove").addEventListener("click", () => row.remove());
   row.querySelector(".btn-browse").addEventListener("click", async () => {
     try {
@@ -304,11 +423,11 @@ function renderAppsList() {
   const container = document.getElementById("apps-list");
   container.innerHTML = "";
   for (const [name, entry] of Object.entries(config.apps || {})) {
-    addAppRow(container, name, entry.process, entry.command || "", entry.key || "");
+    addAppRow(container, name, entry.process, entry.command || "", entry.key || "", entry.shift_key || "");
   }
 }
 
-function addAppRow(container, name = "", process = "", command = "", dispatchKey = "") {
+function addAppRow(container, name = "", process = "", command = "", dispatchKey = "", shiftKey = "") {
   const row = document.createElement("div");
   row.className = "list-row";
   row.innerHTML = `
@@ -318,6 +437,7 @@ function addAppRow(container, name = "", process = "", command = "", dispatchKey
   `;
   const keySelect = createDispatchKeySelect(dispatchKey);
   row.insertBefore(keySelect, row.firstChild);
+  keySelect.insertAdjacentElement("afterend", createDispatchKeySelect(shiftKey, true));
 
   const appSelect = createAppSelect(process, command);
   const nameInput = row.querySelector(".key-input");
@@ -356,6 +476,66 @@ document.getElementById("btn-add-app").addEventListener("click", () => {
   addAppRow(document.getElementById("apps-list"));
 });
 
+// ── Commands ──
+function renderCommandsList() {
+  const container = document.getElementById("commands-list");
+  container.innerHTML = "";
+  for (const [name, entry] of Object.entries(config.commands || {})) {
+    addCommandRow(container, name, entry.command, !!entry.show_output, entry.key || "", entry.shift_key || "");
+  }
+}
+
+function addCommandRow(container, name = "", command = "", showOutput = false, dispatchKey = "", shiftKey = "") {
+  const row = document.createElement("div");
+  row.className = "list-row";
+  row.innerHTML = `
+    <input type="text" class="key-input" placeholder="機能名" value="${escapeHtml(name)}">
+    <input type="text" class="command-input" placeholder="コマンド ({selection} など)" value="${escapeHtml(command)}">
+    <label class="checkbox-label show-output-label" title="標準出力を通知で表示">
+      <input type="checkbox" class="show-output-check"${showOutput ? " checked" : ""}>
+      出力を通知
+    </label>
+    <button class="btn-remove" title="削除">&times;</button>
+  `;
+  const keySelect = createDispatchKeySelect(dispatchKey);
+  row.insertBefore(keySelect, row.firstChild);
+  keySelect.insertAdjacentElement("afterend", createDispatchKeySelect(shiftKey, true));
+  row.querySelector(".btn-remove").addEventListener("click", () => row.remove());
+  container.appendChild(row);
+}
+
+document.getElementById("btn-add-command").addEventListener("click", () => {
+  addCommandRow(document.getElementById("commands-list"));
+});
+
+// ── Snippets ──
+function renderSnippetsList() {
+  const container = document.getElementById("snippets-list");
+  container.innerHTML = "";
+  for (const [name, entry] of Object.entries(config.snippets || {})) {
+    addSnippetRow(container, name, entry.text, entry.key || "", entry.shift_key || "");
+  }
+}
+
+function addSnippetRow(container, name = "", text = "", dispatchKey = "", shiftKey = "") {
+  const row = document.createElement("div");
+  row.className = "list-row";
+  row.innerHTML = `
+    <input type="text" class="key-input" placeholder="機能名" value="${escapeHtml(name)}">
+    <textarea class="snippet-input" rows="2" placeholder="定型文 ({date} {cursor} など)">${escapeHtml(text)}</textarea>
+    <button class="btn-remove" title="削除">&times;</button>
+  `;
+  const keySelect = createDispatchKeySelect(dispatchKey);
+  row.insertBefore(keySelect, row.firstChild);
+  keySelect.insertAdjacentElement("afterend", createDispatchKeySelect(shiftKey, true));
+  row.querySelector(".btn-remove").addEventListener("click", () => row.remove());
+  container.appendChild(row);
+}
+
+document.getElementById("btn-add-snippet").addEventListener("click", () => {
+  addSnippetRow(document.getElementById("snippets-list"));
+});
+
 // ── Process picker modal ──
 async function showProcessPicker() {
   return new Promise(async (resolve) => {
@@ -499,32 +679,100 @@ function showSearchPicker() {
   });
 }
 
-// ── Dispatch key duplicate validation ──
+// ── Dispatch key duplicate validation (無変換 / 無変換+Shift のレイヤーごと) ──
 function validateDispatchKeys() {
-  const usedKeys = {};
-  for (const select of document.querySelectorAll(".dispatch-key-select")) {
-    const key = select.value;
-    if (!key) continue;
-    if (usedKeys[key]) {
-      return `割当キー "${key.toUpperCase()}" が重複しています`;
+  for (const [selector, prefix] of [[".dispatch-key-select", ""], [".shift-key-select", "Shift+"]]) {
+    const usedKeys = {};
+    for (const select of document.querySelectorAll(selector)) {
+      const key = select.value;
+      if (!key) continue;
+      if (usedKeys[key]) {
+        return `割当キー "${prefix}${key.toUpperCase()}" が重複しています`;
+      }
+      usedKeys[key] = true;
     }
-    usedKeys[key] = true;
   }
   return null;
 }
 
+// ── Validation diagnostics (Rust 側の validate の結果を該当エントリに表示) ──
+const DIAGNOSTIC_PATH_RE = /^(search|folders|apps|commands|snippets)\.("(?:[^"\\]|\\.)*")\./;
+
+// 診断のパス（例: search."Google".url）から該当する行を探す
+function findDiagnosticRow(path) {
+  const m = DIAGNOSTIC_PATH_RE.exec(path);
+  if (!m) return null;
+  const name = JSON.parse(m[2]);
+  for (const row of document.querySelectorAll(`#${m[1]}-list .list-row`)) {
+    if (row.querySelector(".key-input").value.trim() === name) return row;
+  }
+  return null;
+}
+
+function clearDiagnostic(row) {
+  row.classList.remove("invalid", "warning");
+  row.removeAttribute("title");
+}
+
+// エラーは赤、警告（存在しないフォルダなど）は黄色で強調する
+function highlightDiagnostics(diagnostics) {
+  for (const row of document.querySelectorAll(".list-row.invalid, .list-row.warning")) {
+    clearDiagnostic(row);
+  }
+  for (const d of diagnostics) {
+    const row = findDiagnosticRow(d.path);
+    if (!row || row.classList.contains("invalid")) continue;
+    clearDiagnostic(row);
+    row.classList.add(d.severity === "error" ? "invalid" : "warning");
+    row.title = formatDiagnostic(d);
+  }
+}
+
+function formatDiagnostic(d) {
+  return d.suggestion ? `${d.message}（${d.suggestion}）` : d.message;
+}
+
+// 編集したら強調表示を解除する
+for (const type of ["input", "change"]) {
+  document.addEventListener(type, (e) => {
+    const row = e.target.closest?.(".list-row.invalid, .list-row.warning");
+    if (row) clearDiagnostic(row);
+  });
+}
+
 // ── Collect config from UI ──
 function collectConfig() {
   const collected = {
     search: {},
     folders: {},
     apps: {},
+    commands: {},
+    snippets: {},
+    // アプリ別の上書きは GUI では編集しないため読み込んだ値をそのまま保存する
+    overrides: config?.overrides || [],
+    // プロファイルも GUI では編集しないため、選択状態と定義をそのまま保存する
+    active_profile: config?.active_profile || null,
+    profiles: config?.profiles || {},
     timestamp: {
       format: getTimestampFormat(),
       position: document.querySelector('input[name="ts-position"]:checked').value,
       delimiter: getTimestampDelimiter(),
     },
     punctuation_style: document.getElementById("punctuation-style").value || "、。",
+    layout: document.getElementById("keyboard-layout").value || "jis",
+    // editing.keys は GUI では編集しないため読み込んだ値をそのまま保存する
+    editing: {
+      ...(config?.editing || {}),
+      preset: document.getElementById("editing-preset").value || "vim",
+    },
+    extra_layers: {
+      mouse: document.getElementById("extra-layer-mouse").checked,
+      numpad: document.getElementById("extra-layer-numpad").checked,
+    },
+    // 記号入力も GUI では編集しないため読み込んだ値をそのまま保存する
+    symbols: config?.symbols || {},
+    // トリガーキーも GUI では編集しないため読み込んだ値をそのまま保存する
+    trigger: config?.trigger || {},
   };
 
   // Search
@@ -532,9 +780,11 @@ function collectConfig() {
     const name = row.querySelector(".key-input").value.trim();
     const url = row.querySelector(".url-input").value.trim();
     const dispatchKey = row.querySelector(".dispatch-key-select").value;
+    const shiftKey = row.querySelector(".shift-key-select").value;
     if (name && url) {
       const entry = { url };
       if (dispatchKey) entry.key = dispatchKey;
+      if (shiftKey) entry.shift_key = shiftKey;
       collected.search[name] = entry;
     }
   }
@@ -544,9 +794,11 @@ function collectConfig() {
     const name = row.querySelector(".key-input").value.trim();
     const path = row.querySelector(".path-input").value.trim();
     const dispatchKey = row.querySelector(".dispatch-key-select").value;
+    const shiftKey = row.querySelector(".shift-key-select").value;
     if (name) {
       const entry = { path };
       if (dispatchKey) entry.key = dispatchKey;
+      if (shiftKey) entry.shift_key = shiftKey;
       collected.folders[name] = entry;
     }
   }
@@ -559,6 +811,7 @@ function collectConfig() {
     const selectedOpt = appSelect.options[appSelect.selectedIndex];
     const command = selectedOpt?.dataset?.command || "";
     const dispatchKey = row.querySelector(".dispatch-key-select").value;
+    const shiftKey = row.querySelector(".shift-key-select").value;
     if (name && process) {
       if (collected.apps[name]) {
         const appLabel = selectedOpt?.textContent || process;
@@ -566,11 +819,41 @@ function collectConfig() {
       }
       const entry = { process };
       if (dispatchKey) entry.key = dispatchKey;
+      if (shiftKey) entry.shift_key = shiftKey;
       if (command) entry.command = command;
       collected.apps[name] = entry;
     }
   }
 
+  // Commands
+  for (const row of document.querySelectorAll("#commands-list .list-row")) {
+    const name = row.querySelector(".key-input").value.trim();
+    const command = row.querySelector(".command-input").value.trim();
+    const dispatchKey = row.querySelector(".dispatch-key-select").value;
+    const shiftKey = row.querySelector(".shift-key-select").value;
+    if (name && command) {
+      const entry = { command };
+      if (dispatchKey) entry.key = dispatchKey;
+      if (shiftKey) entry.shift_key = shiftKey;
+      if (row.querySelector(".show-output-check").checked) entry.show_output = true;
+      collected.commands[name] = entry;
+    }
+  }
+
+  // Snippets — 本文は前後の空白・改行も含めてそのまま保存
+  for (const row of document.querySelectorAll("#snippets-list .list-row")) {
+    const name = row.querySelector(".key-input").value.trim();
+    const text = row.querySelector(".snippet-input").value;
+    const dispatchKey = row.querySelector(".dispatch-key-select").value;
+    const shiftKey = row.querySelector(".shift-key-select").value;
+    if (name && text) {
+      const entry = { text };
+      if (dispatchKey) entry.key = dispatchKey;
+      if (shiftKey) entry.shift_key = shiftKey;
+      collected.snippets[name] = entry;
+    }
+  }
+
   return collected;
 }
 
@@ -585,9 +868,19 @@ document.getElementById("btn-apply").addEventListener("click", async () => {
     }
 
     const newConfig = collectConfig();
+    const diagnostics = await invoke("validate_config", { config: newConfig });
+    highlightDiagnostics(diagnostics);
+    const errors = diagnostics.filter((d) => d.severity === "error");
+    if (errors.length > 0) {
+      await message(errors.map(formatDiagnostic).join("\n"), { title: "エラー", kind: "error" });
+      return;
+    }
+
     console.log("[apply] saving config:", JSON.stringify(newConfig).slice(0, 200));
     await invoke("save_config", { config: newConfig });
     config = newConfig;
+    markSaved();
+    await refreshConfigWarnings();
 
     // Brief save success indicator
     const btn = document.getElementById("btn-apply");
@@ -652,20 +945,136 @@ document.getElementById("btn-export-config").addEventListener("click", async ()
 });
 
 document.getElementById("btn-import-config").addEventListener("click", async () => {
-  const ok = await ask("現在の設定を上書きします。よろしいですか？", { title: "インポート", kind: "warning" });
-  if (!ok) return;
   try {
-    const newConfig = await invoke("import_config");
-    if (newConfig) {
-      config = newConfig;
-      renderConfig();
-      await message("設定ファイルをインポートしました。", { title: "インポート" });
-    }
+    const preview = await invoke("preview_import");
+    if (!preview) return;
+    const strategy = await showImportPreview(preview.diff);
+    if (!strategy) return;
+    config = await invoke("apply_import", { path: preview.path, strategy });
+    renderConfig();
+    markSaved();
+    await message("設定ファイルをインポートしました。", { title: "インポート" });
   } catch (e) {
     await message("インポートに失敗しました:\n" + e, { title: "エラー", kind: "error" });
   }
 });
 
+// ── Import preview modal ──
+// 差分を表示し、取り込み方（replace / keep_mine / theirs_win）を選ばせる
+function showImportPreview(diff) {
+  return new Promise((resolve) => {
+    const overlay = document.createElement("div");
+    overlay.className = "modal-overlay";
+    overlay.innerHTML = `
+      <div class="modal">
+        <div class="modal-header">インポートする設定との差分</div>
+        <div class="modal-body">
+          <ul class="modal-list import-diff"></ul>
+        </div>
+        <div class="modal-footer">
+          <button class="btn-cancel">キャンセル</button>
+          <button data-strategy="replace">置き換え</button>
+          <button data-strategy="keep_mine">マージ（今の設定を優先）</button>
+          <button data-strategy="theirs_win">マージ（取り込む側を優先）</button>
+        </div>
+      </div>
+    `;
+
+    const list = overlay.querySelector(".modal-list");
+    const groups = [
+      ["追加", diff.changes.filter((c) => c.change === "added")],
+      ["変更", diff.changes.filter((c) => c.change === "changed")],
+      ["削除（置き換え時のみ）", diff.changes.filter((c) => c.change === "removed")],
+    ];
+    for (const [label, changes] of groups) {
+      if (changes.length === 0) continue;
+      const header = document.createElement("li");
+      header.className = "modal-list-header";
+      header.textContent = label;
+      list.appendChild(header);
+      for (const c of changes) {
+        const li = document.createElement("li");
+        li.textContent = c.section ? `${c.section} / ${c.name}` : c.name;
+        list.appendChild(li);
+      }
+    }
+    if (diff.conflicts.length > 0) {
+      const header = document.createElement("li");
+      header.className = "modal-list-header";
+      header.textContent = "割当キーの衝突（マージ時は優先しない側の割当を外します）";
+      list.appendChild(header);
+      for (const c of diff.conflicts) {
+        const li = document.createElement("li");
+        li.textContent = `${c.key}: ${c.mine} ↔ ${c.theirs}`;
+        list.appendChild(li);
+      }
+    }
+    if (list.children.length === 0) {
+      const li = document.createElement("li");
+      li.textContent = "今の設定と同じです";
+      list.appendChild(li);
+    }
+
+    function close(result) {
+      overlay.remove();
+      document.removeEventListener("keydown", onKeydown);
+      resolve(result);
+    }
+
+    overlay.querySelector(".btn-cancel").addEventListener("click", () => close(null));
+    for (const button of overlay.querySelectorAll("[data-strategy]")) {
+      button.addEventListener("click", () => close(button.dataset.strategy));
+    }
+    overlay.addEventListener("click", (e) => {
+      if (e.target === overlay) close(null);
+    });
+
+    function onKeydown(e) {
+      if (e.key === "Escape") close(null);
+    }
+    document.addEventListener("keydown", onKeydown);
+
+    document.body.appendChild(overlay);
+  });
+}
+
+// ── Config backups ──
+async function refreshBackups() {
+  let backups = [];
+  try {
+    backups = await invoke("list_config_backups");
+  } catch (e) {
+    console.error("バックアップの一覧の取得に失敗:", e);
+  }
+  const select = document.getElementById("backup-select");
+  select.innerHTML = "";
+  for (const backup of backups) {
+    const option = document.createElement("option");
+    option.value = backup.path;
+    option.textContent = backup.saved_at;
+    select.appendChild(option);
+  }
+  select.disabled = backups.length === 0;
+  document.getElementById("btn-restore-backup").disabled = backups.length === 0;
+}
+
+document.getElementById("btn-restore-backup").addEventListener("click", async () => {
+  const select = document.getElementById("backup-select");
+  if (!select.value) return;
+  const label = select.options[select.selectedIndex].textContent;
+  const ok = await ask(`${label} の設定に戻します。よろしいですか？`, { title: "復元", kind: "warning" });
+  if (!ok) return;
+  try {
+    config = await invoke("restore_config_backup", { path: select.value });
+    renderConfig();
+    markSaved();
+    await refreshConfigWarnings();
+    await message("バックアップから復元しました。", { title: "復元" });
+  } catch (e) {
+    await message("復元に失敗しました:\n" + e, { title: "エラー", kind: "error" });
+  }
+});
+
 // ── General tab: help / install dir / quit ──
 document.getElementById("btn-help").addEventListener("click", async () => {
   try {
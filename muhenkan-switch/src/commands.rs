@@ -1,9 +1,11 @@
-use muhenkan_switch_config::{self as config, Config};
+use muhenkan_switch_config::diff::{ConfigDiff, MergeStrategy};
+use muhenkan_switch_config::{self as config, storage::Backup, Config, Diagnostic};
 use serde::Serialize;
 use std::path::PathBuf;
 use tauri::State;
 
 use crate::kanata::KanataManager;
+use crate::watcher::ConfigWatcher;
 
 // ── Config commands ──
 
@@ -17,35 +19,141 @@ fn resolve_config_path() -> PathBuf {
     })
 }
 
+/// 保存先の config.toml（$MUHENKAN_SWITCH_CONFIG かユーザー設定）。バックアップもここに作られる。
+fn writable_config_path() -> PathBuf {
+    config::layers::writable_layer()
+        .and_then(|layer| layer.path)
+        .unwrap_or_else(resolve_config_path)
+}
+
 #[tauri::command]
 pub fn get_config() -> Result<Config, String> {
     config::load().map_err(|e| e.to_string())
 }
 
+/// 読み込んだ config.toml の不明なキー（typo など）の警告。
+#[tauri::command]
+pub fn get_config_warnings() -> Result<Vec<Diagnostic>, String> {
+    config::load_lenient()
+        .map(|(_, warnings)| warnings)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn generate_keyboard_svg() -> Result<String, String> {
     let cfg = config::load().map_err(|e| e.to_string())?;
     Ok(config::svg::generate(&cfg))
 }
 
+/// ヘルプや設定画面に表示するトリガーキー名（例: "無変換"）。
 #[tauri::command]
-pub fn save_config(app: tauri::AppHandle, config: Config, manager: State<KanataManager>) -> Result<(), String> {
-    use tauri::Emitter;
-    let errors = config::validate(&config);
-    if !errors.is_empty() {
-        return Err(errors.join("\n"));
-    }
-    let path = resolve_config_path();
-    config::save(&path, &config).map_err(|e| e.to_string())?;
+pub fn get_trigger_label() -> Result<String, String> {
+    let cfg = config::load().map_err(|e| e.to_string())?;
+    Ok(cfg.trigger_key().label().to_string())
+}
 
-    // kbd ファイルの句読点を書き換え → kanata 再起動
-    if let Ok(kbd_path) = KanataManager::resolve_kbd_path() {
-        let _ = config::rewrite_kbd_punctuation(&kbd_path, &config.punctuation_style);
+/// 割当キーのドロップダウンに並べるキー名（物理配列順）。
+#[tauri::command]
+pub fn get_assignable_keys() -> Vec<&'static str> {
+    config::keys::assignable_keys()
+}
+
+/// 診断のうちエラーがあれば、メッセージを改行区切りでまとめて返す。
+fn check_diagnostics(config: &Config) -> Result<(), String> {
+    let diagnostics = config::validate(config);
+    if !config::has_errors(&diagnostics) {
+        return Ok(());
     }
+    Err(diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// 保存せずに設定を検証する（GUI で該当エントリを強調表示するため）。
+/// フォルダの存在など、保存を妨げない警告も含む。
+#[tauri::command]
+pub fn validate_config(config: Config) -> Vec<Diagnostic> {
+    config::validate_deep(&config)
+}
+
+#[tauri::command]
+pub fn save_config(
+    app: tauri::AppHandle,
+    config: Config,
+    manager: State<KanataManager>,
+    watcher: State<ConfigWatcher>,
+) -> Result<(), String> {
+    check_diagnostics(&config)?;
+    // 下のレイヤー（インストール先など）と違う値だけを保存先に書く
+    watcher
+        .write(|| config::layers::save(&config))
+        .map_err(|e| e.to_string())?;
+    apply_saved(&app, &config, &manager)
+}
+
+/// 保存後の反映: kbd ファイルを再生成 → kanata 再起動、画面とトレイに通知。
+fn apply_saved(app: &tauri::AppHandle, config: &Config, manager: &KanataManager) -> Result<(), String> {
+    use tauri::Emitter;
+    KanataManager::write_kbd(config).map_err(|e| format!("{:#}", e))?;
     let _ = manager.stop();
     let _ = manager.start();
 
     let _ = app.emit("config-saved", ());
+    crate::tray::refresh(app);
+    Ok(())
+}
+
+/// config.toml のバックアップ（新しい順）。
+#[tauri::command]
+pub fn list_config_backups() -> Result<Vec<Backup>, String> {
+    config::storage::list_backups(&writable_config_path()).map_err(|e| e.to_string())
+}
+
+/// バックアップから config.toml を復元して反映する。
+#[tauri::command]
+pub fn restore_config_backup(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    manager: State<KanataManager>,
+    watcher: State<ConfigWatcher>,
+) -> Result<Config, String> {
+    let dest = writable_config_path();
+    watcher
+        .write(|| config::storage::restore_backup(&dest, &path))
+        .map_err(|e| format!("{:#}", e))?;
+    let restored = config::load().map_err(|e| e.to_string())?;
+    apply_saved(&app, &restored, &manager)?;
+    Ok(restored)
+}
+
+/// 選択中のプロファイルを切り替えて保存する（None で標準）。
+/// 割当キーは全プロファイル分が kbd に含まれるため、kanata は再起動しない。
+#[tauri::command]
+pub fn set_active_profile(
+    app: tauri::AppHandle,
+    name: Option<String>,
+    watcher: State<ConfigWatcher>,
+) -> Result<(), String> {
+    use tauri::Emitter;
+    watcher
+        .write(|| {
+            config::layers::update(|cfg| {
+                if let Some(n) = &name {
+                    if !cfg.profiles.contains_key(n) {
+                        anyhow::bail!("プロファイル '{}' が config.toml に定義されていません", n);
+                    }
+                }
+                cfg.active_profile = name;
+                Ok(())
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let _ = app.emit("config-saved", ());
+    crate::tray::refresh(&app);
     Ok(())
 }
 
@@ -84,8 +192,16 @@ pub async fn export_config(app: tauri::AppHandle) -> Result<bool, String> {
     }
 }
 
+/// インポートするファイルと、今の設定との差分。
+#[derive(Serialize)]
+pub struct ImportPreview {
+    path: PathBuf,
+    diff: ConfigDiff,
+}
+
+/// インポートするファイルを選び、今の設定との差分を返す（まだ保存しない）。
 #[tauri::command]
-pub async fn import_config(app: tauri::AppHandle) -> Result<Option<Config>, String> {
+pub async fn preview_import(app: tauri::AppHandle) -> Result<Option<ImportPreview>, String> {
     use tauri_plugin_dialog::DialogExt;
     let (tx, rx) = std::sync::mpsc::channel();
     app.dialog()
@@ -96,20 +212,36 @@ pub async fn import_config(app: tauri::AppHandle) -> Result<Option<Config>, Stri
         });
     let selected = rx.recv().map_err(|e| e.to_string())?;
     match selected {
-        Some(src) => {
-            let imported = config::load_from(&src).map_err(|e| e.to_string())?;
-            let errors = config::validate(&imported);
-            if !errors.is_empty() {
-                return Err(errors.join("\n"));
-            }
-            let dest = resolve_config_path();
-            config::save(&dest, &imported).map_err(|e| e.to_string())?;
-            Ok(Some(imported))
+        Some(path) => {
+            let imported = config::load_from(&path).map_err(|e| e.to_string())?;
+            let current = config::load().map_err(|e| e.to_string())?;
+            let diff = config::diff::diff(&current, &imported);
+            Ok(Some(ImportPreview { path, diff }))
         }
         None => Ok(None),
     }
 }
 
+/// 選んだファイルを指定の方法（置き換え / マージ）で取り込んで保存する。
+#[tauri::command]
+pub fn apply_import(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    strategy: MergeStrategy,
+    manager: State<KanataManager>,
+    watcher: State<ConfigWatcher>,
+) -> Result<Config, String> {
+    let imported = config::load_from(&path).map_err(|e| e.to_string())?;
+    let current = config::load().map_err(|e| e.to_string())?;
+    let merged = config::diff::merge(&current, &imported, strategy);
+    check_diagnostics(&merged)?;
+    watcher
+        .write(|| config::layers::save(&merged))
+        .map_err(|e| e.to_string())?;
+    apply_saved(&app, &merged, &manager)?;
+    Ok(merged)
+}
+
 // ── App presets ──
 
 /// コンパイル時に埋め込んだ app-presets.json から現在の OS 用プリセットを返す。
@@ -530,21 +662,12 @@ document.addEventListener("keydown", e => {{
 pub fn validate_timestamp_format(
     format: String,
     delimiter: String,
-    position: String,
+    position: config::TimestampPosition,
 ) -> Result<String, String> {
-    if format.is_empty() {
-        return Err("フォーマットを入力してください".to_string());
-    }
-    let now = chrono::Local::now();
-    use std::fmt::Write;
-    let mut ts = String::new();
-    write!(ts, "{}", now.format(&format))
-        .map_err(|_| "無効なフォーマット文字列です".to_string())?;
-    let (stem, ext) = ("FileName", ".txt");
-    let preview = if position == "after" {
-        format!("{}{}{}{}", stem, delimiter, ts, ext)
-    } else {
-        format!("{}{}{}{}", ts, delimiter, stem, ext)
+    let timestamp = config::TimestampConfig {
+        format,
+        position,
+        delimiter,
     };
-    Ok(preview)
+    config::timestamp::preview(&timestamp, &chrono::Local::now()).map_err(|d| d.to_string())
 }
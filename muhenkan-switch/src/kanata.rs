@@ -6,6 +6,13 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tauri::{Emitter, Manager};
 
+/// kbd ファイル名（macOS はインストーラーが配置する muhenkan-macos.kbd を config.toml から再生成する）。
+const KBD_FILE: &str = if cfg!(target_os = "macos") {
+    "muhenkan-macos.kbd"
+} else {
+    "muhenkan.kbd"
+};
+
 // ── Platform: Windows (Job Object) ──
 
 /// Windows Job Object: GUI 終了時に子プロセス (kanata) を自動終了させる。
@@ -206,12 +213,12 @@ impl KanataManager {
     /// kanata 設定ファイルのパスを取得
     ///
     /// 探索順序:
-    /// 1. exe と同じディレクトリの muhenkan.kbd（インストール環境 / dev: ./bin/ 実行時）
-    /// 2. CARGO_MANIFEST_DIR/../bin/muhenkan.kbd（開発環境: cargo run 互換）
+    /// 1. exe と同じディレクトリの KBD_FILE（インストール環境 / dev: ./bin/ 実行時）
+    /// 2. CARGO_MANIFEST_DIR/../bin/KBD_FILE（開発環境: cargo run 互換）
     fn kbd_path() -> Result<PathBuf> {
         // 1. exe と同じディレクトリ
         if let Ok(exe_dir) = std::env::current_exe().map(|p| p.parent().unwrap().to_path_buf()) {
-            let path = exe_dir.join("muhenkan.kbd");
+            let path = exe_dir.join(KBD_FILE);
             if path.exists() {
                 return Ok(path);
             }
@@ -222,7 +229,7 @@ impl KanataManager {
             .parent()
             .map(|p| p.join("bin"));
         if let Some(ref dir) = bin_dir {
-            let path = dir.join("muhenkan.kbd");
+            let path = dir.join(KBD_FILE);
             if path.exists() {
                 return Ok(path);
             }
@@ -262,9 +269,28 @@ impl KanataManager {
         anyhow::bail!("キー割当の補助プログラムが見つかりません。\n再インストールしてください。");
     }
 
-    /// kbd ファイルのパスを外部に公開する（句読点書き換え用）
-    pub fn resolve_kbd_path() -> Result<PathBuf> {
-        Self::kbd_path()
+    /// config.toml の内容から kbd ファイルを再生成する。
+    /// kbd ファイルが見つからなければ exe と同じディレクトリに作成する。
+    pub fn write_kbd(config: &muhenkan_switch_config::Config) -> Result<PathBuf> {
+        let path = Self::kbd_target()?;
+        muhenkan_switch_config::kbd::write(&path, config)?;
+        Ok(path)
+    }
+
+    /// 内容が変わる場合だけ kbd ファイルを書き込む。書き込んだら true。
+    pub fn sync_kbd(config: &muhenkan_switch_config::Config) -> Result<bool> {
+        muhenkan_switch_config::kbd::sync(&Self::kbd_target()?, config)
+    }
+
+    /// kbd ファイルの書き込み先（未生成なら実行ファイルと同じディレクトリ）。
+    fn kbd_target() -> Result<PathBuf> {
+        match Self::kbd_path() {
+            Ok(path) => Ok(path),
+            Err(_) => std::env::current_exe()
+                .ok()
+                .and_then(|p| p.parent().map(|d| d.join(KBD_FILE)))
+                .context("kbd ファイルの保存先が見つかりません"),
+        }
     }
 
     pub fn start(&self) -> Result<()> {
@@ -3,6 +3,7 @@
 mod commands;
 mod kanata;
 mod tray;
+mod watcher;
 
 fn main() {
     // windows_subsystem = "windows" では stderr が見えないため、パニック時にファイルへ記録
@@ -38,12 +39,20 @@ fn main() {
         .manage(kanata::KanataManager::new())
         .invoke_handler(tauri::generate_handler![
             commands::get_config,
+            commands::get_config_warnings,
             commands::generate_keyboard_svg,
+            commands::get_trigger_label,
+            commands::get_assignable_keys,
+            commands::validate_config,
             commands::save_config,
+            commands::set_active_profile,
             commands::reset_config,
             commands::default_config,
             commands::export_config,
-            commands::import_config,
+            commands::preview_import,
+            commands::apply_import,
+            commands::list_config_backups,
+            commands::restore_config_backup,
             commands::get_kanata_status,
             commands::start_kanata,
             commands::stop_kanata,
@@ -71,34 +80,60 @@ fn main() {
                 if let Some(exe_dir) = exe_path.parent() {
                     let config_path = exe_dir.join("config.toml");
                     let should_generate = if config_path.exists() {
-                        // 旧バージョンが生成した不完全な config.toml を検出して再生成
-                        match muhenkan_switch_config::load_from(&config_path) {
-                            Ok(cfg) => cfg.folders.is_empty() && cfg.apps.is_empty(),
-                            Err(_) => true,
+                        // 旧形式の config.toml は元のファイルをバックアップして現在の形式に移行する
+                        match muhenkan_switch_config::migrate::migrate_file(&config_path) {
+                            Ok(Some(report)) => {
+                                eprintln!("[setup] {}", report);
+                                notify_migration(app.handle(), &report);
+                                false
+                            }
+                            Ok(None) => false,
+                            Err(e) if !muhenkan_switch_config::migrate::is_parse_error(&e) => {
+                                // 読み込みエラー・新しい config_version などはファイルに手を付けない
+                                eprintln!("[setup] config.toml を移行できません: {:#}", e);
+                                notify_config_error(app.handle(), &e);
+                                false
+                            }
+                            Err(e) => {
+                                // TOML として解析できないファイルは退避し、最新のバックアップから復元する。
+                                // 復元できなければ再生成する
+                                eprintln!("[setup] config.toml を移行できません: {:#}", e);
+                                let broken = config_path.with_extension("toml.broken.bak");
+                                if let Err(e) = std::fs::rename(&config_path, &broken) {
+                                    eprintln!("[setup] config.toml の退避に失敗: {}", e);
+                                }
+                                match muhenkan_switch_config::storage::restore_latest_backup(&config_path) {
+                                    Ok(Some(backup)) => {
+                                        eprintln!("[setup] バックアップ ({}) から復元しました", backup.saved_at);
+                                        false
+                                    }
+                                    Ok(None) => true,
+                                    Err(e) => {
+                                        eprintln!("[setup] バックアップからの復元に失敗: {:#}", e);
+                                        true
+                                    }
+                                }
+                            }
                         }
                     } else {
                         true
                     };
-                    if should_generate {
-                        let default = muhenkan_switch_config::default_config();
-                        if let Err(e) = muhenkan_switch_config::save(&config_path, &default) {
-                            eprintln!("[setup] config.toml の生成に失敗: {:#}", e);
-                        }
+                    // 他のレイヤー（ユーザー設定など）に config.toml があれば生成しない
+                    if should_generate && muhenkan_switch_config::config_path().is_none() {
+                        generate_default_config(&config_path);
                     }
                 }
             }
-            // kbd ファイルの句読点を config に合わせて同期
+            // kbd ファイルを config から再生成
             if let Ok(cfg) = muhenkan_switch_config::load() {
-                if let Ok(kbd_path) = kanata::KanataManager::resolve_kbd_path() {
-                    let _ = muhenkan_switch_config::rewrite_kbd_punctuation(
-                        &kbd_path,
-                        &cfg.punctuation_style,
-                    );
+                if let Err(e) = kanata::KanataManager::write_kbd(&cfg) {
+                    eprintln!("[setup] kbd ファイルの生成に失敗: {:#}", e);
                 }
             }
 
             tray::setup(app)?;
             kanata::setup(app)?;
+            watcher::setup(app);
             Ok(())
         })
         .on_window_event(|window, event| {
@@ -124,3 +159,53 @@ fn main() {
         }
     });
 }
+
+/// config.toml を移行したことを通知する（詳細は stderr に出力済み）。
+fn notify_migration(handle: &tauri::AppHandle, report: &muhenkan_switch_config::MigrationReport) {
+    use tauri_plugin_notification::NotificationExt;
+    let mut body = format!(
+        "設定ファイルを新しい形式に移行しました（{} 件の変更）。",
+        report.changes.len()
+    );
+    if let Some(backup) = report.backup.as_ref().and_then(|p| p.file_name()) {
+        body.push_str(&format!("\n元のファイル: {}", backup.to_string_lossy()));
+    }
+    let _ = handle
+        .notification()
+        .builder()
+        .title("muhenkan-switch")
+        .body(body)
+        .show();
+}
+
+/// 移行できなかった config.toml をそのまま残したことを通知する。
+fn notify_config_error(handle: &tauri::AppHandle, e: &anyhow::Error) {
+    use tauri_plugin_notification::NotificationExt;
+    let _ = handle
+        .notification()
+        .builder()
+        .title("muhenkan-switch")
+        .body(format!("config.toml を読み込めないため、変更せずに残しました。\n{:#}", e))
+        .show();
+}
+
+/// デフォルトの config.toml を生成する。
+/// インストール先に書き込めない場合はユーザーごとの設定ディレクトリに作る。
+fn generate_default_config(exe_config: &std::path::Path) {
+    let default = muhenkan_switch_config::default_config();
+    let Err(e) = muhenkan_switch_config::save(exe_config, &default) else {
+        return;
+    };
+    eprintln!("[setup] config.toml の生成に失敗: {:#}", e);
+    let Some(user_config) = muhenkan_switch_config::layers::user_config_path() else {
+        return;
+    };
+    let result = user_config
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| muhenkan_switch_config::save(&user_config, &default));
+    if let Err(e) = result {
+        eprintln!("[setup] {} の生成に失敗: {:#}", user_config.display(), e);
+    }
+}
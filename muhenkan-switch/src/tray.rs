@@ -1,9 +1,15 @@
 use std::sync::Mutex;
 use std::time::Instant;
 
-use tauri::menu::{CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, PredefinedMenuItem};
+use tauri::menu::{
+    CheckMenuItemBuilder, Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder,
+};
 use tauri::tray::TrayIconBuilder;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+const TRAY_ID: &str = "main";
+/// プロファイル切替メニューの id 接頭辞（"profile:" のみは標準）
+const PROFILE_ID_PREFIX: &str = "profile:";
 
 pub fn setup(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     let handle = app.handle();
@@ -13,11 +19,23 @@ pub fn setup(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn build_tray(handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+/// config.toml の変更（プロファイル切替など）をトレイメニューに反映する。
+pub fn refresh(handle: &AppHandle) {
+    if let Some(tray) = handle.tray_by_id(TRAY_ID) {
+        if let Ok(menu) = build_menu(handle) {
+            let _ = tray.set_menu(Some(menu));
+        }
+    }
+}
+
+fn build_menu(handle: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    use tauri_plugin_autostart::ManagerExt;
+
     let settings_item = MenuItemBuilder::with_id("settings", "設定...").build(handle)?;
     let sep1 = PredefinedMenuItem::separator(handle)?;
     let autostart_item =
         CheckMenuItemBuilder::with_id("autostart", "ログイン時に自動起動")
+            .checked(handle.autolaunch().is_enabled().unwrap_or(false))
             .build(handle)?;
     let open_dir_item = MenuItemBuilder::with_id("open_dir", "インストール先を開く")
         .build(handle)?;
@@ -32,18 +50,48 @@ fn build_tray(handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
 
     let mut menu = MenuBuilder::new(handle)
         .item(&settings_item)
-        .item(&sep1)
+        .item(&sep1);
+    // プロファイルが定義されていればサブメニューで切り替えられるようにする
+    if let Some(profile_menu) = build_profile_menu(handle)? {
+        menu = menu.item(&profile_menu);
+    }
+    let mut menu = menu
         .item(&autostart_item)
         .item(&open_dir_item);
     if let Some(ref item) = check_update_item {
         menu = menu.item(item);
     }
-    let menu = menu
-        .item(&sep3)
-        .item(&quit_item)
-        .build()?;
+    menu.item(&sep3).item(&quit_item).build()
+}
+
+/// プロファイル切替のサブメニュー（profiles 未定義なら None）。
+fn build_profile_menu(handle: &AppHandle) -> tauri::Result<Option<tauri::menu::Submenu<Wry>>> {
+    let Ok(cfg) = muhenkan_switch_config::load() else {
+        return Ok(None);
+    };
+    if cfg.profiles.is_empty() {
+        return Ok(None);
+    }
+    let standard = CheckMenuItemBuilder::with_id(
+        PROFILE_ID_PREFIX,
+        muhenkan_switch_config::DEFAULT_PROFILE_LABEL,
+    )
+    .checked(cfg.profile().is_none())
+    .build(handle)?;
+    let mut submenu = SubmenuBuilder::with_id(handle, "profile", "プロファイル").item(&standard);
+    for name in cfg.profiles.keys() {
+        let item = CheckMenuItemBuilder::with_id(format!("{PROFILE_ID_PREFIX}{name}"), name)
+            .checked(cfg.active_profile.as_deref() == Some(name.as_str()))
+            .build(handle)?;
+        submenu = submenu.item(&item);
+    }
+    Ok(Some(submenu.build()?))
+}
 
-    let _tray = TrayIconBuilder::new()
+fn build_tray(handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let menu = build_menu(handle)?;
+
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(handle.default_window_icon().cloned().ok_or("no app icon")?)
         .menu(&menu)
         .tooltip("muhenkan-switch")
@@ -72,6 +120,16 @@ fn build_tray(handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
+                id if id.starts_with(PROFILE_ID_PREFIX) => {
+                    let name = &id[PROFILE_ID_PREFIX.len()..];
+                    let name = (!name.is_empty()).then(|| name.to_string());
+                    let watcher = app.state::<crate::watcher::ConfigWatcher>();
+                    if let Err(e) = crate::commands::set_active_profile(app.clone(), name, watcher) {
+                        eprintln!("プロファイルの切り替えに失敗しました: {}", e);
+                        // 失敗時はチェック状態を config.toml に合わせ直す（成功時は set_active_profile が更新する）
+                        refresh(app);
+                    }
+                }
                 "quit" => {
                     use crate::kanata::KanataManager;
                     let manager = app.state::<KanataManager>();
@@ -0,0 +1,124 @@
+//! config.toml の変更監視。
+//!
+//! エディタなど GUI 以外で編集された config.toml を検出し、検証してから
+//! kbd ファイルの再生成・kanata の再起動を行い、画面に `config-changed` を通知する。
+//! シグナルファイルと同じくポーリングで監視する（ファイルは小さいので内容ごと比較する）。
+
+use muhenkan_switch_config as config;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::kanata::KanataManager;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 変更を検出してから反映するまでの待ち時間（エディタの書き込み途中を読まないため）
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// 設定ファイルの候補ごとの内容（存在しなければ None）。
+type Snapshot = Vec<(PathBuf, Option<String>)>;
+
+fn snapshot() -> Snapshot {
+    config::layers::candidate_paths()
+        .into_iter()
+        .map(|(_, path)| {
+            let content = std::fs::read_to_string(&path).ok();
+            (path, content)
+        })
+        .collect()
+}
+
+/// 最後に反映した設定ファイルの内容。
+pub struct ConfigWatcher {
+    last: Mutex<Snapshot>,
+}
+
+impl ConfigWatcher {
+    /// GUI 自身による書き込みを行う。書き込んだ内容は外部の変更として扱わない。
+    pub fn write<T>(&self, f: impl FnOnce() -> T) -> T {
+        let mut last = self.last.lock().unwrap();
+        let result = f();
+        *last = snapshot();
+        result
+    }
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(ConfigWatcher {
+        last: Mutex::new(snapshot()),
+    });
+
+    let handle = app.handle().clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let watcher = handle.state::<ConfigWatcher>();
+        let current = snapshot();
+        if current == *watcher.last.lock().unwrap() {
+            continue;
+        }
+
+        std::thread::sleep(SETTLE_DELAY);
+        {
+            let mut last = watcher.last.lock().unwrap();
+            // まだ書き込み中 or その間に GUI が保存した場合は次の周期で判定する
+            if snapshot() != current || current == *last {
+                continue;
+            }
+            *last = current;
+        }
+        reload(&handle);
+    });
+}
+
+/// 変更された設定を検証して反映する。エラーがあれば反映せずに通知する。
+fn reload(app: &AppHandle) {
+    let cfg = match config::load() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            notify(app, &format!("config.toml を読み込めません:\n{:#}", e));
+            return;
+        }
+    };
+    let diagnostics = config::validate(&cfg);
+    if config::has_errors(&diagnostics) {
+        let errors: Vec<String> = diagnostics
+            .iter()
+            .filter(|d| d.is_error())
+            .map(ToString::to_string)
+            .collect();
+        notify(
+            app,
+            &format!("config.toml にエラーがあるため反映しませんでした:\n{}", errors.join("\n")),
+        );
+        return;
+    }
+    eprintln!("[watcher] config.toml の変更を反映します");
+
+    match KanataManager::sync_kbd(&cfg) {
+        Ok(true) => {
+            let manager = app.state::<KanataManager>();
+            if manager.status().0 {
+                let _ = manager.stop();
+                let _ = manager.start();
+            }
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("[watcher] kbd ファイルの生成に失敗: {:#}", e),
+    }
+
+    let _ = app.emit("config-changed", &cfg);
+    crate::tray::refresh(app);
+}
+
+fn notify(app: &AppHandle, body: &str) {
+    use tauri_plugin_notification::NotificationExt;
+    eprintln!("[watcher] {}", body);
+    let _ = app
+        .notification()
+        .builder()
+        .title("muhenkan-switch")
+        .body(body)
+        .show();
+}
@@ -32,6 +32,10 @@ fi
 cp kanata/muhenkan.kbd ./bin/muhenkan.kbd
 echo "Copied -> ./bin/muhenkan.kbd"
 
+# エディタ用の JSON Schema を bin/ にコピー（config.toml の #:schema から参照）
+cp config/config.schema.json ./bin/config.schema.json
+echo "Copied -> ./bin/config.schema.json"
+
 # config.toml が bin/ になければ OS 別デフォルトからコピー
 # （ユーザーが bin/config.toml を編集している場合は上書きしない）
 if [ ! -f "./bin/config.toml" ]; then
@@ -19,6 +19,7 @@ cp ./bin/muhenkan-switch "$STAGING/" 2>/dev/null || true
 
 # 設定ファイル
 cp config/default.toml "$STAGING/config.toml"
+cp config/config.schema.json "$STAGING/"
 cp kanata/muhenkan.kbd "$STAGING/"
 cp kanata/muhenkan-macos.kbd "$STAGING/" 2>/dev/null || true
 
@@ -83,6 +83,7 @@ copy_file() {
 copy_file "muhenkan-switch" "muhenkan-switch"
 copy_file "muhenkan-switch-core" "muhenkan-switch-core"
 copy_file "config.toml" "config.toml"
+copy_file "config.schema.json" "config.schema.json"
 copy_file "muhenkan-macos.kbd" "muhenkan-macos.kbd"
 copy_file "update-macos.sh" "update-macos.sh"
 copy_file "uninstall-macos.sh" "uninstall-macos.sh"